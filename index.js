@@ -1,8 +1,9 @@
 "use strict";
 
 import { createRequire } from 'node:module';
+import { Readable } from 'node:stream';
 const require = createRequire(import.meta.url);
-const { geofileOpen, geofileFind } = require('./index.node');
+const { geofileOpen, geofileFilter, geofileFind, geofileFindObjects, geofileFindBinary, geofileFindFeatureCollection, geofileFindFeatures, geofileGet, geofileGetMany, geofileAppend, geofileDelete, geofileUpdate, geofileCompact } = require('./index.node');
 
 export default class Geofile {
 	#me;
@@ -13,15 +14,100 @@ export default class Geofile {
 		this.#me = geofileOpen(filename, options);
 	}
 
-	* find(bbox) {
-		if ((!Array.isArray(bbox)) || (bbox.length !== 4)) throw Error('argument "bbox" must be an Array of 4 numbers');
+	* find(bbox, options = {}) {
+		const { where, from, to } = queryArguments(this.#me, bbox, options);
+		const { parse, properties } = options;
+		if ((properties !== undefined) && !(Array.isArray(properties) && properties.every(p => typeof p === 'string'))) {
+			throw Error('option "properties" must be an Array of strings');
+		}
+		if ((properties !== undefined) && !parse) throw Error('option "properties" requires option "parse"');
 		let index = 0;
 		const maxCount = 1000;
 
 		do {
-			let result = geofileFind.call(this.#me, bbox, index, maxCount);
+			let result;
+			if (parse) {
+				result = geofileFindObjects.call(this.#me, bbox, index, maxCount, where, from, to, properties);
+			} else {
+				result = geofileFind.call(this.#me, bbox, index, maxCount, where, from, to);
+			}
 			index = result.pop();
 			for (let entry of result) yield entry;
 		} while (index > 0);
 	}
+
+	* findBinary(bbox, options = {}) {
+		const { where, from, to } = queryArguments(this.#me, bbox, options);
+		const pageSize = options.pageSize ?? 10000;
+		if ((!Number.isInteger(pageSize)) || (pageSize < 1)) throw Error('option "pageSize" must be a positive integer');
+		let index = 0;
+
+		do {
+			let buffer, offsets;
+			[buffer, offsets, index] = geofileFindBinary.call(this.#me, bbox, index, pageSize, where, from, to);
+			if (buffer.length > 0) yield { buffer, offsets: new Uint32Array(offsets) };
+		} while (index > 0);
+	}
+
+	findAsFeatureCollection(bbox, options = {}) {
+		const { where, from, to } = queryArguments(this.#me, bbox, options);
+		return geofileFindFeatureCollection.call(this.#me, bbox, where, from, to);
+	}
+
+	findAsFeatureCollectionStream(bbox, options = {}) {
+		const { where, from, to } = queryArguments(this.#me, bbox, options);
+		const me = this.#me;
+		const maxCount = 1000;
+
+		return Readable.from((function* () {
+			yield Buffer.from('{"type":"FeatureCollection","features":[');
+			let index = 0;
+			let first = true;
+			do {
+				let features;
+				[features, index] = geofileFindFeatures.call(me, bbox, index, maxCount, where, from, to);
+				if (features.length === 0) continue;
+				if (!first) yield Buffer.from(',');
+				yield features;
+				first = false;
+			} while (index > 0);
+			yield Buffer.from(']}');
+		})(), { objectMode: false });
+	}
+
+	get(id) {
+		return geofileGet.call(this.#me, id);
+	}
+
+	getMany(ids) {
+		if (!Array.isArray(ids)) throw Error('argument "ids" must be an Array');
+		return geofileGetMany.call(this.#me, ids);
+	}
+
+	append(lines) {
+		if (!Array.isArray(lines)) throw Error('argument "lines" must be an Array of strings');
+		geofileAppend.call(this.#me, lines);
+	}
+
+	delete(id) {
+		return geofileDelete.call(this.#me, id);
+	}
+
+	update(id, line) {
+		geofileUpdate.call(this.#me, id, line);
+	}
+
+	compact() {
+		geofileCompact.call(this.#me);
+	}
+}
+
+// Check the bbox and the options of a query, converting Dates to milliseconds.
+// The where expression is parsed once into a filter shared by all pages.
+function queryArguments(me, bbox, options) {
+	if ((!Array.isArray(bbox)) || (bbox.length !== 4)) throw Error('argument "bbox" must be an Array of 4 numbers');
+	if ((options.where !== undefined) && (typeof options.where !== 'string')) throw Error('option "where" must be a string');
+	const time = value => (value instanceof Date) ? value.getTime() : value;
+	const where = (options.where === undefined) ? undefined : geofileFilter.call(me, options.where);
+	return { where, from: time(options.from), to: time(options.to) };
 }
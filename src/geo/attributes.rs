@@ -0,0 +1,140 @@
+use super::{
+	filter::{Field, RecordReader, Value},
+	index::{load_versioned, save_versioned},
+	GeoIndex, GeoTable,
+};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, error::Error, path::Path, result::Result, str::from_utf8};
+
+// Secondary indexes of a table: for each indexed property, the leaves of every value.
+// Leaves are node numbers in the tree, in ascending order, so they can be queried like the tree itself.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GeoAttributeIndex {
+	properties: Vec<String>,
+	values: Vec<BTreeMap<String, Vec<usize>>>,
+}
+impl GeoAttributeIndex {
+	// Read the values of the fields from all leaves, and save the index
+	pub fn create(
+		index: &GeoIndex, table: &GeoTable, reader: &RecordReader, properties: &[String], fields: &[Field],
+		filename_attributes: &Path,
+	) -> Result<Self, Box<dyn Error>> {
+		let data = table.get_data();
+		let leaves: Vec<(usize, usize, usize)> = index
+			.enumerate_leaves()
+			.map(|(node, leaf)| (node, leaf.value1, leaf.value2))
+			.collect();
+		let keys: Vec<Vec<Option<String>>> = leaves
+			.par_iter()
+			.map(|&(_, start, length)| {
+				let record = from_utf8(&data[start..start + length])
+					.ok()
+					.and_then(|line| reader.read(line));
+				fields
+					.iter()
+					.map(|field| record.as_ref().and_then(|record| record.key(field)))
+					.collect()
+			})
+			.collect();
+
+		let mut values: Vec<BTreeMap<String, Vec<usize>>> = vec![BTreeMap::new(); fields.len()];
+		for (&(node, _, _), keys) in leaves.iter().zip(keys) {
+			for (field_values, key) in values.iter_mut().zip(keys) {
+				if let Some(key) = key {
+					field_values.entry(key).or_default().push(node);
+				}
+			}
+		}
+
+		let attributes = GeoAttributeIndex {
+			properties: properties.to_vec(),
+			values,
+		};
+		save_versioned(&attributes, filename_attributes)?;
+		Ok(attributes)
+	}
+	// None if the file has another format version, see FORMAT_VERSION
+	pub fn load(filename_attributes: &Path) -> Result<Option<Self>, Box<dyn Error>> {
+		load_versioned(filename_attributes)
+	}
+	// The indexed properties, to detect a changed indexProperties option
+	pub fn properties(&self) -> &[String] {
+		&self.properties
+	}
+	// Leaves whose property has one of the values: a sorted list for each distinct value
+	pub fn get_leaves(&self, property: usize, values: &[&Value]) -> Vec<&[usize]> {
+		let mut keys: Vec<String> = values.iter().map(|value| value.key()).collect();
+		keys.sort_unstable();
+		keys.dedup();
+		keys
+			.iter()
+			.filter_map(|key| self.values[property].get(key))
+			.map(Vec::as_slice)
+			.collect()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::geo::{index::TreeType, GeoBBox, GeoFile, GeoFileOptions};
+	use assert_fs::TempDir;
+	use std::fs::write;
+
+	#[test]
+	fn test_create_and_query_attributes() -> Result<(), Box<dyn Error>> {
+		let dir = TempDir::new()?;
+		let filename = dir.path().join("points.csv");
+		write(
+			&filename,
+			"a;1;1;BW\nb;2;2;BY\nc;3;3;BW\nd;4;4;1000.0\ne;5;5;\nf;6;6;-0\n",
+		)?;
+		let options = GeoFileOptions::new(";", 1, 2, 0);
+		let mut geo_file = GeoFile::load(&filename, options.clone())?;
+		let filename_index = dir.path().join("points.idx");
+		let filename_table = dir.path().join("points.dat");
+		let filename_attributes = dir.path().join("points.attr");
+		let index = GeoIndex::create(&mut geo_file, TreeType::Str(2), &filename_index, &filename_table, None)?;
+		let table = GeoTable::load(&filename_table)?;
+		let reader = GeoFile::get_record_reader(&filename, &options);
+		let properties = vec![String::from("$3")];
+
+		GeoAttributeIndex::create(
+			&index,
+			&table,
+			&reader,
+			&properties,
+			&[Field::Column(3)],
+			&filename_attributes,
+		)?;
+		let attributes = GeoAttributeIndex::load(&filename_attributes)?.unwrap();
+		assert_eq!(attributes.properties(), properties);
+
+		let names = |leaves: Vec<&[usize]>| -> Vec<String> {
+			let bbox = GeoBBox::new(0., 10., 0., 10.);
+			let (leaves, _) = index.query_leaves(&bbox, None, &leaves, 0, 10);
+			let mut names: Vec<String> = table
+				.read_ranges(leaves)
+				.iter()
+				.map(|line| String::from_utf8_lossy(&line[..1]).to_string())
+				.collect();
+			names.sort();
+			names
+		};
+		let bw = Value::String(String::from("BW"));
+		let by = Value::String(String::from("BY"));
+		assert_eq!(names(attributes.get_leaves(0, &[&bw])), vec!["a", "c"]);
+		assert_eq!(names(attributes.get_leaves(0, &[&bw, &by, &bw])), vec!["a", "b", "c"]);
+		assert_eq!(names(attributes.get_leaves(0, &[&Value::Number(1000.)])), vec!["d"]);
+		assert!(attributes.get_leaves(0, &[&Value::String(String::new())]).is_empty());
+		// -0 and 0 are equal, like in the line filter
+		assert_eq!(names(attributes.get_leaves(0, &[&Value::Number(0.)])), vec!["f"]);
+		assert_eq!(names(attributes.get_leaves(0, &[&Value::Number(-0.)])), vec!["f"]);
+
+		// attribute indexes of older versions are rebuilt
+		write(&filename_attributes, bincode::serialize(&attributes)?)?;
+		assert!(GeoAttributeIndex::load(&filename_attributes)?.is_none());
+		Ok(())
+	}
+}
@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 
-// GeoBBox struct representing a geographical bounding box with min and max coordinates
+/// GeoBBox struct representing a geographical bounding box with min and max coordinates
 #[derive(Clone, Serialize, Deserialize, PartialEq, Debug)]
 pub struct GeoBBox {
 	x_min: f32, // minimum x-coordinate
@@ -11,7 +11,7 @@ pub struct GeoBBox {
 
 // Implementation of methods for GeoBBox struct
 impl GeoBBox {
-	// Create a new GeoBBox given min and max coordinates
+	/// Create a new GeoBBox given min and max coordinates
 	pub fn new(x_min: f32, x_max: f32, y_min: f32, y_max: f32) -> Self {
 		GeoBBox {
 			x_min,
@@ -20,16 +20,25 @@ impl GeoBBox {
 			y_max,
 		}
 	}
-	// Create a new GeoBBox from a point
+	/// Create a new GeoBBox from a point
 	pub fn new_point(x: f32, y: f32) -> Self {
+		GeoBBox::new(x, x, y, y)
+	}
+	/// Create a new GeoBBox from f64 coordinates, rounding outwards to the enclosing f32 values,
+	/// so that the box never shrinks and bbox queries are never falsely negative
+	pub fn from_f64(x_min: f64, x_max: f64, y_min: f64, y_max: f64) -> Self {
 		GeoBBox {
-			x_min: x,
-			x_max: x,
-			y_min: y,
-			y_max: y,
+			x_min: round_down(x_min),
+			x_max: round_up(x_max),
+			y_min: round_down(y_min),
+			y_max: round_up(y_max),
 		}
 	}
-	// Create an "empty" GeoBBox with extreme values
+	/// Create a new GeoBBox from a f64 point, rounding outwards
+	pub fn from_f64_point(x: f64, y: f64) -> Self {
+		GeoBBox::from_f64(x, x, y, y)
+	}
+	/// Create an "empty" GeoBBox with extreme values
 	pub fn new_empty() -> Self {
 		GeoBBox {
 			x_min: f32::MAX,
@@ -38,22 +47,11 @@ impl GeoBBox {
 			y_max: f32::MIN,
 		}
 	}
-	// Include a point into the GeoBBox, potentially extending it
+	/// Include a point into the GeoBBox, potentially extending it
 	pub fn include_point(&mut self, x: f32, y: f32) {
-		if self.x_min > x {
-			self.x_min = x
-		}
-		if self.x_max < x {
-			self.x_max = x
-		}
-		if self.y_min > y {
-			self.y_min = y
-		}
-		if self.y_max < y {
-			self.y_max = y
-		}
+		self.include_bbox(&GeoBBox::new_point(x, y));
 	}
-	// Expand current GeoBBox to include another GeoBBox
+	/// Expand current GeoBBox to include another GeoBBox
 	pub fn include_bbox(&mut self, bbox: &GeoBBox) {
 		if self.x_min > bbox.x_min {
 			self.x_min = bbox.x_min
@@ -68,12 +66,46 @@ impl GeoBBox {
 			self.y_max = bbox.y_max
 		}
 	}
-	// Check if GeoBBox is wider than high
+	/// Check if GeoBBox is wider than high
 	pub fn is_horizontal(&self) -> bool {
-		(self.x_max - self.x_min) > (self.y_max - self.y_min)
+		self.width() > (self.y_max - self.y_min)
+	}
+	/// Compute the width, not taking the antimeridian into account
+	pub fn width(&self) -> f32 {
+		self.x_max - self.x_min
 	}
-	// Check if current GeoBBox overlaps with another GeoBBox
+	/// Check if GeoBBox crosses the antimeridian, i.e. x_min is east of x_max (as in RFC 7946)
+	pub fn is_wrapped(&self) -> bool {
+		self.x_min > self.x_max && self.x_min <= 180.0 && self.x_max >= -180.0
+	}
+	/// Split a wrapped GeoBBox into an eastern part ending at 180° and a western part starting at -180°
+	pub fn split_antimeridian(&self) -> Vec<GeoBBox> {
+		if !self.is_wrapped() {
+			return vec![self.clone()];
+		}
+		vec![
+			GeoBBox::new(self.x_min, 180.0, self.y_min, self.y_max),
+			GeoBBox::new(-180.0, self.x_max, self.y_min, self.y_max),
+		]
+	}
+	/// Map longitudes that were shifted into the range 0..360 back into -180..180.
+	/// A GeoBBox covering 180° becomes wrapped.
+	pub fn unshift_longitudes(&self) -> GeoBBox {
+		// exact in f32, since x is between 180 and 720
+		let unshift = |x: f32| if x > 180.0 { x - 360.0 } else { x };
+		GeoBBox::new(unshift(self.x_min), unshift(self.x_max), self.y_min, self.y_max)
+	}
+	/// Check if current GeoBBox overlaps with another GeoBBox. Wrapped GeoBBoxes are split at the antimeridian.
 	pub fn overlap_bbox(&self, bbox: &GeoBBox) -> bool {
+		if self.is_wrapped() || bbox.is_wrapped() {
+			return self
+				.split_antimeridian()
+				.iter()
+				.any(|a| bbox.split_antimeridian().iter().any(|b| a.overlap_unwrapped(b)));
+		}
+		self.overlap_unwrapped(bbox)
+	}
+	fn overlap_unwrapped(&self, bbox: &GeoBBox) -> bool {
 		if self.x_min > bbox.x_max {
 			return false;
 		}
@@ -88,17 +120,50 @@ impl GeoBBox {
 		}
 		true
 	}
-	// Compute the sum of x coordinates
+	/// Check if another GeoBBox lies completely within this one, not taking the antimeridian into account
+	pub fn contains_bbox(&self, bbox: &GeoBBox) -> bool {
+		self.x_min <= bbox.x_min && self.x_max >= bbox.x_max && self.y_min <= bbox.y_min && self.y_max >= bbox.y_max
+	}
+	/// The coordinates in GeoJSON order: x_min, y_min, x_max, y_max
+	pub fn to_array(&self) -> [f32; 4] {
+		[self.x_min, self.y_min, self.x_max, self.y_max]
+	}
+	/// Compute the center point
+	pub fn center(&self) -> (f32, f32) {
+		(self.sum_x() / 2.0, self.sum_y() / 2.0)
+	}
+	/// Compute the sum of x coordinates
 	pub fn sum_x(&self) -> f32 {
 		self.x_min + self.x_max
 	}
-	// Compute the sum of y coordinates
+	/// Compute the sum of y coordinates
 	pub fn sum_y(&self) -> f32 {
 		self.y_min + self.y_max
 	}
 }
 
+// Largest f32 that is not greater than the value
+fn round_down(value: f64) -> f32 {
+	let rounded = value as f32;
+	if rounded as f64 > value {
+		rounded.next_down()
+	} else {
+		rounded
+	}
+}
+
+// Smallest f32 that is not less than the value
+fn round_up(value: f64) -> f32 {
+	let rounded = value as f32;
+	if (rounded as f64) < value {
+		rounded.next_up()
+	} else {
+		rounded
+	}
+}
+
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
 mod tests {
 	use super::*;
 
@@ -120,6 +185,37 @@ mod tests {
 		assert_eq!(bbox.y_max, 2.0);
 	}
 
+	#[test]
+	fn test_from_f64_point() {
+		let bbox = GeoBBox::from_f64_point(1.0, 2.0);
+		assert_eq!(bbox.x_min, 1.0);
+		assert_eq!(bbox.x_max, 1.0);
+		assert_eq!(bbox.y_min, 2.0);
+		assert_eq!(bbox.y_max, 2.0);
+	}
+
+	#[test]
+	fn test_from_f64() {
+		let bbox = GeoBBox::from_f64(1.0, 2.0, -3.5, 4.25);
+		assert_eq!(bbox, GeoBBox::new(1.0, 2.0, -3.5, 4.25));
+
+		for value in [8.709154129, -8.709154129, 5428123.456, 0.1, -1e-30] {
+			let bbox = GeoBBox::from_f64_point(value, value);
+			assert!((bbox.x_min as f64) < value && value < (bbox.x_max as f64));
+			assert!((bbox.y_min as f64) < value && value < (bbox.y_max as f64));
+			assert_eq!(bbox.x_min.next_up(), bbox.x_max);
+		}
+	}
+
+	#[test]
+	fn test_from_f64_overlap() {
+		// a feature and a query sharing an edge that isn't representable as f32 must still overlap
+		let edge = 5428123.456;
+		let feature = GeoBBox::from_f64(edge - 10.0, edge, 0.0, 1.0);
+		let query = GeoBBox::from_f64(edge, edge + 10.0, 0.0, 1.0);
+		assert!(feature.overlap_bbox(&query));
+	}
+
 	#[test]
 	fn test_new_empty() {
 		let bbox = GeoBBox::new_empty();
@@ -163,6 +259,54 @@ mod tests {
 		assert_eq!(bbox.overlap_bbox(&bbox2), true);
 	}
 
+	#[test]
+	fn test_wrapped() {
+		let pacific = GeoBBox::new(170.0, -170.0, -20.0, 20.0);
+		assert!(pacific.is_wrapped());
+		assert!(!GeoBBox::new_empty().is_wrapped());
+		assert!(!GeoBBox::new(-170.0, 170.0, -20.0, 20.0).is_wrapped());
+		assert_eq!(
+			pacific.split_antimeridian(),
+			vec![
+				GeoBBox::new(170.0, 180.0, -20.0, 20.0),
+				GeoBBox::new(-180.0, -170.0, -20.0, 20.0)
+			]
+		);
+
+		assert!(pacific.overlap_bbox(&GeoBBox::new(175.0, 176.0, 0.0, 1.0)));
+		assert!(pacific.overlap_bbox(&GeoBBox::new(-176.0, -175.0, 0.0, 1.0)));
+		assert!(GeoBBox::new(-176.0, -175.0, 0.0, 1.0).overlap_bbox(&pacific));
+		assert!(!pacific.overlap_bbox(&GeoBBox::new(0.0, 1.0, 0.0, 1.0)));
+		assert!(pacific.overlap_bbox(&GeoBBox::new(179.0, -179.0, 0.0, 1.0)));
+		assert!(!GeoBBox::new_empty().overlap_bbox(&pacific));
+	}
+
+	#[test]
+	fn test_unshift_longitudes() {
+		assert_eq!(
+			GeoBBox::new(170.0, 190.0, 0.0, 1.0).unshift_longitudes(),
+			GeoBBox::new(170.0, -170.0, 0.0, 1.0)
+		);
+		assert_eq!(
+			GeoBBox::new(10.0, 20.0, 0.0, 1.0).unshift_longitudes(),
+			GeoBBox::new(10.0, 20.0, 0.0, 1.0)
+		);
+	}
+
+	#[test]
+	fn test_contains_bbox() {
+		let bbox = GeoBBox::new(1.0, 3.0, 1.0, 3.0);
+		assert!(bbox.contains_bbox(&GeoBBox::new(1.0, 2.0, 2.0, 3.0)));
+		assert!(bbox.contains_bbox(&bbox));
+		assert!(!bbox.contains_bbox(&GeoBBox::new(2.0, 4.0, 2.0, 3.0)));
+		assert_eq!(bbox.to_array(), [1.0, 1.0, 3.0, 3.0]);
+	}
+
+	#[test]
+	fn test_center() {
+		assert_eq!(GeoBBox::new(1.0, 2.0, 3.0, 5.0).center(), (1.5, 4.0));
+	}
+
 	#[test]
 	fn test_sum_x() {
 		let bbox = GeoBBox::new(1.0, 2.0, 1.0, 2.0);
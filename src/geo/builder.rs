@@ -0,0 +1,121 @@
+use super::{GeoDB, GeoFileOptions, Progress};
+use std::{
+	error::Error,
+	path::{Path, PathBuf},
+	result::Result,
+};
+
+/// Sets the options of a [`GeoDB`] and opens it. Created by [`GeoDB::builder`].
+///
+/// ```no_run
+/// use geojson_db::GeoDB;
+///
+/// let geo_db = GeoDB::builder("features.csv.gz")
+///     .separator(";")
+///     .col_x(3)
+///     .col_y(4)
+///     .skip_lines(1)
+///     .index_type("hilbert")
+///     .open()?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub struct GeoDBBuilder<'a> {
+	filename: PathBuf,
+	opt: GeoFileOptions,
+	progress: Option<&'a dyn Progress>,
+}
+
+impl GeoDBBuilder<'static> {
+	pub(crate) fn new(filename: &Path) -> Self {
+		GeoDBBuilder {
+			filename: filename.to_path_buf(),
+			opt: GeoFileOptions::empty(),
+			progress: None,
+		}
+	}
+}
+
+impl<'a> GeoDBBuilder<'a> {
+	/// Field separator of CSV/TSV files, "," or "\t" by default
+	pub fn separator(mut self, separator: &str) -> Self {
+		self.opt.separator = Some(separator.to_string());
+		self
+	}
+	/// Column of x values in CSV/TSV files, 0 by default
+	pub fn col_x(mut self, col_x: usize) -> Self {
+		self.opt.col_x = Some(col_x);
+		self
+	}
+	/// Column of y values in CSV/TSV files, 1 by default
+	pub fn col_y(mut self, col_y: usize) -> Self {
+		self.opt.col_y = Some(col_y);
+		self
+	}
+	/// Number of lines to skip, e.g. a CSV header line, which names the columns in filters
+	pub fn skip_lines(mut self, skip_lines: usize) -> Self {
+		self.opt.skip_lines = Some(skip_lines);
+		self
+	}
+	/// Name of the GeoPackage layer or TopoJSON object
+	pub fn layer(mut self, layer: &str) -> Self {
+		self.opt.layer = Some(layer.to_string());
+		self
+	}
+	/// CRS of the input coordinates, e.g. "EPSG:25832"
+	pub fn source_crs(mut self, source_crs: &str) -> Self {
+		self.opt.source_crs = Some(source_crs.to_string());
+		self
+	}
+	/// CRS of the index and of bbox queries
+	pub fn target_crs(mut self, target_crs: &str) -> Self {
+		self.opt.target_crs = Some(target_crs.to_string());
+		self
+	}
+	/// Also reproject the coordinates of the returned lines
+	pub fn rewrite_geometries(mut self, rewrite_geometries: bool) -> Self {
+		self.opt.rewrite_geometries = Some(rewrite_geometries);
+		self
+	}
+	/// "binary", "str" or "hilbert"
+	pub fn index_type(mut self, index_type: &str) -> Self {
+		self.opt.index_type = Some(index_type.to_string());
+		self
+	}
+	/// Number of children per node of "str" and "hilbert" trees
+	pub fn node_size(mut self, node_size: usize) -> Self {
+		self.opt.node_size = Some(node_size);
+		self
+	}
+	/// Column of feature ids in CSV/TSV files
+	pub fn col_id(mut self, col_id: usize) -> Self {
+		self.opt.col_id = Some(col_id);
+		self
+	}
+	/// Property with the feature id in GeoJSON, instead of the "id" member
+	pub fn id_property(mut self, id_property: &str) -> Self {
+		self.opt.id_property = Some(id_property.to_string());
+		self
+	}
+	/// Properties or CSV columns with an attribute index for filters
+	pub fn index_properties(mut self, index_properties: &[&str]) -> Self {
+		self.opt.index_properties = Some(index_properties.iter().map(|p| p.to_string()).collect());
+		self
+	}
+	/// Property or CSV column with the time of features
+	pub fn time_property(mut self, time_property: &str) -> Self {
+		self.opt.time_property = Some(time_property.to_string());
+		self
+	}
+	/// Receiver of progress events while the index is created
+	pub fn progress<'b>(self, progress: &'b dyn Progress) -> GeoDBBuilder<'b> {
+		GeoDBBuilder {
+			filename: self.filename,
+			opt: self.opt,
+			progress: Some(progress),
+		}
+	}
+	/// Open the file, creating its index if needed
+	pub fn open(self) -> Result<GeoDB, Box<dyn Error>> {
+		GeoDB::open(&self.filename, self.opt, self.progress)
+	}
+}
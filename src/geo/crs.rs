@@ -0,0 +1,492 @@
+use std::{
+	error::Error,
+	f64::consts::{FRAC_PI_2, FRAC_PI_4},
+	io::{Error as IoError, ErrorKind},
+	result::Result,
+};
+
+// Reference ellipsoid, given by semi-major axis and flattening
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Ellipsoid {
+	a: f64,
+	f: f64,
+}
+
+const WGS84: Ellipsoid = Ellipsoid {
+	a: 6378137.0,
+	f: 1.0 / 298.257223563,
+};
+const GRS80: Ellipsoid = Ellipsoid {
+	a: 6378137.0,
+	f: 1.0 / 298.257222101,
+};
+const BESSEL: Ellipsoid = Ellipsoid {
+	a: 6377397.155,
+	f: 1.0 / 299.1528128,
+};
+
+// 7-parameter Helmert transformation to WGS84 (position vector convention, like PROJ's "towgs84"):
+// translation in metres, rotation in arc seconds, scale in ppm
+type Helmert = [f64; 7];
+
+// DHDN to WGS84, as used for Gauss-Krüger coordinates in Germany
+const DHDN_TO_WGS84: Helmert = [598.1, 73.7, 418.2, 0.202, 0.045, -2.455, 6.7];
+
+// Geodetic datum: ellipsoid and an optional shift to WGS84. ETRS89 is treated as identical to WGS84.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Datum {
+	ellipsoid: Ellipsoid,
+	to_wgs84: Option<Helmert>,
+}
+
+const DATUM_WGS84: Datum = Datum {
+	ellipsoid: WGS84,
+	to_wgs84: None,
+};
+const DATUM_ETRS89: Datum = Datum {
+	ellipsoid: GRS80,
+	to_wgs84: None,
+};
+const DATUM_DHDN: Datum = Datum {
+	ellipsoid: BESSEL,
+	to_wgs84: Some(DHDN_TO_WGS84),
+};
+
+#[derive(Clone, Debug, PartialEq)]
+enum Projection {
+	// longitude/latitude in degrees
+	Geographic,
+	// spherical "Web Mercator" (EPSG:3857)
+	WebMercator,
+	TransverseMercator {
+		lon0: f64,
+		lat0: f64,
+		k0: f64,
+		false_easting: f64,
+		false_northing: f64,
+	},
+}
+
+// A coordinate reference system, identified by an EPSG code
+#[derive(Clone, Debug, PartialEq)]
+pub struct Crs {
+	datum: Datum,
+	projection: Projection,
+}
+
+impl Crs {
+	// Parse "EPSG:25832", "epsg:25832" or "25832". Only a built-in set of common codes is supported.
+	pub fn from_code(code: &str) -> Result<Self, Box<dyn Error>> {
+		let epsg = parse_epsg(code)?;
+
+		let utm = |datum: Datum, zone: u32, south: bool| Crs {
+			datum,
+			projection: Projection::TransverseMercator {
+				lon0: zone as f64 * 6.0 - 183.0,
+				lat0: 0.0,
+				k0: 0.9996,
+				false_easting: 500000.0,
+				false_northing: if south { 10000000.0 } else { 0.0 },
+			},
+		};
+
+		Ok(match epsg {
+			4326 => Crs {
+				datum: DATUM_WGS84,
+				projection: Projection::Geographic,
+			},
+			4258 => Crs {
+				datum: DATUM_ETRS89,
+				projection: Projection::Geographic,
+			},
+			4314 => Crs {
+				datum: DATUM_DHDN,
+				projection: Projection::Geographic,
+			},
+			3857 | 900913 => Crs {
+				datum: DATUM_WGS84,
+				projection: Projection::WebMercator,
+			},
+			25828..=25838 => utm(DATUM_ETRS89, epsg - 25800, false),
+			32601..=32660 => utm(DATUM_WGS84, epsg - 32600, false),
+			32701..=32760 => utm(DATUM_WGS84, epsg - 32700, true),
+			// Gauss-Krüger zones 2 to 5
+			31466..=31469 => {
+				let zone = (epsg - 31464) as f64;
+				Crs {
+					datum: DATUM_DHDN,
+					projection: Projection::TransverseMercator {
+						lon0: zone * 3.0,
+						lat0: 0.0,
+						k0: 1.0,
+						false_easting: zone * 1000000.0 + 500000.0,
+						false_northing: 0.0,
+					},
+				}
+			}
+			_ => return Err(unsupported(code)),
+		})
+	}
+
+	// Check if coordinates are longitude/latitude in degrees
+	pub fn is_geographic(&self) -> bool {
+		self.projection == Projection::Geographic
+	}
+
+	// Convert projected coordinates into longitude/latitude in degrees on this CRS's datum
+	fn unproject(&self, x: f64, y: f64) -> (f64, f64) {
+		match &self.projection {
+			Projection::Geographic => (x, y),
+			Projection::WebMercator => (
+				(x / WGS84.a).to_degrees(),
+				(2.0 * (y / WGS84.a).exp().atan() - FRAC_PI_2).to_degrees(),
+			),
+			Projection::TransverseMercator {
+				lon0,
+				lat0,
+				k0,
+				false_easting,
+				false_northing,
+			} => {
+				let tm = KruegerSeries::new(self.datum.ellipsoid);
+				let xi = (y - false_northing) / (k0 * tm.a) + tm.forward(lat0.to_radians(), 0.0).0;
+				let eta = (x - false_easting) / (k0 * tm.a);
+				let (lat, lon) = tm.inverse(xi, eta);
+				(lon.to_degrees() + lon0, lat.to_degrees())
+			}
+		}
+	}
+
+	// Convert longitude/latitude in degrees on this CRS's datum into projected coordinates
+	fn project(&self, lon: f64, lat: f64) -> (f64, f64) {
+		match &self.projection {
+			Projection::Geographic => (lon, lat),
+			Projection::WebMercator => (
+				WGS84.a * lon.to_radians(),
+				WGS84.a * (FRAC_PI_4 + lat.to_radians() / 2.0).tan().ln(),
+			),
+			Projection::TransverseMercator {
+				lon0,
+				lat0,
+				k0,
+				false_easting,
+				false_northing,
+			} => {
+				let tm = KruegerSeries::new(self.datum.ellipsoid);
+				let (xi, eta) = tm.forward(lat.to_radians(), (lon - lon0).to_radians());
+				let xi0 = tm.forward(lat0.to_radians(), 0.0).0;
+				(false_easting + k0 * tm.a * eta, false_northing + k0 * tm.a * (xi - xi0))
+			}
+		}
+	}
+}
+
+// Transformation of coordinates between two CRSs
+#[derive(Clone, Debug)]
+pub struct Transform {
+	source: Crs,
+	target: Crs,
+}
+
+impl Transform {
+	pub fn new(source: &str, target: &str) -> Result<Self, Box<dyn Error>> {
+		Ok(Self {
+			source: Crs::from_code(source)?,
+			target: Crs::from_code(target)?,
+		})
+	}
+
+	pub fn apply(&self, x: f64, y: f64) -> (f64, f64) {
+		if self.source == self.target {
+			return (x, y);
+		}
+		let (mut lon, mut lat) = self.source.unproject(x, y);
+		if self.source.datum != self.target.datum {
+			(lon, lat) = shift_datum(lon, lat, &self.source.datum, &self.target.datum);
+		}
+		self.target.project(lon, lat)
+	}
+}
+
+// Coefficients of the Krüger series for the ellipsoidal transverse Mercator projection
+struct KruegerSeries {
+	a: f64,
+	e: f64,
+	alpha: [f64; 4],
+	beta: [f64; 4],
+	delta: [f64; 4],
+}
+
+impl KruegerSeries {
+	fn new(ellipsoid: Ellipsoid) -> Self {
+		let n = ellipsoid.f / (2.0 - ellipsoid.f);
+		let (n2, n3, n4) = (n * n, n * n * n, n * n * n * n);
+		Self {
+			a: ellipsoid.a / (1.0 + n) * (1.0 + n2 / 4.0 + n4 / 64.0),
+			e: 2.0 * n.sqrt() / (1.0 + n),
+			alpha: [
+				n / 2.0 - 2.0 * n2 / 3.0 + 5.0 * n3 / 16.0 + 41.0 * n4 / 180.0,
+				13.0 * n2 / 48.0 - 3.0 * n3 / 5.0 + 557.0 * n4 / 1440.0,
+				61.0 * n3 / 240.0 - 103.0 * n4 / 140.0,
+				49561.0 * n4 / 161280.0,
+			],
+			beta: [
+				n / 2.0 - 2.0 * n2 / 3.0 + 37.0 * n3 / 96.0 - n4 / 360.0,
+				n2 / 48.0 + n3 / 15.0 - 437.0 * n4 / 1440.0,
+				17.0 * n3 / 480.0 - 37.0 * n4 / 840.0,
+				4397.0 * n4 / 161280.0,
+			],
+			delta: [
+				2.0 * n - 2.0 * n2 / 3.0 - 2.0 * n3 + 116.0 * n4 / 45.0,
+				7.0 * n2 / 3.0 - 8.0 * n3 / 5.0 - 227.0 * n4 / 45.0,
+				56.0 * n3 / 15.0 - 136.0 * n4 / 35.0,
+				4279.0 * n4 / 630.0,
+			],
+		}
+	}
+
+	// Forward projection of latitude and longitude difference (radians) to normalised (xi, eta)
+	fn forward(&self, lat: f64, dlon: f64) -> (f64, f64) {
+		let t = (lat.sin().atanh() - self.e * (self.e * lat.sin()).atanh()).sinh();
+		let xi1 = t.atan2(dlon.cos());
+		let eta1 = (dlon.sin() / (1.0 + t * t).sqrt()).atanh();
+		let mut xi = xi1;
+		let mut eta = eta1;
+		for (j, alpha) in self.alpha.iter().enumerate() {
+			let k = 2.0 * (j + 1) as f64;
+			xi += alpha * (k * xi1).sin() * (k * eta1).cosh();
+			eta += alpha * (k * xi1).cos() * (k * eta1).sinh();
+		}
+		(xi, eta)
+	}
+
+	// Inverse projection of normalised (xi, eta) to latitude and longitude difference (radians)
+	fn inverse(&self, xi: f64, eta: f64) -> (f64, f64) {
+		let mut xi1 = xi;
+		let mut eta1 = eta;
+		for (j, beta) in self.beta.iter().enumerate() {
+			let k = 2.0 * (j + 1) as f64;
+			xi1 -= beta * (k * xi).sin() * (k * eta).cosh();
+			eta1 -= beta * (k * xi).cos() * (k * eta).sinh();
+		}
+		let chi = (xi1.sin() / eta1.cosh()).asin();
+		let mut lat = chi;
+		for (j, delta) in self.delta.iter().enumerate() {
+			lat += delta * (2.0 * (j + 1) as f64 * chi).sin();
+		}
+		(lat, eta1.sinh().atan2(xi1.cos()))
+	}
+}
+
+// Move longitude/latitude (degrees) from one datum to another via WGS84 geocentric coordinates
+fn shift_datum(lon: f64, lat: f64, source: &Datum, target: &Datum) -> (f64, f64) {
+	let mut xyz = to_geocentric(lon, lat, &source.ellipsoid);
+	if let Some(helmert) = &source.to_wgs84 {
+		xyz = apply_helmert(xyz, helmert);
+	}
+	if let Some(helmert) = &target.to_wgs84 {
+		xyz = invert_helmert(xyz, helmert);
+	}
+	from_geocentric(xyz, &target.ellipsoid)
+}
+
+fn apply_helmert([x, y, z]: [f64; 3], helmert: &Helmert) -> [f64; 3] {
+	let (rx, ry, rz, scale) = helmert_factors(helmert);
+	[
+		helmert[0] + scale * (x - rz * y + ry * z),
+		helmert[1] + scale * (rz * x + y - rx * z),
+		helmert[2] + scale * (-ry * x + rx * y + z),
+	]
+}
+
+// Exact inverse of apply_helmert: remove translation and scale, then rotate back with the transposed matrix
+fn invert_helmert([x, y, z]: [f64; 3], helmert: &Helmert) -> [f64; 3] {
+	let (rx, ry, rz, scale) = helmert_factors(helmert);
+	let (x, y, z) = (
+		(x - helmert[0]) / scale,
+		(y - helmert[1]) / scale,
+		(z - helmert[2]) / scale,
+	);
+	let [[a, b, c], [d, e, f], [g, h, i]] = [[1.0, -rz, ry], [rz, 1.0, -rx], [-ry, rx, 1.0]];
+	let det = a * (e * i - f * h) - b * (d * i - f * g) + c * (d * h - e * g);
+	[
+		((e * i - f * h) * x + (c * h - b * i) * y + (b * f - c * e) * z) / det,
+		((f * g - d * i) * x + (a * i - c * g) * y + (c * d - a * f) * z) / det,
+		((d * h - e * g) * x + (b * g - a * h) * y + (a * e - b * d) * z) / det,
+	]
+}
+
+// Rotations in radians and the scale factor of a Helmert transformation
+fn helmert_factors(helmert: &Helmert) -> (f64, f64, f64, f64) {
+	let arc_second = (1.0f64 / 3600.0).to_radians();
+	(
+		helmert[3] * arc_second,
+		helmert[4] * arc_second,
+		helmert[5] * arc_second,
+		1.0 + helmert[6] * 1e-6,
+	)
+}
+
+fn to_geocentric(lon: f64, lat: f64, ellipsoid: &Ellipsoid) -> [f64; 3] {
+	let (lon, lat) = (lon.to_radians(), lat.to_radians());
+	let e2 = ellipsoid.f * (2.0 - ellipsoid.f);
+	let n = ellipsoid.a / (1.0 - e2 * lat.sin().powi(2)).sqrt();
+	[
+		n * lat.cos() * lon.cos(),
+		n * lat.cos() * lon.sin(),
+		n * (1.0 - e2) * lat.sin(),
+	]
+}
+
+fn from_geocentric([x, y, z]: [f64; 3], ellipsoid: &Ellipsoid) -> (f64, f64) {
+	let e2 = ellipsoid.f * (2.0 - ellipsoid.f);
+	let p = (x * x + y * y).sqrt();
+	let mut lat = z.atan2(p * (1.0 - e2));
+	for _ in 0..5 {
+		let n = ellipsoid.a / (1.0 - e2 * lat.sin().powi(2)).sqrt();
+		let h = p / lat.cos() - n;
+		lat = z.atan2(p * (1.0 - e2 * n / (n + h)));
+	}
+	(y.atan2(x).to_degrees(), lat.to_degrees())
+}
+
+// The number of an EPSG code like "EPSG:25832", "epsg:25832" or "25832"
+pub fn parse_epsg(code: &str) -> Result<u32, Box<dyn Error>> {
+	let trimmed = code.trim();
+	let number = trimmed
+		.strip_prefix("EPSG:")
+		.or_else(|| trimmed.strip_prefix("epsg:"))
+		.unwrap_or(trimmed);
+	number.parse().map_err(|_| unsupported(code))
+}
+
+fn unsupported(code: &str) -> Box<dyn Error> {
+	Box::new(IoError::new(
+		ErrorKind::InvalidInput,
+		format!("Unsupported coordinate reference system: {}", code),
+	))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn assert_close((x1, y1): (f64, f64), (x2, y2): (f64, f64), tolerance: f64) {
+		assert!(
+			(x1 - x2).abs() < tolerance && (y1 - y2).abs() < tolerance,
+			"({}, {}) != ({}, {})",
+			x1,
+			y1,
+			x2,
+			y2
+		);
+	}
+
+	#[test]
+	fn test_from_code() {
+		assert!(Crs::from_code("EPSG:25832").is_ok());
+		assert!(Crs::from_code("epsg:4326").is_ok());
+		assert!(Crs::from_code("31467").is_ok());
+		assert!(Crs::from_code("EPSG:2056").is_err());
+		assert!(Crs::from_code("WGS84").is_err());
+	}
+
+	// Example from EPSG Guidance Note 7-2: British National Grid
+	#[test]
+	fn test_transverse_mercator() {
+		let crs = Crs {
+			datum: Datum {
+				ellipsoid: Ellipsoid {
+					a: 6377563.396,
+					f: 1.0 / 299.3249646,
+				},
+				to_wgs84: None,
+			},
+			projection: Projection::TransverseMercator {
+				lon0: -2.0,
+				lat0: 49.0,
+				k0: 0.9996012717,
+				false_easting: 400000.0,
+				false_northing: -100000.0,
+			},
+		};
+		assert_close(crs.project(0.5, 50.5), (577274.99, 69740.50), 0.01);
+		assert_close(crs.unproject(577274.99, 69740.50), (0.5, 50.5), 1e-7);
+	}
+
+	// Example from EPSG Guidance Note 7-2: Popular Visualisation Pseudo Mercator
+	#[test]
+	fn test_web_mercator() -> Result<(), Box<dyn Error>> {
+		let transform = Transform::new("EPSG:4326", "EPSG:3857")?;
+		let lat = 24.0 + 22.0 / 60.0 + 54.433 / 3600.0;
+		assert_close(transform.apply(-100.33333333, lat), (-11169055.58, 2800000.00), 0.01);
+		let transform = Transform::new("EPSG:3857", "EPSG:4326")?;
+		assert_close(transform.apply(-11169055.58, 2800000.00), (-100.33333333, lat), 1e-7);
+		Ok(())
+	}
+
+	#[test]
+	fn test_utm() -> Result<(), Box<dyn Error>> {
+		let transform = Transform::new("EPSG:4326", "EPSG:32632")?;
+		assert_close(transform.apply(9.0, 0.0), (500000.0, 0.0), 1e-6);
+
+		let transform = Transform::new("EPSG:4326", "EPSG:32733")?;
+		assert_close(transform.apply(15.0, 0.0), (500000.0, 10000000.0), 1e-6);
+
+		// on the central meridian, northing is the scaled meridian arc, here integrated numerically
+		let forward = Transform::new("EPSG:4326", "EPSG:25832")?;
+		let backward = Transform::new("EPSG:25832", "EPSG:4326")?;
+		assert_close(forward.apply(9.0, 48.78), (500000.0, 5402999.894), 0.01);
+		let (x, y) = forward.apply(9.18, 48.78);
+		assert_close(backward.apply(x, y), (9.18, 48.78), 1e-8);
+		Ok(())
+	}
+
+	#[test]
+	fn test_gauss_krueger() -> Result<(), Box<dyn Error>> {
+		// the meridian arc of the Bessel ellipsoid, integrated numerically
+		let transform = Transform::new("EPSG:4314", "EPSG:31467")?;
+		assert_close(transform.apply(9.0, 48.78), (3500000.0, 5404609.821), 0.01);
+
+		let forward = Transform::new("EPSG:4326", "EPSG:31467")?;
+		let backward = Transform::new("EPSG:31467", "EPSG:4326")?;
+		let (x, y) = forward.apply(9.18, 48.78);
+		assert_close(backward.apply(x, y), (9.18, 48.78), 1e-8);
+
+		// the datum shift between DHDN and WGS84 is roughly 100 m in Germany
+		let utm = Transform::new("EPSG:4326", "EPSG:25832")?.apply(9.18, 48.78);
+		let shift = Transform::new("EPSG:4314", "EPSG:4326")?.apply(9.18, 48.78);
+		assert!((x - 3000000.0 - utm.0).abs() > 1.0);
+		assert!((shift.0 - 9.18).abs() < 0.01 && (shift.1 - 48.78).abs() < 0.01);
+		assert!((shift.0 - 9.18).abs() > 1e-5 || (shift.1 - 48.78).abs() > 1e-5);
+		Ok(())
+	}
+	// Example from EPSG Guidance Note 7-2: position vector transformation from WGS72 to WGS84
+	#[test]
+	fn test_helmert() {
+		let helmert: Helmert = [0.0, 0.0, 4.5, 0.0, 0.0, 0.554, 0.219];
+		let [x, y, z] = apply_helmert([3657660.66, 255768.55, 5201382.11], &helmert);
+		assert_close((x, y), (3657660.78, 255778.43), 0.01);
+		assert_close((z, 0.0), (5201387.75, 0.0), 0.01);
+		let [x, y, z] = invert_helmert([x, y, z], &helmert);
+		assert_close((x, y), (3657660.66, 255768.55), 1e-6);
+		assert_close((z, 0.0), (5201382.11, 0.0), 1e-6);
+	}
+
+	// Example from EPSG Guidance Note 7-2: geographic/geocentric conversion on WGS84, 73 m above the ellipsoid
+	#[test]
+	fn test_geocentric() {
+		let lon = 2.0 + 7.0 / 60.0 + 46.38 / 3600.0;
+		let lat = 53.0 + 48.0 / 60.0 + 33.82 / 3600.0;
+		assert_close(
+			from_geocentric([3771793.968, 140253.342, 5124304.349], &WGS84),
+			(lon, lat),
+			1e-8,
+		);
+		assert_close(
+			from_geocentric(to_geocentric(lon, lat, &BESSEL), &BESSEL),
+			(lon, lat),
+			1e-10,
+		);
+	}
+}
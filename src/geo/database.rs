@@ -1,40 +1,1550 @@
-use super::{file::GeoFileOptions, GeoBBox, GeoFile, GeoIndex, GeoTable};
-use neon::types::Finalize;
-use std::{error::Error, path::Path, result::Result};
+use super::{
+	crs::parse_epsg,
+	file::{FeatureParser, GeoFileOptions, IdExtractor},
+	filter::{Field, LineFilter},
+	index::{TreeType, FORMAT_VERSION},
+	progress::Progress,
+	GeoAttributeIndex, GeoBBox, GeoDBBuilder, GeoEntry, GeoFile, GeoIdIndex, GeoIndex, GeoNode, GeoTable, Query,
+	TimeRange,
+};
+use geojson::{Feature, JsonValue};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::{
+	collections::{HashMap, HashSet},
+	error::Error,
+	fs::{read, read_to_string, remove_file, rename, write, File},
+	io::{Error as IoError, ErrorKind, Write},
+	path::{Path, PathBuf},
+	result::Result,
+	str::from_utf8,
+};
 
+/// The lines of a page of a query, and the index to continue from, 0 at the end
 pub type IteratorResult<'a> = (Vec<&'a [u8]>, usize);
 
+// A tree with its table. The first segment is built from the file, the others from appended lines.
 #[derive(Debug)]
-pub struct GeoDB {
+struct Segment {
+	// number of the segment files, see get_segment_filenames
+	number: usize,
 	index: GeoIndex,
 	table: GeoTable,
+	// loaded when features are first looked up by id, or on open if an id is configured
+	ids: Option<GeoIdIndex>,
+	// loaded on open if indexProperties are configured
+	attributes: Option<GeoAttributeIndex>,
+	// table offsets of the lines hidden by tombstones, found through the id index
+	deleted: HashSet<usize>,
+}
+
+// The segments and tombstones of an index. Appends, deletes, updates and compactions write their files first
+// and then replace the manifest by renaming, so an interrupted call leaves the previous state intact.
+#[derive(Debug, Deserialize, Serialize)]
+struct Manifest {
+	// numbers of the segment files, 0 for the index of the file itself
+	segments: Vec<usize>,
+	// deleted ids, each with the first segment in which features with this id are alive again
+	tombstones: HashMap<String, usize>,
+}
+
+/// Statistics of the index of a file, see [`GeoDB::info`]
+#[derive(Clone, Debug, PartialEq)]
+pub struct GeoDBInfo {
+	/// Version of the index file format
+	pub format_version: u32,
+	/// Number of segments: the index of the file and one for each append since the last compaction
+	pub segments: usize,
+	/// Number of nodes in the trees of all segments
+	pub nodes: usize,
+	/// Number of indexed features, including deleted ones until compaction
+	pub features: usize,
+	/// Number of deleted ids
+	pub deleted_ids: usize,
+	/// Bbox of all features, None if there are none
+	pub extent: Option<GeoBBox>,
+	/// Time range of all features with a time, if the index has a time property
+	pub time: Option<TimeRange>,
+}
+
+/// A spatial index over a file of GeoJSON features or CSV/TSV points, with appended segments,
+/// lookups by id and attribute indexes. Open it with [`GeoDB::builder`] and query it with [`GeoDB::query`].
+pub struct GeoDB {
+	filename: PathBuf,
+	opt: GeoFileOptions,
+	segments: Vec<Segment>,
+	// the first skipped line of the file, naming the columns of CSV/TSV files in filters
+	header: Option<String>,
+	// the fields of the indexProperties option, in the order of the attribute indexes
+	index_fields: Vec<Field>,
+	// deleted ids, each with the first segment in which features with this id are alive again
+	tombstones: HashMap<String, usize>,
+	id_extractor: Option<IdExtractor>,
+	feature_parser: FeatureParser,
+	// CSV/TSV lines are converted into GeoJSON features when written
+	is_csv: bool,
 }
-unsafe impl Send for GeoDB {}
-impl Finalize for GeoDB {}
 
 impl GeoDB {
-	pub fn open(filename: &Path, opt: GeoFileOptions) -> Result<Self, Box<dyn Error>> {
-		let stem = filename.file_name().unwrap().to_str().unwrap();
-		let filename_index = filename.with_file_name(format!("{}.idx", stem));
-		let filename_table = filename.with_file_name(format!("{}.dat", stem));
+	/// Open a file, creating its index if needed. Index creation reports to the optional progress receiver.
+	pub fn open(filename: &Path, opt: GeoFileOptions, progress: Option<&dyn Progress>) -> Result<Self, Box<dyn Error>> {
+		let filename_header = GeoDB::get_header_filename(filename);
+		let mut manifest = GeoDB::load_manifest(filename)?;
+
+		let index: GeoIndex = if GeoDB::has_index(filename)? {
+			// indexes created before header lines were stored get them now, so that filters can use column names
+			if opt.skip_lines.unwrap_or(0) > 0 && !filename_header.exists() {
+				if let Some(header) = GeoFile::load(filename, opt.clone())?.get_header() {
+					write(&filename_header, header)?;
+				}
+			}
+			GeoIndex::load(&GeoDB::get_segment_filenames(filename, manifest.segments[0]).0)?
+		} else {
+			// appended, compacted and deleted features are only in the index, a rebuild from the file would lose them
+			if manifest.segments != [0] || !manifest.tombstones.is_empty() {
+				return Err(Box::new(IoError::new(
+					ErrorKind::InvalidData,
+					format!(
+						"The index of {} is missing or has another format version, but contains appended, compacted \
+						or deleted features that are not in the file. Remove the index to rebuild it from the file \
+						without these changes.",
+						filename.to_string_lossy()
+					),
+				)));
+			}
+			// ids and attribute indexes of a previous index don't belong to the new one
+			GeoDB::remove_index(filename)?;
+			manifest = GeoDB::load_manifest(filename)?;
+			let (filename_index, filename_table) = GeoDB::get_segment_filenames(filename, 0);
+			let tree_type = TreeType::from_options(&opt)?;
+			let mut geo_file = GeoFile::load(filename, opt.clone())?;
+			if let Some(progress) = progress {
+				geo_file.warnings().iter().for_each(|warning| progress.warning(warning));
+			}
+			if let Some(header) = geo_file.get_header() {
+				write(&filename_header, header)?;
+			}
+			geo_file.set_time_extractor(GeoFile::get_time_extractor(filename, &opt, geo_file.get_header())?);
+			GeoIndex::create(&mut geo_file, tree_type, &filename_index, &filename_table, progress)?
+		};
+		let header = if filename_header.exists() {
+			Some(read_to_string(&filename_header)?)
+		} else {
+			None
+		};
+
+		// new indexes get a manifest, indexes written before manifests get one replacing their tombstones file
+		let filename_manifest = GeoDB::get_manifest_filename(filename);
+		if !filename_manifest.exists() {
+			GeoDB::save_manifest_file(filename, &manifest)?;
+			remove_if_exists(&GeoDB::get_tombstones_filename(filename))?;
+		}
+		GeoDB::remove_unused_segment_files(filename, &manifest.segments)?;
+
+		let mut index = Some(index);
+		let mut segments = Vec::with_capacity(manifest.segments.len());
+		for &number in manifest.segments.iter() {
+			let (filename_index, filename_table) = GeoDB::get_segment_filenames(filename, number);
+			segments.push(Segment {
+				number,
+				index: match index.take() {
+					Some(index) => index,
+					None => GeoIndex::load(&filename_index)?,
+				},
+				table: GeoTable::load(&filename_table)?,
+				ids: None,
+				attributes: None,
+				deleted: HashSet::new(),
+			});
+		}
+		// times of features taken from another time property, or none, are taken again from the lines
+		if segments
+			.iter()
+			.any(|segment| segment.index.time_property() != opt.time_property.as_deref())
+		{
+			let time_extractor = GeoFile::get_time_extractor(filename, &opt, header.as_deref())?;
+			for segment in segments.iter_mut() {
+				if segment.index.time_property() != opt.time_property.as_deref() {
+					let table = segment.table.get_data();
+					segment
+						.index
+						.set_times(table, time_extractor.as_ref(), opt.time_property.as_deref());
+					let (filename_index, _) = GeoDB::get_segment_filenames(filename, segment.number);
+					let temp_index = filename_index.with_extension("idx.tmp");
+					segment.index.save(&temp_index)?;
+					rename(&temp_index, &filename_index)?;
+				}
+			}
+		}
+
+		let tombstones = manifest.tombstones;
+		let load_ids = opt.col_id.is_some() || opt.id_property.is_some() || !tombstones.is_empty();
+		let index_fields = GeoFile::get_index_fields(filename, &opt, header.as_deref())?;
+		let feature_parser = GeoFile::get_feature_parser(filename, &opt, header.as_deref());
+		let mut geo_db = GeoDB {
+			filename: filename.to_path_buf(),
+			opt,
+			segments,
+			header,
+			index_fields,
+			tombstones,
+			id_extractor: None,
+			feature_parser,
+			is_csv: GeoFile::is_csv(filename),
+		};
+		if load_ids {
+			geo_db.load_ids()?;
+		}
+		geo_db.find_deleted();
+		geo_db.load_attributes()?;
+		Ok(geo_db)
+	}
+
+	/// Whether a file has an index of the current format version, which [`GeoDB::open`] uses instead of creating one
+	pub fn has_index(filename: &Path) -> Result<bool, Box<dyn Error>> {
+		let manifest = GeoDB::load_manifest(filename)?;
+		let (filename_index, filename_table) = GeoDB::get_segment_filenames(filename, manifest.segments[0]);
+		Ok(filename_index.exists()
+			&& filename_table.exists()
+			&& GeoIndex::read_version(&filename_index)? == Some(FORMAT_VERSION))
+	}
+
+	/// Start setting the options of a file to open
+	pub fn builder(filename: impl AsRef<Path>) -> GeoDBBuilder<'static> {
+		GeoDBBuilder::new(filename.as_ref())
+	}
+
+	/// Start a query of the features in a bbox
+	pub fn query(&self, bbox: &GeoBBox) -> Query<'_> {
+		Query::new(self, bbox)
+	}
+
+	/// Parse a line returned by a query or a lookup into a feature. CSV/TSV lines become points
+	/// with the other columns as string properties.
+	pub fn parse_feature(&self, line: &[u8]) -> Result<Feature, Box<dyn Error>> {
+		(self.feature_parser)(from_utf8(line)?)
+	}
 
-		let index: GeoIndex = if filename_index.exists() && filename_table.exists() {
-			GeoIndex::load(&filename_index)?
+	/// Write a line returned by a query or a lookup as a GeoJSON feature. GeoJSON lines are written as they are,
+	/// CSV/TSV lines are converted like in parse_feature.
+	pub fn write_feature(&self, line: &[u8], output: &mut dyn Write) -> Result<(), Box<dyn Error>> {
+		if self.is_csv {
+			serde_json::to_writer(output, &self.parse_feature(line)?)?;
 		} else {
-			GeoIndex::create(&mut GeoFile::load(filename, opt)?, &filename_index, &filename_table)?
+			output.write_all(line)?;
+		}
+		Ok(())
+	}
+
+	/// Parse a line returned by a query or a lookup into GeoJSON, like in write_feature. With a list of properties,
+	/// only these properties of the feature are kept.
+	pub fn feature_json(&self, line: &[u8], properties: Option<&[String]>) -> Result<JsonValue, Box<dyn Error>> {
+		let mut feature: JsonValue = if self.is_csv {
+			serde_json::to_value(self.parse_feature(line)?)?
+		} else {
+			serde_json::from_slice(line)?
 		};
+		if let (Some(names), Some(JsonValue::Object(values))) = (properties, feature.get_mut("properties")) {
+			values.retain(|name, _| names.contains(name));
+		}
+		Ok(feature)
+	}
+
+	/// Create a filter for query_bbox from an expression over GeoJSON properties or CSV/TSV columns
+	pub fn get_filter(&self, expression: &str) -> Result<LineFilter, Box<dyn Error>> {
+		GeoFile::get_filter(&self.filename, &self.opt, self.header.as_deref(), expression)
+	}
+
+	/// Get the current feature with this id, from the newest segment containing it
+	pub fn get(&mut self, id: &str) -> Result<Option<&[u8]>, Box<dyn Error>> {
+		self.load_ids()?;
+		Ok(self.get_loaded(id))
+	}
+
+	/// Get the current features of several ids, in the order of the ids
+	pub fn get_many(&mut self, ids: &[&str]) -> Result<Vec<Option<&[u8]>>, Box<dyn Error>> {
+		self.load_ids()?;
+		let geo_db: &Self = self;
+		Ok(ids.iter().map(|id| geo_db.get_loaded(id)).collect())
+	}
+
+	/// Like get, but without loading id indexes, so that lookups can be shared between threads.
+	/// Only finds features after load_ids, or if an id column or property is configured.
+	pub fn get_loaded(&self, id: &str) -> Option<&[u8]> {
+		for (segment_no, segment) in self.segments.iter().enumerate().rev() {
+			if self.is_deleted_id(id, segment_no) {
+				continue;
+			}
+			let data = segment.table.get_data();
+			if let Some((start, length)) = segment.ids.as_ref()?.get(id).next() {
+				return Some(&data[start..start + length]);
+			}
+		}
+		None
+	}
+
+	/// The EPSG code of the coordinates in the index: of the targetCrs option, or 4326 for longitude/latitude
+	pub fn epsg_code(&self) -> u32 {
+		self
+			.opt
+			.target_crs
+			.as_deref()
+			.and_then(|code| parse_epsg(code).ok())
+			.unwrap_or(4326)
+	}
+
+	/// Whether features have ids to look them up by. CSV/TSV files need an id column.
+	pub fn has_ids(&self) -> bool {
+		!self.is_csv || self.opt.col_id.is_some()
+	}
+
+	/// Load the id index of every segment, creating it if needed or if the id option changed
+	pub fn load_ids(&mut self) -> Result<(), Box<dyn Error>> {
+		for segment_no in 0..self.segments.len() {
+			if self.segments[segment_no].ids.is_none() {
+				let ids = self.load_segment_ids(segment_no)?;
+				self.segments[segment_no].ids = Some(ids);
+			}
+		}
+		Ok(())
+	}
+
+	fn load_segment_ids(&mut self, segment_no: usize) -> Result<GeoIdIndex, Box<dyn Error>> {
+		let filename_ids = GeoDB::get_ids_filename(&self.filename, self.segments[segment_no].number);
+		let source = GeoFile::get_id_source(&self.filename, &self.opt);
+		if filename_ids.exists() {
+			// ids of another format version, or taken from another column or property, are rebuilt
+			if let Some(ids) = GeoIdIndex::load(&filename_ids)?.filter(|ids| ids.source() == source) {
+				return Ok(ids);
+			}
+		}
+		self.get_id_extractor()?;
+		let segment = &self.segments[segment_no];
+		GeoIdIndex::create(
+			&segment.index,
+			&segment.table,
+			self.id_extractor.as_ref().unwrap(),
+			&source,
+			&filename_ids,
+		)
+	}
+
+	// Load the attribute index of every segment, creating it if needed or if the indexed properties changed
+	fn load_attributes(&mut self) -> Result<(), Box<dyn Error>> {
+		if self.index_fields.is_empty() {
+			return Ok(());
+		}
+		let properties = self.opt.index_properties.clone().unwrap_or_default();
+		let reader = GeoFile::get_record_reader(&self.filename, &self.opt);
+		for segment in self.segments.iter_mut() {
+			if segment.attributes.is_some() {
+				continue;
+			}
+			let filename_attributes = GeoDB::get_attributes_filename(&self.filename, segment.number);
+			let mut attributes = None;
+			if filename_attributes.exists() {
+				// attribute indexes of another format version are rebuilt, too
+				attributes = GeoAttributeIndex::load(&filename_attributes)?
+					.filter(|attributes| attributes.properties() == properties);
+			}
+			segment.attributes = match attributes {
+				Some(attributes) => Some(attributes),
+				None => Some(GeoAttributeIndex::create(
+					&segment.index,
+					&segment.table,
+					&reader,
+					&properties,
+					&self.index_fields,
+					&filename_attributes,
+				)?),
+			};
+		}
+		Ok(())
+	}
+
+	// The leaves of a segment the filter can match, from the attribute index of the most selective
+	// condition, or None if no condition uses an indexed property
+	fn get_candidates<'a>(&self, segment: &'a Segment, filter: &LineFilter) -> Option<Vec<&'a [usize]>> {
+		let attributes = segment.attributes.as_ref()?;
+		filter
+			.equalities()
+			.into_iter()
+			.filter_map(|(field, values)| {
+				let property = self.index_fields.iter().position(|f| f == field)?;
+				Some(attributes.get_leaves(property, &values))
+			})
+			.min_by_key(|lists| lists.iter().map(|list| list.len()).sum::<usize>())
+	}
+
+	/// Query all segments. Nodes are numbered across segments, so start_index and the returned index
+	/// address a node in any segment. 0 marks the end of the query.
+	/// Lines not matching the filter are dropped, so fewer than max_count lines may be returned before the end.
+	/// With a time range, only features with a time in this range are found.
+	pub fn query_bbox(
+		&self, bbox: &GeoBBox, time: Option<&TimeRange>, start_index: usize, max_count: usize,
+		filter: Option<&LineFilter>,
+	) -> IteratorResult<'_> {
+		let mut chunks: Vec<&[u8]> = Vec::new();
+		let mut base: usize = 0;
+
+		for segment in self.segments.iter() {
+			let node_count = segment.index.node_count();
+			if start_index < base + node_count {
+				if chunks.len() >= max_count {
+					return (chunks, base);
+				}
+				let start = start_index.saturating_sub(base);
+				let (leaves, next_index) = match filter.and_then(|filter| self.get_candidates(segment, filter)) {
+					Some(candidates) => segment
+						.index
+						.query_leaves(bbox, time, &candidates, start, max_count - chunks.len()),
+					None => segment.index.query_bbox(bbox, time, start, max_count - chunks.len()),
+				};
+				let leaves = leaves
+					.into_iter()
+					.filter(|leaf| !segment.deleted.contains(&leaf.value1))
+					.collect();
+				chunks.extend(segment.table.read_ranges(leaves).into_iter().filter(|line| {
+					match (filter, from_utf8(line)) {
+						(Some(filter), Ok(line)) => filter.matches(line),
+						(Some(_), Err(_)) => false,
+						(None, _) => true,
+					}
+				}));
+				if next_index > 0 {
+					return (chunks, base + next_index);
+				}
+			}
+			base += node_count;
+		}
+		(chunks, 0)
+	}
+
+	/// Sizes, extent and time range of the index
+	pub fn info(&self) -> GeoDBInfo {
+		let mut extent: Option<GeoBBox> = None;
+		let mut time: Option<TimeRange> = None;
+		for segment in self.segments.iter() {
+			if let Some(root) = segment.index.root() {
+				extent.get_or_insert_with(GeoBBox::new_empty).include_bbox(&root.bbox);
+			}
+			if let Some(root_time) = segment.index.time(0) {
+				time.get_or_insert(root_time).include(&root_time);
+			}
+		}
+		GeoDBInfo {
+			format_version: FORMAT_VERSION,
+			segments: self.segments.len(),
+			nodes: self.segments.iter().map(|segment| segment.index.node_count()).sum(),
+			features: self
+				.segments
+				.iter()
+				.map(|segment| segment.index.features().count())
+				.sum(),
+			deleted_ids: self.tombstones.len(),
+			extent,
+			time,
+		}
+	}
+
+	/// Check the trees and tables of all segments, returning a description of every problem found.
+	/// Besides the structure of the trees, every line must be a valid feature.
+	pub fn verify(&self) -> Vec<String> {
+		let mut problems: Vec<String> = Vec::new();
+		for (segment_no, segment) in self.segments.iter().enumerate() {
+			let data = segment.table.get_data();
+			let tree_problems = segment.index.verify(data.len());
+			let is_valid = tree_problems.is_empty();
+			problems.extend(
+				tree_problems
+					.into_iter()
+					.map(|problem| format!("segment {}, {}", segment_no, problem)),
+			);
+			if !is_valid {
+				continue;
+			}
+			let leaves: Vec<(usize, &GeoNode)> = segment.index.enumerate_leaves().collect();
+			problems.extend(
+				leaves
+					.par_iter()
+					.filter_map(|&(node, leaf)| {
+						let line = &data[leaf.value1..leaf.value1 + leaf.value2];
+						match self.parse_feature(line) {
+							Ok(_) if !line.contains(&b'\n') => None,
+							Ok(_) => Some(format!(
+								"segment {}, leaf {}: line contains a line break",
+								segment_no, node
+							)),
+							Err(error) => Some(format!("segment {}, leaf {}: {}", segment_no, node, error)),
+						}
+					})
+					.collect::<Vec<String>>(),
+			);
+		}
+		problems
+	}
+
+	/// Remove the index files of a file, so that the next open builds a new index
+	pub fn remove_index(filename: &Path) -> Result<(), Box<dyn Error>> {
+		GeoDB::remove_unused_segment_files(filename, &[])?;
+		remove_if_exists(&GeoDB::get_manifest_filename(filename))?;
+		remove_if_exists(&GeoDB::get_tombstones_filename(filename))?;
+		remove_if_exists(&GeoDB::get_header_filename(filename))
+	}
 
-		Ok(GeoDB {
+	/// Add lines in the format of the file as a new segment with its own small tree
+	pub fn append(&mut self, lines: &[&str]) -> Result<(), Box<dyn Error>> {
+		self.add_segment(lines, None)
+	}
+
+	// Append lines as a new segment, and with an updated id, hide the features of this id in older segments.
+	// Both become visible together with the manifest.
+	fn add_segment(&mut self, lines: &[&str], updated_id: Option<&str>) -> Result<(), Box<dyn Error>> {
+		let mut data: Vec<u8> = Vec::new();
+		for line in lines {
+			if line.contains('\n') {
+				return Err(Box::new(IoError::new(
+					ErrorKind::InvalidInput,
+					"appended lines must not contain line breaks",
+				)));
+			}
+			data.extend_from_slice(line.as_bytes());
+			data.push(b'\n');
+		}
+
+		let mut geo_file = GeoFile::from_data(&self.filename, &self.opt, data, 0)?;
+		geo_file.set_time_extractor(GeoFile::get_time_extractor(
+			&self.filename,
+			&self.opt,
+			self.header.as_deref(),
+		)?);
+		let number = self.next_segment_number();
+		let (filename_index, filename_table) = GeoDB::get_segment_filenames(&self.filename, number);
+		let index = GeoIndex::create(
+			&mut geo_file,
+			TreeType::from_options(&self.opt)?,
+			&filename_index,
+			&filename_table,
+			None,
+		)?;
+		sync_files(&[&filename_index, &filename_table])?;
+		let load_ids = self.segments[0].ids.is_some();
+		self.segments.push(Segment {
+			number,
 			index,
 			table: GeoTable::load(&filename_table)?,
-		})
+			ids: None,
+			attributes: None,
+			deleted: HashSet::new(),
+		});
+		if let Some(id) = updated_id {
+			// features in older segments are deleted, the appended one stays alive
+			self.tombstones.insert(id.to_string(), self.segments.len() - 1);
+		}
+		self.save_manifest()?;
+		if load_ids {
+			self.load_ids()?;
+		}
+		self.find_deleted();
+		self.load_attributes()
 	}
 
-	pub fn query_bbox(
-		&self, bbox: &GeoBBox, start_index: usize, max_count: usize,
-	) -> Result<IteratorResult, Box<dyn Error>> {
-		let (leaves, next_index) = self.index.query_bbox(bbox, start_index, max_count);
-		let chunks: Vec<&[u8]> = self.table.read_ranges(leaves);
-		Ok((chunks, next_index))
+	/// Merge all segments into a single tree and table in new files, which replace them with the manifest
+	pub fn compact(&mut self, progress: Option<&dyn Progress>) -> Result<(), Box<dyn Error>> {
+		if self.segments.len() < 2 && self.tombstones.is_empty() {
+			return Ok(());
+		}
+
+		let mut data: Vec<u8> = Vec::new();
+		let mut entries: Vec<GeoEntry> = Vec::new();
+		for segment in self.segments.iter() {
+			let offset = data.len();
+			let table = segment.table.get_data();
+			data.extend_from_slice(table);
+			entries.extend(
+				segment
+					.index
+					.entries()
+					.filter(|entry| !segment.deleted.contains(&entry.leaf.value1))
+					.map(|mut entry| {
+						entry.leaf.value1 += offset;
+						entry
+					}),
+			);
+		}
+
+		// the merged segment gets new files, which replace the segments only when the manifest is saved
+		let number = self.next_segment_number();
+		let (filename_index, filename_table) = GeoDB::get_segment_filenames(&self.filename, number);
+		let index = GeoIndex::create_from_entries(
+			entries,
+			&data,
+			TreeType::from_options(&self.opt)?,
+			self.opt.time_property.as_deref(),
+			&filename_index,
+			&filename_table,
+			progress,
+		)?;
+		sync_files(&[&filename_index, &filename_table])?;
+		let load_ids = self.segments[0].ids.is_some();
+		self.segments = vec![Segment {
+			number,
+			index,
+			table: GeoTable::load(&filename_table)?,
+			ids: None,
+			attributes: None,
+			deleted: HashSet::new(),
+		}];
+		self.tombstones.clear();
+		self.save_manifest()?;
+		GeoDB::remove_unused_segment_files(&self.filename, &[number])?;
+		if load_ids {
+			self.load_ids()?;
+		}
+		self.load_attributes()
+	}
+
+	/// Delete all features with this id, by storing a tombstone that hides them until compaction.
+	/// Returns false if there is no feature with this id.
+	pub fn delete(&mut self, id: &str) -> Result<bool, Box<dyn Error>> {
+		self.load_ids()?;
+		if self.get_loaded(id).is_none() {
+			return Ok(false);
+		}
+		self.tombstones.insert(id.to_string(), self.segments.len());
+		self.save_manifest()?;
+		self.find_deleted();
+		Ok(true)
+	}
+
+	/// Replace all features with this id by a new line, which must have the same id
+	pub fn update(&mut self, id: &str, line: &str) -> Result<(), Box<dyn Error>> {
+		if self.get_id_extractor()?(line).as_deref() != Some(id) {
+			return Err(Box::new(IoError::new(
+				ErrorKind::InvalidInput,
+				format!("updated line must have the id {}", id),
+			)));
+		}
+		self.load_ids()?;
+		self.add_segment(&[line], Some(id))
+	}
+
+	fn is_deleted_id(&self, id: &str, segment_no: usize) -> bool {
+		match self.tombstones.get(id) {
+			Some(alive_from) => segment_no < *alive_from,
+			None => false,
+		}
+	}
+
+	fn get_id_extractor(&mut self) -> Result<&IdExtractor, Box<dyn Error>> {
+		if self.id_extractor.is_none() {
+			self.id_extractor = Some(GeoFile::get_id_extractor(&self.filename, &self.opt)?);
+		}
+		Ok(self.id_extractor.as_ref().unwrap())
+	}
+
+	fn next_segment_number(&self) -> usize {
+		self.segments.iter().map(|segment| segment.number).max().unwrap_or(0) + 1
+	}
+
+	fn save_manifest(&self) -> Result<(), Box<dyn Error>> {
+		let manifest = Manifest {
+			segments: self.segments.iter().map(|segment| segment.number).collect(),
+			tombstones: self.tombstones.clone(),
+		};
+		GeoDB::save_manifest_file(&self.filename, &manifest)
+	}
+
+	// Write the manifest to a temporary file first and rename it, so a crash leaves either the old or the new one
+	fn save_manifest_file(filename: &Path, manifest: &Manifest) -> Result<(), Box<dyn Error>> {
+		let filename_manifest = GeoDB::get_manifest_filename(filename);
+		let temp_manifest = filename_manifest.with_extension("manifest.tmp");
+		let mut file = File::create(&temp_manifest)?;
+		file.write_all(&bincode::serialize(manifest)?)?;
+		file.sync_all()?;
+		rename(&temp_manifest, &filename_manifest)?;
+		Ok(())
+	}
+
+	// The manifest of an index. Indexes written before manifests have consecutive segments and a tombstones file.
+	fn load_manifest(filename: &Path) -> Result<Manifest, Box<dyn Error>> {
+		let filename_manifest = GeoDB::get_manifest_filename(filename);
+		if filename_manifest.exists() {
+			return Ok(bincode::deserialize(&read(&filename_manifest)?)?);
+		}
+		let mut segments = vec![0];
+		loop {
+			let (filename_index, filename_table) = GeoDB::get_segment_filenames(filename, segments.len());
+			if !filename_index.exists() || !filename_table.exists() {
+				break;
+			}
+			segments.push(segments.len());
+		}
+		let filename_tombstones = GeoDB::get_tombstones_filename(filename);
+		let tombstones = if filename_tombstones.exists() {
+			bincode::deserialize(&read(&filename_tombstones)?)?
+		} else {
+			HashMap::new()
+		};
+		Ok(Manifest { segments, tombstones })
+	}
+
+	// Find the lines hidden by tombstones by their ids, which are loaded whenever there are tombstones
+	fn find_deleted(&mut self) {
+		for (segment_no, segment) in self.segments.iter_mut().enumerate() {
+			segment.deleted = match &segment.ids {
+				Some(ids) => self
+					.tombstones
+					.iter()
+					.filter(|(_, alive_from)| segment_no < **alive_from)
+					.flat_map(|(id, _)| ids.get(id).map(|(start, _)| start))
+					.collect(),
+				None => HashSet::new(),
+			};
+		}
+	}
+
+	fn get_manifest_filename(filename: &Path) -> PathBuf {
+		let stem = filename.file_name().unwrap().to_str().unwrap();
+		filename.with_file_name(format!("{}.manifest", stem))
+	}
+
+	// Tombstones of indexes written before manifests
+	fn get_tombstones_filename(filename: &Path) -> PathBuf {
+		let stem = filename.file_name().unwrap().to_str().unwrap();
+		filename.with_file_name(format!("{}.del", stem))
+	}
+
+	// Index and table files of a segment: "file.idx"/"file.dat" for the first, "file.1.idx"/"file.1.dat" and so on
+	fn get_segment_filenames(filename: &Path, segment: usize) -> (PathBuf, PathBuf) {
+		let stem = filename.file_name().unwrap().to_str().unwrap();
+		let stem = match segment {
+			0 => stem.to_string(),
+			n => format!("{}.{}", stem, n),
+		};
+		(
+			filename.with_file_name(format!("{}.idx", stem)),
+			filename.with_file_name(format!("{}.dat", stem)),
+		)
+	}
+
+	fn get_header_filename(filename: &Path) -> PathBuf {
+		let stem = filename.file_name().unwrap().to_str().unwrap();
+		filename.with_file_name(format!("{}.hdr", stem))
+	}
+
+	// Id index file of a segment: "file.ids", "file.1.ids" and so on
+	fn get_ids_filename(filename: &Path, segment: usize) -> PathBuf {
+		let (filename_index, _) = GeoDB::get_segment_filenames(filename, segment);
+		filename_index.with_extension("ids")
+	}
+
+	// Attribute index file of a segment: "file.attr", "file.1.attr" and so on
+	fn get_attributes_filename(filename: &Path, segment: usize) -> PathBuf {
+		let (filename_index, _) = GeoDB::get_segment_filenames(filename, segment);
+		filename_index.with_extension("attr")
+	}
+
+	// Remove the files of all segments not in the list: replaced by a compaction, or written by a call that was
+	// interrupted before saving the manifest. New segments get the number after the highest one in the manifest.
+	fn remove_unused_segment_files(filename: &Path, segments: &[usize]) -> Result<(), Box<dyn Error>> {
+		let manifest = GeoDB::load_manifest(filename)?;
+		let last = manifest.segments.iter().chain(segments).max().unwrap_or(&0) + 1;
+		for segment in (0..=last).filter(|segment| !segments.contains(segment)) {
+			let (filename_index, filename_table) = GeoDB::get_segment_filenames(filename, segment);
+			remove_if_exists(&filename_index)?;
+			remove_if_exists(&filename_table)?;
+			remove_if_exists(&GeoDB::get_ids_filename(filename, segment))?;
+			remove_if_exists(&GeoDB::get_attributes_filename(filename, segment))?;
+		}
+		Ok(())
+	}
+}
+
+// Flush written files to the disk before the manifest refers to them
+fn sync_files(filenames: &[&Path]) -> Result<(), Box<dyn Error>> {
+	for filename in filenames {
+		File::open(filename)?.sync_all()?;
+	}
+	Ok(())
+}
+
+fn remove_if_exists(filename: &Path) -> Result<(), Box<dyn Error>> {
+	if filename.exists() {
+		remove_file(filename)?;
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use assert_fs::TempDir;
+	use std::{
+		fs::{read_dir, write},
+		str::FromStr,
+	};
+
+	fn query_all(geo_db: &GeoDB, bbox: &GeoBBox, max_count: usize) -> Result<Vec<String>, Box<dyn Error>> {
+		query_filtered(geo_db, bbox, max_count, None)
+	}
+
+	fn query_filtered(
+		geo_db: &GeoDB, bbox: &GeoBBox, max_count: usize, filter: Option<&LineFilter>,
+	) -> Result<Vec<String>, Box<dyn Error>> {
+		query_time(geo_db, bbox, None, max_count, filter)
+	}
+
+	fn query_time(
+		geo_db: &GeoDB, bbox: &GeoBBox, time: Option<&TimeRange>, max_count: usize, filter: Option<&LineFilter>,
+	) -> Result<Vec<String>, Box<dyn Error>> {
+		let mut lines: Vec<String> = Vec::new();
+		let mut index = 0;
+		loop {
+			let (chunks, next_index) = geo_db.query_bbox(bbox, time, index, max_count, filter);
+			assert!(chunks.len() <= max_count);
+			for chunk in chunks {
+				lines.push(from_utf8(chunk)?.to_string());
+			}
+			if next_index == 0 {
+				break;
+			}
+			index = next_index;
+		}
+		lines.sort();
+		Ok(lines)
+	}
+
+	#[test]
+	fn test_append_and_compact() -> Result<(), Box<dyn Error>> {
+		let dir = TempDir::new()?;
+		let filename = dir.path().join("points.csv");
+		write(&filename, "name,x,y\na,1,1\nb,2,2\nc,3,3\n")?;
+		let options = GeoFileOptions::new(",", 1, 2, 1);
+		let all = GeoBBox::new(0., 10., 0., 10.);
+
+		let mut geo_db = GeoDB::open(&filename, options.clone(), None)?;
+		assert_eq!(query_all(&geo_db, &all, 10)?, vec!["a,1,1", "b,2,2", "c,3,3"]);
+
+		geo_db.append(&["d,4,4", "e,5,5"])?;
+		geo_db.append(&["f,6,6"])?;
+		assert!(geo_db.append(&["g,7,7\nh,8,8"]).is_err());
+		assert_eq!(geo_db.segments.len(), 3);
+		let expected = vec!["a,1,1", "b,2,2", "c,3,3", "d,4,4", "e,5,5", "f,6,6"];
+		for max_count in [1, 2, 3, 10] {
+			assert_eq!(query_all(&geo_db, &all, max_count)?, expected);
+		}
+		assert_eq!(
+			query_all(&geo_db, &GeoBBox::new(2.5, 5.5, 2.5, 5.5), 1)?,
+			vec!["c,3,3", "d,4,4", "e,5,5"]
+		);
+
+		// segments are loaded again
+		let mut geo_db = GeoDB::open(&filename, options.clone(), None)?;
+		assert_eq!(geo_db.segments.len(), 3);
+		assert_eq!(query_all(&geo_db, &all, 2)?, expected);
+
+		geo_db.compact(None)?;
+		assert_eq!(geo_db.segments.len(), 1);
+		assert_eq!(query_all(&geo_db, &all, 2)?, expected);
+		assert!(!dir.path().join("points.csv.1.idx").exists());
+		assert!(!dir.path().join("points.csv.idx.tmp").exists());
+
+		let geo_db = GeoDB::open(&filename, options, None)?;
+		assert_eq!(geo_db.segments.len(), 1);
+		assert_eq!(query_all(&geo_db, &all, 10)?, expected);
+		Ok(())
+	}
+
+	#[test]
+	fn test_invalid_lines() -> Result<(), Box<dyn Error>> {
+		let dir = TempDir::new()?;
+		let all = GeoBBox::new(0., 10., 0., 10.);
+
+		let filename = dir.path().join("points.csv");
+		write(&filename, "name,x,y\na,1,1\nb,2\nc,x,3\n")?;
+		let error = GeoDB::open(&filename, GeoFileOptions::new(",", 1, 2, 1), None)
+			.err()
+			.unwrap()
+			.to_string();
+		assert_eq!(
+			error,
+			"2 invalid lines, line 3: missing coordinate column 2, line 4: invalid coordinate: x"
+		);
+
+		write(&filename, "name,x,y\na,1,1\n")?;
+		let mut geo_db = GeoDB::open(&filename, GeoFileOptions::new(",", 1, 2, 1), None)?;
+		assert!(geo_db.append(&["b,2,2", "c,NaN,3"]).is_err());
+		assert!(geo_db.append(&["d,4"]).is_err());
+		assert_eq!(geo_db.segments.len(), 1);
+		assert_eq!(query_all(&geo_db, &all, 10)?, vec!["a,1,1"]);
+
+		let filename = dir.path().join("points.geojsonl");
+		let feature = |id: &str, geometry: &str| {
+			format!(
+				"{{\"type\":\"Feature\",\"id\":\"{}\",\"properties\":{{}},\"geometry\":{}}}",
+				id, geometry
+			)
+		};
+		let point = feature("a", r#"{"type":"Point","coordinates":[1,1]}"#);
+		write(&filename, format!("{}\n", point))?;
+		let mut geo_db = GeoDB::open(&filename, GeoFileOptions::empty(), None)?;
+		for line in [
+			String::from("not json"),
+			feature("b", "null"),
+			feature("b", r#"{"type":"Point","coordinates":[1]}"#),
+		] {
+			assert!(geo_db.append(&[&line]).is_err(), "{}", line);
+		}
+		assert!(geo_db.update("a", &feature("a", "null")).is_err());
+		assert_eq!(geo_db.segments.len(), 1);
+		assert_eq!(geo_db.get("a")?, Some(point.as_bytes()));
+		Ok(())
+	}
+
+	#[test]
+	fn test_keep_changes_on_rebuild() -> Result<(), Box<dyn Error>> {
+		let dir = TempDir::new()?;
+		let filename = dir.path().join("points.csv");
+		write(&filename, "a,1,1\nb,2,2\n")?;
+		let mut options = GeoFileOptions::new(",", 1, 2, 0);
+		options.col_id = Some(0);
+		let all = GeoBBox::new(0., 10., 0., 10.);
+		let outdate = |geo_db: &GeoDB| -> Result<(), Box<dyn Error>> {
+			let (filename_index, _) = GeoDB::get_segment_filenames(&filename, geo_db.segments[0].number);
+			let bytes = read(&filename_index)?;
+			write(&filename_index, &bytes[8..])?;
+			Ok(())
+		};
+
+		// appended, deleted and compacted features would be lost, so the index isn't rebuilt
+		let mut geo_db = GeoDB::open(&filename, options.clone(), None)?;
+		geo_db.append(&["c,3,3"])?;
+		outdate(&geo_db)?;
+		assert!(GeoDB::open(&filename, options.clone(), None).is_err());
+		assert!(dir.path().join("points.csv.1.idx").exists());
+
+		GeoDB::remove_index(&filename)?;
+		let mut geo_db = GeoDB::open(&filename, options.clone(), None)?;
+		geo_db.delete("a")?;
+		outdate(&geo_db)?;
+		assert!(GeoDB::open(&filename, options.clone(), None).is_err());
+
+		GeoDB::remove_index(&filename)?;
+		let mut geo_db = GeoDB::open(&filename, options.clone(), None)?;
+		geo_db.delete("a")?;
+		geo_db.compact(None)?;
+		outdate(&geo_db)?;
+		let error = GeoDB::open(&filename, options.clone(), None).err().unwrap();
+		assert!(error.to_string().contains("Remove the index"));
+
+		// after removing the index, it is rebuilt from the file
+		GeoDB::remove_index(&filename)?;
+		let geo_db = GeoDB::open(&filename, options, None)?;
+		assert_eq!(geo_db.segments.len(), 1);
+		assert_eq!(query_all(&geo_db, &all, 10)?, vec!["a,1,1", "b,2,2"]);
+		assert_eq!(read_dir(dir.path())?.count(), 5);
+		Ok(())
+	}
+
+	#[test]
+	fn test_rebuild_outdated_index() -> Result<(), Box<dyn Error>> {
+		let dir = TempDir::new()?;
+		let filename = dir.path().join("points.csv");
+		write(&filename, "1,1\n2,2\n")?;
+		let filename_index = dir.path().join("points.csv.idx");
+		GeoDB::open(&filename, GeoFileOptions::empty(), None)?;
+
+		// indexes written before the format version was stored have no header
+		let bytes = read(&filename_index)?;
+		write(&filename_index, &bytes[8..])?;
+		let geo_db = GeoDB::open(&filename, GeoFileOptions::empty(), None)?;
+		assert_eq!(GeoIndex::read_version(&filename_index)?, Some(FORMAT_VERSION));
+		assert_eq!(
+			query_all(&geo_db, &GeoBBox::new(0., 10., 0., 10.), 10)?,
+			vec!["1,1", "2,2"]
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn test_write_missing_header() -> Result<(), Box<dyn Error>> {
+		let dir = TempDir::new()?;
+		let filename = dir.path().join("points.csv");
+		write(&filename, "name,x,y\nMainz,8.27,50\nBonn,7.1,50.73\n")?;
+		let geo_db = GeoDB::builder(&filename).skip_lines(1).col_x(1).col_y(2).open()?;
+		drop(geo_db);
+		remove_file(dir.path().join("points.csv.hdr"))?;
+
+		let geo_db = GeoDB::builder(&filename).skip_lines(1).col_x(1).col_y(2).open()?;
+		assert_eq!(read_to_string(dir.path().join("points.csv.hdr"))?, "name,x,y");
+		let lines = geo_db
+			.query(&GeoBBox::new(0., 10., 0., 90.))
+			.filter("name = 'Bonn'")?
+			.lines()
+			.collect::<Vec<_>>();
+		assert_eq!(lines, vec![b"Bonn,7.1,50.73".as_slice()]);
+		Ok(())
+	}
+
+	#[test]
+	fn test_delete_and_update() -> Result<(), Box<dyn Error>> {
+		let dir = TempDir::new()?;
+		let filename = dir.path().join("points.geojsonl");
+		let feature = |id: &str, x: f64| {
+			format!(
+				"{{\"type\":\"Feature\",\"id\":\"{}\",\"properties\":{{}},\"geometry\":{{\"type\":\"Point\",\"coordinates\":[{},1]}}}}",
+				id, x
+			)
+		};
+		write(
+			&filename,
+			format!("{}\n{}\n{}\n", feature("a", 1.), feature("b", 2.), feature("c", 3.)),
+		)?;
+		let all = GeoBBox::new(0., 10., 0., 10.);
+
+		let mut geo_db = GeoDB::open(&filename, GeoFileOptions::empty(), None)?;
+		assert!(geo_db.delete("b")?);
+		assert!(!geo_db.delete("b")?);
+		assert!(!geo_db.delete("x")?);
+		assert!(!dir.path().join("points.geojsonl.manifest.tmp").exists());
+		geo_db.update("c", &feature("c", 5.))?;
+		assert!(geo_db.update("c", &feature("d", 5.)).is_err());
+		let expected = vec![feature("a", 1.), feature("c", 5.)];
+		assert_eq!(query_all(&geo_db, &all, 1)?, expected);
+
+		// an updated feature can be deleted and updated again
+		geo_db.delete("c")?;
+		assert_eq!(query_all(&geo_db, &all, 10)?, vec![feature("a", 1.)]);
+		geo_db.update("c", &feature("c", 6.))?;
+		geo_db.append(&[&feature("b", 7.)])?;
+		let expected = vec![feature("a", 1.), feature("b", 7.), feature("c", 6.)];
+		assert_eq!(query_all(&geo_db, &all, 10)?, expected);
+
+		// tombstones are persisted
+		let mut geo_db = GeoDB::open(&filename, GeoFileOptions::empty(), None)?;
+		assert_eq!(query_all(&geo_db, &all, 10)?, expected);
+
+		geo_db.compact(None)?;
+		assert!(geo_db.tombstones.is_empty());
+		assert_eq!(query_all(&geo_db, &all, 10)?, expected);
+		assert_eq!(geo_db.segments[0].index.features().count(), 3);
+		Ok(())
+	}
+
+	#[test]
+	fn test_interrupted_changes() -> Result<(), Box<dyn Error>> {
+		let dir = TempDir::new()?;
+		let filename = dir.path().join("points.csv");
+		write(&filename, "a,1,1\nb,2,2\n")?;
+		let mut options = GeoFileOptions::new(",", 1, 2, 0);
+		options.col_id = Some(0);
+		let all = GeoBBox::new(0., 10., 0., 10.);
+		let file = |name: &str| dir.path().join(name);
+
+		let mut geo_db = GeoDB::open(&filename, options.clone(), None)?;
+		geo_db.append(&["c,3,3"])?;
+		assert!(geo_db.delete("a")?);
+		let expected = vec!["b,2,2", "c,3,3"];
+		drop(geo_db);
+
+		// files of an append or a compaction that didn't save the manifest are ignored and removed
+		let copy = |from: &str, to: &str| std::fs::copy(file(from), file(to));
+		copy("points.csv.1.idx", "points.csv.2.idx")?;
+		copy("points.csv.1.dat", "points.csv.2.dat")?;
+		copy("points.csv.1.ids", "points.csv.2.ids")?;
+		let mut geo_db = GeoDB::open(&filename, options.clone(), None)?;
+		assert_eq!(geo_db.segments.len(), 2);
+		assert_eq!(query_all(&geo_db, &all, 10)?, expected);
+		assert!(!file("points.csv.2.idx").exists());
+		assert!(!file("points.csv.2.ids").exists());
+
+		// segments replaced by a compaction are removed, even if it was interrupted after saving the manifest
+		geo_db.compact(None)?;
+		assert_eq!(geo_db.segments[0].number, 2);
+		assert!(!file("points.csv.idx").exists());
+		assert!(!file("points.csv.1.idx").exists());
+		copy("points.csv.2.idx", "points.csv.1.idx")?;
+		copy("points.csv.2.dat", "points.csv.1.dat")?;
+		let geo_db = GeoDB::open(&filename, options.clone(), None)?;
+		assert_eq!(geo_db.segments.len(), 1);
+		assert_eq!(query_all(&geo_db, &all, 10)?, expected);
+		assert!(!file("points.csv.1.idx").exists());
+
+		// indexes written before manifests have consecutive segments and a tombstones file
+		GeoDB::remove_index(&filename)?;
+		let mut geo_db = GeoDB::open(&filename, options.clone(), None)?;
+		geo_db.append(&["c,3,3"])?;
+		remove_file(file("points.csv.manifest"))?;
+		write(
+			file("points.csv.del"),
+			bincode::serialize(&HashMap::from([(String::from("a"), 2usize)]))?,
+		)?;
+		let geo_db = GeoDB::open(&filename, options, None)?;
+		assert_eq!(query_all(&geo_db, &all, 10)?, expected);
+		assert!(file("points.csv.manifest").exists());
+		assert!(!file("points.csv.del").exists());
+		Ok(())
+	}
+
+	#[test]
+	fn test_delete_antimeridian() -> Result<(), Box<dyn Error>> {
+		let dir = TempDir::new()?;
+		let filename = dir.path().join("lines.geojsonl");
+		write(
+			&filename,
+			concat!(
+				"{\"type\":\"Feature\",\"id\":\"a\",\"properties\":{},\"geometry\":{\"type\":\"LineString\",\"coordinates\":[[170,0],[-170,10]]}}\n",
+				"{\"type\":\"Feature\",\"id\":\"b\",\"properties\":{},\"geometry\":{\"type\":\"Point\",\"coordinates\":[0,5]}}\n",
+			),
+		)?;
+		let mut geo_db = GeoDB::open(&filename, GeoFileOptions::empty(), None)?;
+		assert!(geo_db.delete("a")?);
+		// both leaves of the line are hidden
+		for bbox in [GeoBBox::new(175., 176., 0., 10.), GeoBBox::new(-176., -175., 0., 10.)] {
+			assert_eq!(query_all(&geo_db, &bbox, 10)?, Vec::<String>::new());
+		}
+		assert_eq!(query_all(&geo_db, &GeoBBox::new(-180., 180., -90., 90.), 10)?.len(), 1);
+		Ok(())
+	}
+
+	#[test]
+	fn test_delete_needs_id() -> Result<(), Box<dyn Error>> {
+		let dir = TempDir::new()?;
+		let filename = dir.path().join("points.csv");
+		write(&filename, "a,1,1\nb,2,2\n")?;
+		let all = GeoBBox::new(0., 10., 0., 10.);
+
+		let mut geo_db = GeoDB::open(&filename, GeoFileOptions::new(",", 1, 2, 0), None)?;
+		assert!(geo_db.delete("a").is_err());
+
+		let mut options = GeoFileOptions::new(",", 1, 2, 0);
+		options.col_id = Some(0);
+		let mut geo_db = GeoDB::open(&filename, options, None)?;
+		geo_db.delete("a")?;
+		assert_eq!(query_all(&geo_db, &all, 10)?, vec!["b,2,2"]);
+		Ok(())
+	}
+
+	#[test]
+	fn test_get_by_id() -> Result<(), Box<dyn Error>> {
+		let dir = TempDir::new()?;
+		let filename = dir.path().join("points.csv");
+		write(&filename, "a,1,1\nb,2,2\nc,3,3\n")?;
+		let mut options = GeoFileOptions::new(",", 1, 2, 0);
+		options.col_id = Some(0);
+		let lines = |lines: Vec<Option<&[u8]>>| -> Vec<Option<String>> {
+			lines
+				.into_iter()
+				.map(|line| line.map(|line| from_utf8(line).unwrap().to_string()))
+				.collect()
+		};
+
+		let mut geo_db = GeoDB::open(&filename, options.clone(), None)?;
+		assert!(dir.path().join("points.csv.ids").exists());
+		assert_eq!(geo_db.get("b")?, Some("b,2,2".as_bytes()));
+		assert_eq!(geo_db.get("d")?, None);
+
+		geo_db.update("b", "b,5,5")?;
+		geo_db.delete("c")?;
+		geo_db.append(&["d,4,4"])?;
+		assert!(dir.path().join("points.csv.2.ids").exists());
+		let expected = vec![
+			Some(String::from("b,5,5")),
+			None,
+			Some(String::from("d,4,4")),
+			Some(String::from("a,1,1")),
+		];
+		assert_eq!(lines(geo_db.get_many(&["b", "c", "d", "a"])?), expected);
+
+		geo_db.compact(None)?;
+		assert!(!dir.path().join("points.csv.2.ids").exists());
+		assert_eq!(lines(geo_db.get_many(&["b", "c", "d", "a"])?), expected);
+
+		// the id index is reused when reopening
+		let mut geo_db = GeoDB::open(&filename, options, None)?;
+		assert_eq!(lines(geo_db.get_many(&["b", "c", "d", "a"])?), expected);
+		Ok(())
+	}
+
+	#[test]
+	fn test_ids_follow_id_option() -> Result<(), Box<dyn Error>> {
+		let dir = TempDir::new()?;
+		let filename = dir.path().join("points.csv");
+		write(&filename, "a,1,1\nb,2,2\n")?;
+		let mut options = GeoFileOptions::new(",", 1, 2, 0);
+		options.col_id = Some(0);
+		let mut geo_db = GeoDB::open(&filename, options.clone(), None)?;
+		assert!(geo_db.has_ids());
+		assert_eq!(geo_db.get("a")?, Some("a,1,1".as_bytes()));
+		assert!(!GeoDB::open(&filename, GeoFileOptions::new(",", 1, 2, 0), None)?.has_ids());
+
+		// the id index of another column is rebuilt
+		options.col_id = Some(1);
+		let mut geo_db = GeoDB::open(&filename, options.clone(), None)?;
+		assert_eq!(geo_db.get("a")?, None);
+		assert_eq!(geo_db.get("2")?, Some("b,2,2".as_bytes()));
+
+		// and so is an id index without the current format version
+		write(dir.path().join("points.csv.ids"), "outdated")?;
+		let mut geo_db = GeoDB::open(&filename, options, None)?;
+		assert_eq!(geo_db.get("2")?, Some("b,2,2".as_bytes()));
+		Ok(())
+	}
+
+	#[test]
+	fn test_feature_json() -> Result<(), Box<dyn Error>> {
+		let dir = TempDir::new()?;
+		let all = GeoBBox::new(0., 10., 0., 10.);
+		let names = |values: &[&str]| -> Vec<String> { values.iter().map(|value| value.to_string()).collect() };
+
+		let filename = dir.path().join("places.geojsonl");
+		let line = r#"{"type":"Feature","properties":{"name":"Mainz","land":"RP","adresse":{"ort":"Mainz"}},"geometry":{"type":"Point","coordinates":[8.27,50]}}"#;
+		write(&filename, format!("{}\n", line))?;
+		let geo_db = GeoDB::open(&filename, GeoFileOptions::empty(), None)?;
+		assert_eq!(
+			geo_db.feature_json(line.as_bytes(), None)?,
+			serde_json::from_str::<JsonValue>(line)?
+		);
+		let feature = geo_db.feature_json(line.as_bytes(), Some(&names(&["name", "adresse", "missing"])))?;
+		assert_eq!(
+			feature["properties"],
+			serde_json::json!({ "name": "Mainz", "adresse": { "ort": "Mainz" } })
+		);
+		assert_eq!(feature["geometry"]["coordinates"], serde_json::json!([8.27, 50]));
+		let feature = geo_db.feature_json(line.as_bytes(), Some(&[]))?;
+		assert_eq!(feature["properties"], serde_json::json!({}));
+		assert!(geo_db.feature_json(b"not json", None).is_err());
+
+		// CSV rows are converted first, and page through the query like lines
+		let filename = dir.path().join("points.csv");
+		write(&filename, "name;x;y;land\na;1;1;BW\nb;2;2;BY\nc;3;3;BE\n")?;
+		let geo_db = GeoDB::builder(&filename)
+			.separator(";")
+			.col_x(1)
+			.col_y(2)
+			.skip_lines(1)
+			.open()?;
+		let query = geo_db.query(&all);
+		let mut features: Vec<JsonValue> = Vec::new();
+		let mut next_index = 0;
+		loop {
+			let (lines, index) = query.page(next_index, 1);
+			assert_eq!(lines.len(), 1);
+			for line in lines {
+				features.push(geo_db.feature_json(line, Some(&names(&["land"])))?);
+			}
+			next_index = index;
+			if next_index == 0 {
+				break;
+			}
+		}
+		let mut lands: Vec<&JsonValue> = features.iter().map(|feature| &feature["properties"]).collect();
+		lands.sort_by_key(|properties| properties.to_string());
+		assert_eq!(
+			lands,
+			vec![
+				&serde_json::json!({ "land": "BE" }),
+				&serde_json::json!({ "land": "BW" }),
+				&serde_json::json!({ "land": "BY" }),
+			]
+		);
+		assert_eq!(features[0]["type"], "Feature");
+		Ok(())
+	}
+
+	#[test]
+	fn test_get_by_default_id() -> Result<(), Box<dyn Error>> {
+		let dir = TempDir::new()?;
+		let filename = dir.path().join("points.geojsonl");
+		let feature =
+			"{\"type\":\"Feature\",\"id\":7,\"properties\":{},\"geometry\":{\"type\":\"Point\",\"coordinates\":[1,1]}}";
+		write(&filename, format!("{}\n", feature))?;
+
+		// without an id option, the id index is only created on the first lookup
+		let mut geo_db = GeoDB::open(&filename, GeoFileOptions::empty(), None)?;
+		assert!(geo_db.has_ids());
+		assert!(!dir.path().join("points.geojsonl.ids").exists());
+		assert_eq!(geo_db.get("7")?, Some(feature.as_bytes()));
+		assert!(dir.path().join("points.geojsonl.ids").exists());
+		Ok(())
+	}
+
+	#[test]
+	fn test_query_with_filter() -> Result<(), Box<dyn Error>> {
+		let dir = TempDir::new()?;
+		let all = GeoBBox::new(0., 10., 0., 10.);
+
+		let filename = dir.path().join("points.csv");
+		write(
+			&filename,
+			"name;x;y;klasse\na;1;1;Wald\nb;2;2;Feld\nc;3;3;Wald\nd;4;4;\n",
+		)?;
+		let geo_db = GeoDB::open(&filename, GeoFileOptions::new(";", 1, 2, 1), None)?;
+		let filter = geo_db.get_filter("klasse = 'Wald' and x > 1")?;
+		assert_eq!(query_filtered(&geo_db, &all, 1, Some(&filter))?, vec!["c;3;3;Wald"]);
+		let filter = geo_db.get_filter("$3 is null or name in ('a')")?;
+		assert_eq!(
+			query_filtered(&geo_db, &all, 10, Some(&filter))?,
+			vec!["a;1;1;Wald", "d;4;4;"]
+		);
+		assert!(geo_db.get_filter("ort = 'Mainz'").is_err());
+
+		// the header is kept when reopening
+		let geo_db = GeoDB::open(&filename, GeoFileOptions::new(";", 1, 2, 1), None)?;
+		let filter = geo_db.get_filter("name = 'b'")?;
+		assert_eq!(query_filtered(&geo_db, &all, 10, Some(&filter))?, vec!["b;2;2;Feld"]);
+
+		let filename = dir.path().join("points.geojsonl");
+		let feature = |x: f64, klasse: &str| {
+			format!(
+				"{{\"type\":\"Feature\",\"properties\":{{\"klasse\":\"{}\"}},\"geometry\":{{\"type\":\"Point\",\"coordinates\":[{},1]}}}}",
+				klasse, x
+			)
+		};
+		write(&filename, format!("{}\n{}\n", feature(1., "Wald"), feature(2., "Feld")))?;
+		let mut geo_db = GeoDB::open(&filename, GeoFileOptions::empty(), None)?;
+		geo_db.append(&[&feature(3., "Wald")])?;
+		let filter = geo_db.get_filter("klasse = 'Wald'")?;
+		let expected = vec![feature(1., "Wald"), feature(3., "Wald")];
+		assert_eq!(query_filtered(&geo_db, &all, 1, Some(&filter))?, expected);
+		Ok(())
+	}
+
+	#[test]
+	fn test_query_with_attribute_index() -> Result<(), Box<dyn Error>> {
+		let dir = TempDir::new()?;
+		let filename = dir.path().join("points.geojsonl");
+		let feature = |x: f64, land: &str| {
+			format!(
+				"{{\"type\":\"Feature\",\"properties\":{{\"land\":\"{}\",\"x\":{}}},\"geometry\":{{\"type\":\"Point\",\"coordinates\":[{},1]}}}}",
+				land, x, x
+			)
+		};
+		let lines: Vec<String> = (0..20)
+			.map(|i| feature(i as f64 * 0.5, ["BW", "BY", "HE"][i % 3]))
+			.collect();
+		write(&filename, lines.join("\n") + "\n")?;
+		let mut options = GeoFileOptions::empty();
+		options.index_type = Some(String::from("str"));
+		options.node_size = Some(4);
+		options.index_properties = Some(vec![String::from("land")]);
+		let bbox = GeoBBox::new(2., 8., 0., 10.);
+		let expected = |lands: &[&str], min_x: f64| -> Vec<String> {
+			let mut lines: Vec<String> = (0..20)
+				.filter(|i| lands.contains(&["BW", "BY", "HE"][i % 3]) && *i as f64 * 0.5 >= min_x && *i <= 16)
+				.map(|i| feature(i as f64 * 0.5, ["BW", "BY", "HE"][i % 3]))
+				.collect();
+			lines.sort();
+			lines
+		};
+
+		let mut geo_db = GeoDB::open(&filename, options.clone(), None)?;
+		assert!(dir.path().join("points.geojsonl.attr").exists());
+		let filter = geo_db.get_filter("land = 'BW' and x >= 2")?;
+		assert_eq!(geo_db.get_candidates(&geo_db.segments[0], &filter).unwrap()[0].len(), 7);
+		assert_eq!(query_filtered(&geo_db, &bbox, 1, Some(&filter))?, expected(&["BW"], 2.));
+		let filter = geo_db.get_filter("land in ('BY', 'HE', 'NI') and x >= 2")?;
+		assert_eq!(
+			query_filtered(&geo_db, &bbox, 2, Some(&filter))?,
+			expected(&["BY", "HE"], 2.)
+		);
+		// conditions on other properties are answered by the tree
+		let filter = geo_db.get_filter("land = 'BW' or x >= 2")?;
+		assert!(geo_db.get_candidates(&geo_db.segments[0], &filter).is_none());
+
+		geo_db.append(&[&feature(5.25, "BW")])?;
+		assert!(dir.path().join("points.geojsonl.1.attr").exists());
+		let filter = geo_db.get_filter("land = 'BW' and x >= 2")?;
+		let mut with_appended = expected(&["BW"], 2.);
+		with_appended.push(feature(5.25, "BW"));
+		with_appended.sort();
+		assert_eq!(query_filtered(&geo_db, &bbox, 1, Some(&filter))?, with_appended);
+
+		// the attribute indexes are reused, or recreated if the properties changed
+		let geo_db = GeoDB::open(&filename, options.clone(), None)?;
+		assert_eq!(query_filtered(&geo_db, &bbox, 1, Some(&filter))?, with_appended);
+		options.index_properties = Some(vec![String::from("x"), String::from("land")]);
+		let mut geo_db = GeoDB::open(&filename, options, None)?;
+		let filter = geo_db.get_filter("x = 3")?;
+		assert_eq!(geo_db.get_candidates(&geo_db.segments[0], &filter).unwrap()[0].len(), 1);
+		assert_eq!(
+			query_filtered(&geo_db, &bbox, 1, Some(&filter))?,
+			vec![feature(3., "BW")]
+		);
+
+		geo_db.compact(None)?;
+		assert!(!dir.path().join("points.geojsonl.1.attr").exists());
+		let filter = geo_db.get_filter("land = 'BW' and x >= 2")?;
+		assert_eq!(query_filtered(&geo_db, &bbox, 1, Some(&filter))?, with_appended);
+		Ok(())
+	}
+
+	#[test]
+	fn test_query_time_range() -> Result<(), Box<dyn Error>> {
+		let dir = TempDir::new()?;
+		let all = GeoBBox::new(0., 10., 0., 10.);
+		let day = |day: i64| TimeRange::new(day * 86_400_000, (day + 1) * 86_400_000 - 1);
+
+		let filename = dir.path().join("events.geojsonl");
+		let feature = |x: f64, time: &str| {
+			format!(
+				"{{\"type\":\"Feature\",\"properties\":{{\"time\":{}}},\"geometry\":{{\"type\":\"Point\",\"coordinates\":[{},1]}}}}",
+				time, x
+			)
+		};
+		let lines = [
+			feature(1., "\"1970-01-02T10:00:00Z\""),
+			feature(2., "\"1970-01-03\""),
+			feature(3., "86400000"),
+			feature(4., "null"),
+			feature(5., "\"soon\""),
+		];
+		write(&filename, lines.join("\n") + "\n")?;
+		let mut options = GeoFileOptions::empty();
+		options.time_property = Some(String::from("time"));
+		let mut geo_db = GeoDB::open(&filename, options, None)?;
+		geo_db.append(&[&feature(6., "\"1970-01-02T23:59:59.999Z\"")])?;
+
+		let mut expected = vec![
+			lines[0].clone(),
+			lines[2].clone(),
+			feature(6., "\"1970-01-02T23:59:59.999Z\""),
+		];
+		expected.sort();
+		assert_eq!(query_time(&geo_db, &all, Some(&day(1)), 1, None)?, expected);
+		assert_eq!(
+			query_time(&geo_db, &all, Some(&day(2)), 10, None)?,
+			vec![lines[1].clone()]
+		);
+		assert_eq!(query_time(&geo_db, &all, Some(&day(3)), 10, None)?.len(), 0);
+		// without a time range, features without a time are found, too
+		assert_eq!(query_time(&geo_db, &all, None, 10, None)?.len(), 6);
+		// time ranges combine with bboxes and filters
+		let filter = geo_db.get_filter("time = 86400000")?;
+		assert_eq!(
+			query_time(&geo_db, &all, Some(&day(1)), 10, Some(&filter))?,
+			vec![lines[2].clone()]
+		);
+		let bbox = GeoBBox::new(0.5, 1.5, 0., 10.);
+		assert_eq!(
+			query_time(&geo_db, &bbox, Some(&day(1)), 10, None)?,
+			vec![lines[0].clone()]
+		);
+
+		let filename = dir.path().join("events.csv");
+		write(&filename, "x,y,time\n1,1,1970-01-02\n2,2,1970-01-03 12:00\n")?;
+		let mut options = GeoFileOptions::new(",", 0, 1, 1);
+		options.time_property = Some(String::from("time"));
+		let mut geo_db = GeoDB::open(&filename, options, None)?;
+		geo_db.append(&["3,3,172800000"])?;
+		assert_eq!(
+			query_time(&geo_db, &all, Some(&day(2)), 10, None)?,
+			vec!["2,2,1970-01-03 12:00", "3,3,172800000"]
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn test_change_time_property() -> Result<(), Box<dyn Error>> {
+		let dir = TempDir::new()?;
+		let all = GeoBBox::new(0., 10., 0., 10.);
+		let day = TimeRange::new(86_400_000, 2 * 86_400_000 - 1);
+		let filename = dir.path().join("events.csv");
+		write(
+			&filename,
+			"x,y,time,other
+1,1,1970-01-02,1970-01-05
+2,2,1970-01-03,1970-01-02
+",
+		)?;
+		let mut geo_db = GeoDB::open(&filename, GeoFileOptions::new(",", 0, 1, 1), None)?;
+		geo_db.append(&["3,3,1970-01-02,1970-01-02"])?;
+		assert_eq!(query_time(&geo_db, &all, Some(&day), 10, None)?.len(), 0);
+
+		// the index and the appended segment both get the times of the new property
+		let options = |time_property: &str| {
+			let mut options = GeoFileOptions::new(",", 0, 1, 1);
+			options.time_property = Some(String::from(time_property));
+			options
+		};
+		let geo_db = GeoDB::open(&filename, options("time"), None)?;
+		assert_eq!(geo_db.segments.len(), 2);
+		assert_eq!(
+			query_time(&geo_db, &all, Some(&day), 10, None)?,
+			vec!["1,1,1970-01-02,1970-01-05", "3,3,1970-01-02,1970-01-02"]
+		);
+		let geo_db = GeoDB::open(&filename, options("other"), None)?;
+		assert_eq!(
+			query_time(&geo_db, &all, Some(&day), 10, None)?,
+			vec!["2,2,1970-01-03,1970-01-02", "3,3,1970-01-02,1970-01-02"]
+		);
+		let geo_db = GeoDB::open(&filename, GeoFileOptions::new(",", 0, 1, 1), None)?;
+		assert_eq!(query_time(&geo_db, &all, Some(&day), 10, None)?.len(), 0);
+		assert_eq!(query_time(&geo_db, &all, None, 10, None)?.len(), 3);
+		Ok(())
+	}
+
+	#[test]
+	fn test_builder_and_query() -> Result<(), Box<dyn Error>> {
+		let dir = TempDir::new()?;
+		let filename = dir.path().join("points.csv");
+		let mut data = String::from("name;x;y;land\n");
+		for i in 0..2500 {
+			data += &format!(
+				"p{};{};{};{}\n",
+				i,
+				i % 10,
+				i / 250,
+				if i % 2 == 0 { "BW" } else { "BY" }
+			);
+		}
+		write(&filename, data)?;
+		let mut geo_db = GeoDB::builder(&filename)
+			.separator(";")
+			.col_x(1)
+			.col_y(2)
+			.skip_lines(1)
+			.index_properties(&["land"])
+			.open()?;
+		geo_db.append(&["q;5;5;BW"])?;
+
+		// lines page through the whole query
+		let all = GeoBBox::new(0., 10., 0., 10.);
+		assert_eq!(geo_db.query(&all).lines().count(), 2501);
+		let mut lines: Vec<&[u8]> = geo_db.query(&all).filter("land = 'BW' and x = 5")?.lines().collect();
+		lines.sort();
+		assert_eq!(lines, vec![b"q;5;5;BW".as_slice()]);
+		assert!(geo_db.query(&all).filter("land = ").is_err());
+		assert_eq!(geo_db.query(&GeoBBox::new(20., 30., 20., 30.)).lines().count(), 0);
+
+		let features = geo_db
+			.query(&GeoBBox::new(4.5, 5.5, 4.5, 5.5))
+			.filter("name = 'q'")?
+			.features()
+			.collect::<Result<Vec<Feature>, _>>()?;
+		assert_eq!(features.len(), 1);
+		assert_eq!(
+			features[0].geometry.as_ref().map(|geometry| &geometry.value),
+			Some(&geojson::Value::Point(vec![5., 5.]))
+		);
+		assert_eq!(features[0].property("name"), Some(&geojson::JsonValue::from("q")));
+		assert_eq!(features[0].property("land"), Some(&geojson::JsonValue::from("BW")));
+		assert!(!features[0].contains_property("x"));
+
+		let filename = dir.path().join("events.geojsonl");
+		let line =
+			r#"{"type":"Feature","properties":{"time":"1970-01-02"},"geometry":{"type":"Point","coordinates":[1,1]}}"#;
+		write(&filename, format!("{}\n", line))?;
+		let geo_db = GeoDB::builder(&filename).time_property("time").open()?;
+		assert_eq!(geo_db.query(&all).time(TimeRange::instant(0)).lines().count(), 0);
+		let features = geo_db
+			.query(&all)
+			.time(TimeRange::instant(86_400_000))
+			.features()
+			.collect::<Result<Vec<Feature>, _>>()?;
+		assert_eq!(features, vec![Feature::from_str(line)?]);
+		assert!(geo_db.parse_feature(b"not json").is_err());
+		Ok(())
 	}
 }
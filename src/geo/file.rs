@@ -1,50 +1,109 @@
-use super::{GeoBBox, GeoNode};
-use brotli_decompressor::{self, BrotliDecompress};
+use super::{
+	crs::{Crs, Transform},
+	filter::{Field, Filter, LineFilter, RecordReader},
+	formats,
+	progress::{Progress, ProgressTracker},
+	GeoBBox, GeoEntry, TimeRange,
+};
+use brotli_decompressor::{self, BrotliDecompressStream, BrotliResult, BrotliState, StandardAlloc};
+use bzip2::read::MultiBzDecoder;
+use geojson::{feature::Id, Feature, JsonValue};
 use libflate::gzip::Decoder;
+use rayon::prelude::*;
 use std::{
 	error::Error,
 	ffi::OsStr,
 	fs::{read, File},
-	io::Read,
+	io::{Cursor, Error as IoError, ErrorKind, Read, Seek},
 	path::{Path, PathBuf},
 	result::Result,
-	str::from_utf8,
-	time::Instant,
+	str::{from_utf8, FromStr},
 };
+use xz2::read::XzDecoder;
+use zip::ZipArchive;
+
+type BboxExtractor = Box<dyn Fn(&str) -> Result<GeoBBox, LineError> + Send + Sync>;
+// Why a line couldn't be indexed. Lines are parsed in parallel, so the error must be sendable.
+type LineError = Box<dyn Error + Send + Sync>;
+pub type IdExtractor = Box<dyn Fn(&str) -> Option<String> + Send + Sync>;
+pub type TimeExtractor = Box<dyn Fn(&str) -> Option<i64> + Send + Sync>;
+pub type FeatureParser = Box<dyn Fn(&str) -> Result<Feature, Box<dyn Error>> + Send + Sync>;
+// The entries of a chunk of lines, the number of lines and the errors of invalid lines
+type ChunkEntries = (Vec<GeoEntry>, usize, Vec<(usize, String)>);
 
-type BboxExtractor = Box<dyn Fn(&str) -> GeoBBox>;
+// Size of the chunks that are parsed in parallel
+const CHUNK_SIZE: usize = 1 << 20;
+// Number of invalid lines listed in the error message
+const MAX_LISTED_ERRORS: usize = 10;
 
 enum Compression {
 	Brotli,
+	Bzip2,
 	Gzip,
+	Xz,
+	Zip,
+	Zstd,
 	None,
 }
 
-#[derive(Debug)]
+/// Options of [`GeoDB::open`](crate::GeoDB::open), usually set with [`GeoDB::builder`](crate::GeoDB::builder).
+/// Options that are None get their defaults. Start with [`GeoFileOptions::empty`], as more options may be added.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
 pub struct GeoFileOptions {
 	pub separator: Option<String>,
 	pub col_x: Option<usize>,
 	pub col_y: Option<usize>,
 	pub skip_lines: Option<usize>,
+	pub layer: Option<String>,
+	pub source_crs: Option<String>,
+	pub target_crs: Option<String>,
+	pub rewrite_geometries: Option<bool>,
+	pub index_type: Option<String>,
+	pub node_size: Option<usize>,
+	pub col_id: Option<usize>,
+	pub id_property: Option<String>,
+	pub index_properties: Option<Vec<String>>,
+	pub time_property: Option<String>,
 }
 
 impl GeoFileOptions {
-	#[allow(dead_code)]
+	/// Options of a CSV/TSV file
 	pub fn new(separator: &str, col_x: usize, col_y: usize, skip_lines: usize) -> Self {
 		Self {
 			separator: Some(separator.to_owned()),
 			col_x: Some(col_x),
 			col_y: Some(col_y),
 			skip_lines: Some(skip_lines),
+			layer: None,
+			source_crs: None,
+			target_crs: None,
+			rewrite_geometries: None,
+			index_type: None,
+			node_size: None,
+			col_id: None,
+			id_property: None,
+			index_properties: None,
+			time_property: None,
 		}
 	}
-	#[allow(dead_code)]
+	/// All options with their defaults
 	pub fn empty() -> Self {
 		Self {
 			separator: None,
 			col_x: None,
 			col_y: None,
 			skip_lines: None,
+			layer: None,
+			source_crs: None,
+			target_crs: None,
+			rewrite_geometries: None,
+			index_type: None,
+			node_size: None,
+			col_id: None,
+			id_property: None,
+			index_properties: None,
+			time_property: None,
 		}
 	}
 }
@@ -52,56 +111,270 @@ impl GeoFileOptions {
 pub struct GeoFile {
 	data: Vec<u8>,
 	extractor: BboxExtractor,
+	time_extractor: Option<TimeExtractor>,
+	time_property: Option<String>,
 	skip_lines: usize,
+	warnings: Vec<String>,
 }
 impl GeoFile {
 	pub fn load(filename: &Path, opt: GeoFileOptions) -> Result<Self, Box<dyn Error>> {
 		let (basename, compression) = GeoFile::get_compression(filename);
-		let extractor: BboxExtractor = GeoFile::get_extractor(&basename, &opt)?;
+		let mut warnings = Vec::new();
 
-		let data = match compression {
-			Compression::Brotli => {
-				let mut temp = Vec::new();
-				BrotliDecompress(&mut File::open(filename)?, &mut temp)?;
-				temp
-			}
-			Compression::Gzip => {
-				let mut temp = Vec::new();
-				Decoder::new(&File::open(filename)?)?.read_to_end(&mut temp)?;
-				temp
-			}
-			Compression::None => read(filename)?,
+		let data = match basename.extension().and_then(OsStr::to_str) {
+			Some("gpkg") => match compression {
+				// SQLite needs random access, so GeoPackages are read directly from disk
+				Compression::None => formats::gpkg::read_layer(filename, opt.layer.as_deref())?,
+				_ => {
+					return Err(Box::new(std::io::Error::new(
+						std::io::ErrorKind::InvalidInput,
+						format!(
+							"Compressed GeoPackage files are not supported: {}",
+							filename.to_string_lossy()
+						),
+					)))
+				}
+			},
+			Some("gpx") => formats::gpx::convert(&GeoFile::read_data(filename, compression, &mut warnings)?)?,
+			Some("kml") => formats::kml::convert(&GeoFile::read_data(filename, compression, &mut warnings)?)?,
+			Some("topojson") => formats::topojson::convert(
+				&GeoFile::read_data(filename, compression, &mut warnings)?,
+				opt.layer.as_deref(),
+			)?,
+			_ => GeoFile::read_data(filename, compression, &mut warnings)?,
+		};
+
+		let mut geo_file = GeoFile::from_data(filename, &opt, data, opt.skip_lines.unwrap_or(0))?;
+		geo_file.warnings = warnings;
+		Ok(geo_file)
+	}
+
+	// Create a GeoFile from decompressed lines, e.g. lines appended to an existing file.
+	// The filename only determines the format of the lines. Non-line formats are stored as GeoJSON lines.
+	pub fn from_data(
+		filename: &Path, opt: &GeoFileOptions, data: Vec<u8>, skip_lines: usize,
+	) -> Result<Self, Box<dyn Error>> {
+		let (basename, _) = GeoFile::get_compression(filename);
+		let transform = GeoFile::get_transform(opt)?;
+		let rewrite = opt.rewrite_geometries.unwrap_or(false);
+		// rewritten geometries are already in the target CRS, so the extractor mustn't transform again
+		let extractor: BboxExtractor =
+			GeoFile::get_extractor(&basename, opt, if rewrite { None } else { transform.clone() })?;
+
+		let data = match (rewrite, &transform) {
+			(true, Some(transform)) => GeoFile::reproject_data(&data, &basename, opt, skip_lines, transform)?,
+			_ => data,
 		};
 
 		Ok(Self {
 			data,
 			extractor,
-			skip_lines: opt.skip_lines.unwrap_or(0),
+			time_extractor: None,
+			time_property: opt.time_property.clone(),
+			skip_lines,
+			warnings: Vec::new(),
 		})
 	}
 
+	// Problems with the file that didn't prevent loading it, e.g. undecoded trailing bytes
+	pub fn warnings(&self) -> &[String] {
+		&self.warnings
+	}
+
+	// An explicit compression extension is trusted, other files are checked for magic bytes
+	fn read_data(
+		filename: &Path, compression: Compression, warnings: &mut Vec<String>,
+	) -> Result<Vec<u8>, Box<dyn Error>> {
+		match compression {
+			Compression::None => {
+				let data = read(filename)?;
+				match GeoFile::sniff_compression(&data) {
+					Some(compression) => GeoFile::decode(filename, Cursor::new(data), compression, warnings),
+					None => Ok(data),
+				}
+			}
+			compression => GeoFile::decode(filename, File::open(filename)?, compression, warnings),
+		}
+	}
+
+	fn decode<R: Read + Seek>(
+		filename: &Path, mut reader: R, compression: Compression, warnings: &mut Vec<String>,
+	) -> Result<Vec<u8>, Box<dyn Error>> {
+		let mut temp = Vec::new();
+		match compression {
+			Compression::None => {
+				reader.read_to_end(&mut temp)?;
+			}
+			Compression::Brotli => {
+				let mut data = Vec::new();
+				reader.read_to_end(&mut data)?;
+				GeoFile::decode_brotli(&data, &mut temp, warnings)?;
+			}
+			Compression::Bzip2 => {
+				MultiBzDecoder::new(reader).read_to_end(&mut temp)?;
+			}
+			Compression::Gzip => {
+				let mut data = Vec::new();
+				reader.read_to_end(&mut data)?;
+				GeoFile::decode_gzip(&data, &mut temp, warnings)?;
+			}
+			Compression::Xz => {
+				XzDecoder::new_multi_decoder(reader).read_to_end(&mut temp)?;
+			}
+			Compression::Zip => {
+				let mut archive = ZipArchive::new(reader)?;
+				let files: Vec<usize> = (0..archive.len())
+					.filter(|i| archive.by_index(*i).map(|f| f.is_file()).unwrap_or(false))
+					.collect();
+				if files.len() != 1 {
+					return Err(Box::new(IoError::new(
+						ErrorKind::InvalidInput,
+						format!(
+							"Zip archives must contain exactly one file: {}",
+							filename.to_string_lossy()
+						),
+					)));
+				}
+				archive.by_index(files[0])?.read_to_end(&mut temp)?;
+			}
+			Compression::Zstd => {
+				zstd::Decoder::new(reader)?.read_to_end(&mut temp)?;
+			}
+		};
+		Ok(temp)
+	}
+
+	// Decode all members of a gzip file, e.g. produced by "cat a.gz b.gz" or pigz
+	fn decode_gzip(data: &[u8], output: &mut Vec<u8>, warnings: &mut Vec<String>) -> Result<(), Box<dyn Error>> {
+		let mut cursor = Cursor::new(data);
+		loop {
+			let offset = cursor.position() as usize;
+			if offset >= data.len() {
+				break;
+			}
+			// the first member must be valid, anything after the last one might be garbage
+			if offset > 0 && !data[offset..].starts_with(&[0x1f, 0x8b]) {
+				// trailing zero bytes are common padding, anything else hints at a damaged file
+				if data[offset..].iter().any(|b| *b != 0) {
+					warnings.push(format!(
+						"{} trailing bytes after gzip data were not decoded",
+						data.len() - offset
+					));
+				}
+				break;
+			}
+			Decoder::new(&mut cursor)?.read_to_end(output)?;
+		}
+		Ok(())
+	}
+
+	// Decode one or more concatenated brotli streams
+	fn decode_brotli(data: &[u8], output: &mut Vec<u8>, warnings: &mut Vec<String>) -> Result<(), Box<dyn Error>> {
+		let mut buffer = vec![0u8; 65536];
+		let mut offset: usize = 0;
+
+		while offset < data.len() {
+			let mut state = BrotliState::new(
+				StandardAlloc::default(),
+				StandardAlloc::default(),
+				StandardAlloc::default(),
+			);
+			let stream_start = output.len();
+			let mut available_in = data.len() - offset;
+			let mut input_offset = offset;
+			let mut total_out: usize = 0;
+
+			let result = loop {
+				let mut available_out = buffer.len();
+				let mut output_offset: usize = 0;
+				let result = BrotliDecompressStream(
+					&mut available_in,
+					&mut input_offset,
+					data,
+					&mut available_out,
+					&mut output_offset,
+					&mut buffer,
+					&mut total_out,
+					&mut state,
+				);
+				output.extend_from_slice(&buffer[..output_offset]);
+				match result {
+					BrotliResult::NeedsMoreOutput => continue,
+					result => break result,
+				}
+			};
+
+			match result {
+				BrotliResult::ResultSuccess => offset = input_offset,
+				// the first stream must be valid, anything after it might be garbage
+				_ if offset > 0 => {
+					output.truncate(stream_start);
+					// trailing zero bytes are common padding, anything else hints at a damaged file
+					if data[offset..].iter().any(|b| *b != 0) {
+						warnings.push(format!(
+							"{} trailing bytes after brotli data were not decoded",
+							data.len() - offset
+						));
+					}
+					break;
+				}
+				BrotliResult::NeedsMoreInput => {
+					return Err(Box::new(IoError::new(
+						ErrorKind::UnexpectedEof,
+						"Unexpected end of brotli data",
+					)))
+				}
+				_ => return Err(Box::new(IoError::new(ErrorKind::InvalidData, "Invalid brotli data"))),
+			}
+		}
+		Ok(())
+	}
+
 	fn get_compression(filename: &Path) -> (PathBuf, Compression) {
-		match filename.extension().and_then(OsStr::to_str) {
-			Some("br") => (filename.with_extension(""), Compression::Brotli),
-			Some("gz") => (filename.with_extension(""), Compression::Gzip),
-			_ => (filename.to_path_buf(), Compression::None),
+		let compression = match filename.extension().and_then(OsStr::to_str) {
+			Some("br") => Compression::Brotli,
+			Some("bz2") => Compression::Bzip2,
+			Some("gz") => Compression::Gzip,
+			Some("xz") => Compression::Xz,
+			Some("zip") => Compression::Zip,
+			Some("zst") => Compression::Zstd,
+			_ => return (filename.to_path_buf(), Compression::None),
+		};
+		(filename.with_extension(""), compression)
+	}
+
+	// Whether the lines are CSV/TSV rows rather than GeoJSON features
+	pub fn is_csv(filename: &Path) -> bool {
+		let (basename, _) = GeoFile::get_compression(filename);
+		matches!(basename.extension().and_then(OsStr::to_str), Some("csv" | "tsv"))
+	}
+
+	// Detect the compression by magic bytes. Brotli has no magic bytes and can't be detected.
+	fn sniff_compression(data: &[u8]) -> Option<Compression> {
+		match &data[..data.len().min(6)] {
+			[0x1f, 0x8b, ..] => Some(Compression::Gzip),
+			[0x28, 0xb5, 0x2f, 0xfd, ..] => Some(Compression::Zstd),
+			[0xfd, b'7', b'z', b'X', b'Z', 0x00] => Some(Compression::Xz),
+			[b'B', b'Z', b'h', b'1'..=b'9', ..] => Some(Compression::Bzip2),
+			[b'P', b'K', 0x03, 0x04, ..] => Some(Compression::Zip),
+			_ => None,
 		}
 	}
 
-	fn get_extractor(filename: &Path, opt: &GeoFileOptions) -> Result<BboxExtractor, Box<dyn Error>> {
+	fn get_extractor(
+		filename: &Path, opt: &GeoFileOptions, transform: Option<Transform>,
+	) -> Result<BboxExtractor, Box<dyn Error>> {
+		// without any CRS given, coordinates are expected to be WGS84
+		let geographic = Crs::from_code(opt.target_crs.as_deref().unwrap_or("EPSG:4326"))?.is_geographic();
 		match filename.extension().and_then(OsStr::to_str) {
-			Some("geojsonl") => Ok(Box::new(make_bbox::from_geojson)),
-			Some("geojson") => Ok(Box::new(make_bbox::from_geojson)),
-			Some("csv") => Ok(make_bbox::make_from_csv(
-				opt.separator.clone().unwrap_or(String::from(",")),
-				opt.col_x.unwrap_or(0),
-				opt.col_y.unwrap_or(1),
-			)),
-			Some("tsv") => Ok(make_bbox::make_from_csv(
-				opt.separator.clone().unwrap_or(String::from("\t")),
-				opt.col_x.unwrap_or(0),
-				opt.col_y.unwrap_or(1),
-			)),
+			Some("geojsonl" | "geojson" | "gpx" | "kml" | "topojson") => {
+				Ok(make_bbox::make_from_geojson(transform, geographic))
+			}
+			Some("gpkg") => Ok(make_bbox::make_from_gpkg(transform, geographic)),
+			Some("csv" | "tsv") => {
+				let (separator, col_x, col_y) = GeoFile::get_csv_columns(filename, opt);
+				Ok(make_bbox::make_from_csv(separator, col_x, col_y, transform))
+			}
 			_ => Err(Box::new(std::io::Error::new(
 				std::io::ErrorKind::InvalidInput,
 				format!("Unsupported file extension: {}", filename.to_string_lossy()),
@@ -109,118 +382,567 @@ impl GeoFile {
 		}
 	}
 
+	// A description of the option the ids are taken from, stored with the id index
+	pub fn get_id_source(filename: &Path, opt: &GeoFileOptions) -> String {
+		if GeoFile::is_csv(filename) {
+			return format!(
+				"colId {}",
+				opt.col_id.map_or(String::new(), |col_id| col_id.to_string())
+			);
+		}
+		match &opt.id_property {
+			Some(property) => format!("idProperty {}", property),
+			None => String::from("id"),
+		}
+	}
+
+	// Create a function that extracts the id of a line: a CSV column, a GeoJSON property or the GeoJSON "id" member
+	pub fn get_id_extractor(filename: &Path, opt: &GeoFileOptions) -> Result<IdExtractor, Box<dyn Error>> {
+		let (basename, _) = GeoFile::get_compression(filename);
+		match basename.extension().and_then(OsStr::to_str) {
+			Some("csv" | "tsv") => match opt.col_id {
+				Some(col_id) => {
+					let (separator, _, _) = GeoFile::get_csv_columns(&basename, opt);
+					Ok(Box::new(move |line: &str| {
+						line.split(&separator).nth(col_id).map(String::from)
+					}))
+				}
+				None => Err(Box::new(IoError::new(
+					ErrorKind::InvalidInput,
+					"Features of CSV/TSV files need an id column",
+				))),
+			},
+			_ => {
+				let property = opt.id_property.clone();
+				Ok(Box::new(move |line: &str| {
+					let feature = Feature::from_str(line).ok()?;
+					match &property {
+						Some(property) => match feature.property(property)? {
+							JsonValue::String(id) => Some(id.clone()),
+							JsonValue::Null => None,
+							value => Some(value.to_string()),
+						},
+						None => match feature.id? {
+							Id::String(id) => Some(id),
+							Id::Number(id) => Some(id.to_string()),
+						},
+					}
+				}))
+			}
+		}
+	}
+
+	// Create a function that turns a line into a feature. CSV/TSV lines become points with the other columns
+	// as string properties, named by the header line or as "$n" by their column number.
+	pub fn get_feature_parser(filename: &Path, opt: &GeoFileOptions, header: Option<&str>) -> FeatureParser {
+		let (basename, _) = GeoFile::get_compression(filename);
+		match basename.extension().and_then(OsStr::to_str) {
+			Some("csv" | "tsv") => {
+				let (separator, col_x, col_y) = GeoFile::get_csv_columns(&basename, opt);
+				let columns = GeoFile::get_column_names(filename, opt, header).unwrap_or_default();
+				Box::new(move |line: &str| {
+					let fields: Vec<&str> = line.split(&separator).collect();
+					let coordinate = |col: usize| -> Result<f64, Box<dyn Error>> {
+						let field = fields.get(col).ok_or_else(|| {
+							IoError::new(ErrorKind::InvalidData, format!("Missing coordinate column {}", col))
+						})?;
+						Ok(field.trim().parse()?)
+					};
+					let point = geojson::Value::Point(vec![coordinate(col_x)?, coordinate(col_y)?]);
+					let properties = fields
+						.iter()
+						.enumerate()
+						.filter(|(col, _)| *col != col_x && *col != col_y)
+						.map(|(col, field)| {
+							let name = columns.get(col).cloned().unwrap_or_else(|| format!("${}", col));
+							(name, JsonValue::String(field.trim().trim_matches('"').to_string()))
+						})
+						.collect();
+					Ok(Feature {
+						bbox: None,
+						geometry: Some(geojson::Geometry::new(point)),
+						id: None,
+						properties: Some(properties),
+						foreign_members: None,
+					})
+				})
+			}
+			_ => Box::new(|line: &str| Ok(Feature::from_str(line)?)),
+		}
+	}
+
+	// Parse a filter expression for the lines of this format. CSV/TSV columns are named by the header line.
+	pub fn get_filter(
+		filename: &Path, opt: &GeoFileOptions, header: Option<&str>, expression: &str,
+	) -> Result<LineFilter, Box<dyn Error>> {
+		let columns = GeoFile::get_column_names(filename, opt, header);
+		Ok(LineFilter::new(
+			Filter::parse(expression, columns.as_deref())?,
+			GeoFile::get_record_reader(filename, opt),
+		))
+	}
+
+	// Create a function that extracts the time of a line from the timeProperty option, if it is set
+	pub fn get_time_extractor(
+		filename: &Path, opt: &GeoFileOptions, header: Option<&str>,
+	) -> Result<Option<TimeExtractor>, Box<dyn Error>> {
+		let name = match &opt.time_property {
+			Some(name) => name,
+			None => return Ok(None),
+		};
+		let columns = GeoFile::get_column_names(filename, opt, header);
+		let field = Field::parse(name, columns.as_deref())?;
+		let reader = GeoFile::get_record_reader(filename, opt);
+		Ok(Some(Box::new(move |line: &str| reader.read(line)?.time(&field))))
+	}
+
+	// Index the time of every line, see get_time_extractor
+	pub fn set_time_extractor(&mut self, time_extractor: Option<TimeExtractor>) {
+		self.time_extractor = time_extractor;
+	}
+
+	// The timeProperty option, if the time of every line is indexed
+	pub fn time_property(&self) -> Option<&str> {
+		self.time_extractor.as_ref().and(self.time_property.as_deref())
+	}
+
+	// The fields of the indexProperties option
+	pub fn get_index_fields(
+		filename: &Path, opt: &GeoFileOptions, header: Option<&str>,
+	) -> Result<Vec<Field>, Box<dyn Error>> {
+		let columns = GeoFile::get_column_names(filename, opt, header);
+		opt.index_properties
+			.iter()
+			.flatten()
+			.map(|name| Field::parse(name, columns.as_deref()))
+			.collect()
+	}
+
+	pub fn get_record_reader(filename: &Path, opt: &GeoFileOptions) -> RecordReader {
+		let (basename, _) = GeoFile::get_compression(filename);
+		match basename.extension().and_then(OsStr::to_str) {
+			Some("csv" | "tsv") => RecordReader::new(Some(GeoFile::get_csv_columns(&basename, opt).0)),
+			_ => RecordReader::new(None),
+		}
+	}
+
+	// Column names of CSV/TSV files from the header line, None for other formats
+	fn get_column_names(filename: &Path, opt: &GeoFileOptions, header: Option<&str>) -> Option<Vec<String>> {
+		let (basename, _) = GeoFile::get_compression(filename);
+		match basename.extension().and_then(OsStr::to_str) {
+			Some("csv" | "tsv") => {
+				let (separator, _, _) = GeoFile::get_csv_columns(&basename, opt);
+				Some(match header {
+					Some(header) => header
+						.split(&separator)
+						.map(|name| name.trim().trim_matches('"').to_string())
+						.collect(),
+					None => Vec::new(),
+				})
+			}
+			_ => None,
+		}
+	}
+
+	// Separator and coordinate columns of CSV/TSV files
+	fn get_csv_columns(filename: &Path, opt: &GeoFileOptions) -> (String, usize, usize) {
+		let default_separator = match filename.extension().and_then(OsStr::to_str) {
+			Some("tsv") => "\t",
+			_ => ",",
+		};
+		(
+			opt.separator.clone().unwrap_or(String::from(default_separator)),
+			opt.col_x.unwrap_or(0),
+			opt.col_y.unwrap_or(1),
+		)
+	}
+
+	// The coordinate transformation, if a source or target CRS is given. Both default to WGS84.
+	fn get_transform(opt: &GeoFileOptions) -> Result<Option<Transform>, Box<dyn Error>> {
+		if opt.source_crs.is_none() && opt.target_crs.is_none() {
+			return Ok(None);
+		}
+		Ok(Some(Transform::new(
+			opt.source_crs.as_deref().unwrap_or("EPSG:4326"),
+			opt.target_crs.as_deref().unwrap_or("EPSG:4326"),
+		)?))
+	}
+
+	// Rewrite the coordinates of all lines into the target CRS. Skipped header lines are kept as they are.
+	fn reproject_data(
+		data: &[u8], filename: &Path, opt: &GeoFileOptions, skip_lines: usize, transform: &Transform,
+	) -> Result<Vec<u8>, Box<dyn Error>> {
+		let data = data.strip_prefix(&[0xef, 0xbb, 0xbf]).unwrap_or(data);
+		let is_csv = matches!(filename.extension().and_then(OsStr::to_str), Some("csv" | "tsv"));
+		let (separator, col_x, col_y) = GeoFile::get_csv_columns(filename, opt);
+		let mut output: Vec<u8> = Vec::with_capacity(data.len());
+
+		for (line_no, line) in data.split(|b| *b == b'\n').enumerate() {
+			let line = from_utf8(line.strip_suffix(b"\r").unwrap_or(line))?;
+			if line_no < skip_lines || line.len() <= 1 {
+				output.extend_from_slice(line.as_bytes());
+			} else if is_csv {
+				output.extend_from_slice(reproject::csv_line(line, &separator, col_x, col_y, transform)?.as_bytes());
+			} else {
+				output.extend_from_slice(reproject::geojson_line(line, transform)?.as_bytes());
+			}
+			output.push(b'\n');
+		}
+		output.pop();
+		Ok(output)
+	}
+
+	#[cfg(test)]
 	pub fn read_range(&self, start: usize, length: usize) -> &[u8] {
 		&self.data[start..start + length]
 	}
 
-	pub fn get_entries(&self) -> Result<Vec<GeoNode>, Box<dyn Error>> {
-		let mut entries: Vec<GeoNode> = Vec::new();
-		let mut line_no: usize = 0;
-		let file_size: f64 = self.data.len() as f64 / 100.;
-		let start = Instant::now();
-		let mut current_pos: usize = 0;
-		let extractor = &self.extractor;
-
-		for i in 0..self.data.len() {
-			if self.data[i] == 10 {
-				// new line break
-
-				line_no += 1;
-
-				if line_no % 1000000 == 0 {
-					println!(
-						"get_entries: {}, {:.1}%, {:.0}/s, {:.1}MB/s",
-						line_no,
-						current_pos as f64 / file_size,
-						line_no as f64 / start.elapsed().as_secs_f64(),
-						current_pos as f64 / 1048576. / start.elapsed().as_secs_f64()
-					)
-				}
+	pub fn get_data(&self) -> &[u8] {
+		&self.data
+	}
+
+	// The first skipped line, e.g. the column names of a CSV file
+	pub fn get_header(&self) -> Option<&str> {
+		if self.skip_lines == 0 {
+			return None;
+		}
+		let data = self.data.strip_prefix(&[0xef, 0xbb, 0xbf]).unwrap_or(&self.data);
+		let end = data.iter().position(|b| *b == b'\n').unwrap_or(data.len());
+		from_utf8(&data[..end]).ok().map(|header| header.trim_end_matches('\r'))
+	}
+
+	pub fn get_entries(&self, progress: Option<&dyn Progress>) -> Result<Vec<GeoEntry>, Box<dyn Error>> {
+		let data = &self.data;
+		let file_size: f64 = data.len() as f64 / 100.;
+
+		// skip a UTF-8 byte order mark and the header lines
+		let mut pos: usize = if data.starts_with(&[0xef, 0xbb, 0xbf]) { 3 } else { 0 };
+		for _ in 0..self.skip_lines {
+			pos = match data[pos..].iter().position(|b| *b == b'\n') {
+				Some(i) => pos + i + 1,
+				None => data.len(),
+			};
+		}
 
-				if line_no > self.skip_lines {
-					let line = from_utf8(&self.data[current_pos..i])?;
-					if line.len() > 1 {
-						entries.push(GeoNode::new_leaf(extractor(line), current_pos, i - current_pos));
+		// cut the data into chunks ending at line breaks, so they can be parsed in parallel
+		let mut chunks: Vec<(usize, usize)> = Vec::new();
+		while pos < data.len() {
+			let end = match data[(pos + CHUNK_SIZE).min(data.len())..]
+				.iter()
+				.position(|b| *b == b'\n')
+			{
+				Some(i) => (pos + CHUNK_SIZE).min(data.len()) + i + 1,
+				None => data.len(),
+			};
+			chunks.push((pos, end));
+			pos = end;
+		}
+
+		// chunks are parsed in parallel batches, progress is reported in between on the calling thread
+		let tracker = ProgressTracker::new(progress, "entries");
+		let batch_size = rayon::current_num_threads() * 16;
+		let mut entries: Vec<GeoEntry> = Vec::new();
+		let mut errors: Vec<String> = Vec::new();
+		let mut error_count: usize = 0;
+		let mut line_count: usize = 0;
+		for batch in chunks.chunks(batch_size) {
+			let batch_entries = batch
+				.par_iter()
+				.map(|(chunk_start, chunk_end)| self.get_chunk_entries(*chunk_start, *chunk_end))
+				.collect::<Vec<ChunkEntries>>();
+			for (chunk_entries, lines, chunk_errors) in batch_entries {
+				entries.extend(chunk_entries);
+				// line numbers count from 1, including the skipped lines
+				for (line, error) in chunk_errors {
+					error_count += 1;
+					if errors.len() < MAX_LISTED_ERRORS {
+						errors.push(format!("line {}: {}", self.skip_lines + line_count + line + 1, error));
 					}
 				}
-
-				current_pos = i + 1;
+				line_count += lines;
 			}
+			let bytes = batch.last().unwrap().1;
+			tracker.report(line_count, bytes, bytes as f64 / file_size);
 		}
 
+		if error_count > 0 {
+			return Err(Box::new(IoError::new(
+				ErrorKind::InvalidData,
+				format!("{} invalid lines, {}", error_count, errors.join(", ")),
+			)));
+		}
 		Ok(entries)
 	}
+
+	// Extract the entries of all lines in a chunk, returning them with the number of lines and the errors
+	// of invalid lines, numbered from 0 within the chunk
+	fn get_chunk_entries(&self, chunk_start: usize, chunk_end: usize) -> ChunkEntries {
+		let mut entries: Vec<GeoEntry> = Vec::new();
+		let mut errors: Vec<(usize, String)> = Vec::new();
+		let mut line_count: usize = 0;
+		let data = &self.data;
+		let mut current_pos = chunk_start;
+
+		for i in chunk_start..=chunk_end {
+			if i < chunk_end && data[i] != b'\n' {
+				continue;
+			}
+			if i == chunk_end && current_pos == chunk_end {
+				// the chunk ends with a line break
+				break;
+			}
+
+			// new line break or end of file, the range excludes the CR of CRLF line endings
+			let end = if i > current_pos && data[i - 1] == b'\r' {
+				i - 1
+			} else {
+				i
+			};
+
+			let bbox = from_utf8(&data[current_pos..end])
+				.map_err(LineError::from)
+				.and_then(|line| match line.len() > 1 {
+					true => (self.extractor)(line).map(|bbox| Some((line, bbox))),
+					false => Ok(None),
+				});
+			match bbox {
+				Ok(Some((line, bbox))) => {
+					let mut leaves = GeoEntry::new_leaves(bbox, current_pos, end - current_pos);
+					if let Some(time_extractor) = &self.time_extractor {
+						let time = time_extractor(line).map(TimeRange::instant);
+						leaves.iter_mut().for_each(|entry| entry.time = time);
+					}
+					entries.extend(leaves);
+				}
+				Ok(None) => {}
+				Err(error) => errors.push((line_count, error.to_string())),
+			}
+
+			line_count += 1;
+			current_pos = i + 1;
+		}
+
+		(entries, line_count, errors)
+	}
 }
 
 mod make_bbox {
-	use super::BboxExtractor;
-	use crate::geo::GeoBBox;
+	use super::{BboxExtractor, LineError};
+	use crate::geo::{crs::Transform, GeoBBox};
 	use geojson::Feature;
 	use std::str::FromStr;
 
-	// Create a GeoBBox from a geojson String
-	pub fn from_geojson(line: &str) -> GeoBBox {
-		let feature = Feature::from_str(line).unwrap();
-		from_geometry(&feature.geometry.unwrap())
+	// How coordinates are read: optionally reprojected, and with longitudes optionally shifted into 0..360
+	struct Coords<'a> {
+		transform: Option<&'a Transform>,
+		shift: bool,
+	}
+
+	impl Coords<'_> {
+		fn get(&self, v1: &[f64]) -> Result<(f64, f64), LineError> {
+			let (x, y) = match (v1, self.transform) {
+				([x, y, ..], Some(transform)) => transform.apply(*x, *y),
+				([x, y, ..], None) => (*x, *y),
+				_ => return Err(format!("invalid position: {:?}", v1).into()),
+			};
+			if !x.is_finite() || !y.is_finite() {
+				return Err(format!("invalid position: {:?}", v1).into());
+			}
+			Ok(if self.shift && x < 0.0 { (x + 360.0, y) } else { (x, y) })
+		}
+	}
+
+	// Create an extractor for geojson lines, reprojecting all coordinates if a transform is given.
+	// For geographic coordinates, features crossing the antimeridian get wrapped bboxes.
+	pub fn make_from_geojson(transform: Option<Transform>, geographic: bool) -> BboxExtractor {
+		Box::new(move |line: &str| from_geojson(line, transform.as_ref(), geographic))
+	}
+
+	// Create an extractor for features of a GeoPackage. The envelope of the geometry header, which the reader
+	// stores as "bbox" member, is used as is, only geometries without envelope are computed.
+	pub fn make_from_gpkg(transform: Option<Transform>, geographic: bool) -> BboxExtractor {
+		Box::new(move |line: &str| {
+			let feature = Feature::from_str(line)?;
+			match &feature.bbox {
+				Some(envelope) => from_envelope(envelope, transform.as_ref()),
+				None => from_feature(feature, transform.as_ref(), geographic),
+			}
+		})
+	}
+
+	// Create a GeoBBox from a geojson String. It is always computed from the geometry, as a "bbox" member
+	// of the feature might be outdated.
+	fn from_geojson(line: &str, transform: Option<&Transform>, geographic: bool) -> Result<GeoBBox, LineError> {
+		from_feature(Feature::from_str(line)?, transform, geographic)
+	}
+
+	fn from_feature(feature: Feature, transform: Option<&Transform>, geographic: bool) -> Result<GeoBBox, LineError> {
+		let geometry = feature.geometry.ok_or("feature without geometry")?;
+		let bbox = from_geometry(
+			&geometry,
+			&Coords {
+				transform,
+				shift: false,
+			},
+		)?;
+		if !geographic || bbox.width() <= 180.0 {
+			return Ok(bbox);
+		}
+		// a feature spanning more than half of the world might rather cross the antimeridian,
+		// so use the narrower of both envelopes
+		let shifted = from_geometry(&geometry, &Coords { transform, shift: true })?;
+		Ok(if shifted.width() < bbox.width() {
+			shifted.unshift_longitudes()
+		} else {
+			bbox
+		})
+	}
+
+	// Create a GeoBBox from a GeoJSON bbox member, [x_min, y_min, x_max, y_max] or with z values
+	// [x_min, y_min, z_min, x_max, y_max, z_max]. Reprojected, it includes all four corners.
+	fn from_envelope(envelope: &[f64], transform: Option<&Transform>) -> Result<GeoBBox, LineError> {
+		let (min, max) = match envelope.len() {
+			4 => ([envelope[0], envelope[1]], [envelope[2], envelope[3]]),
+			6 => ([envelope[0], envelope[1]], [envelope[3], envelope[4]]),
+			_ => return Err(format!("invalid bbox: {:?}", envelope).into()),
+		};
+		let coords = Coords {
+			transform,
+			shift: false,
+		};
+		let mut bbox = GeoBBox::new_empty();
+		for corner in [min, [max[0], min[1]], [min[0], max[1]], max] {
+			bbox.include_bbox(&from_vec(&corner, &coords)?);
+		}
+		Ok(bbox)
 	}
 
 	// Create a GeoBBox from a geojson::Geometry value
-	fn from_geometry(geometry: &geojson::Geometry) -> GeoBBox {
+	fn from_geometry(geometry: &geojson::Geometry, coords: &Coords) -> Result<GeoBBox, LineError> {
 		match &geometry.value {
-			geojson::Value::Point(c) => from_vec(c),
-			geojson::Value::MultiPoint(c) => from_vec2(c),
-			geojson::Value::LineString(c) => from_vec2(c),
-			geojson::Value::MultiLineString(c) => from_vec3(c),
-			geojson::Value::Polygon(c) => from_vec3(c),
-			geojson::Value::MultiPolygon(c) => from_vec4(c),
+			geojson::Value::Point(c) => from_vec(c, coords),
+			geojson::Value::MultiPoint(c) => from_vec2(c, coords),
+			geojson::Value::LineString(c) => from_vec2(c, coords),
+			geojson::Value::MultiLineString(c) => from_vec3(c, coords),
+			geojson::Value::Polygon(c) => from_vec3(c, coords),
+			geojson::Value::MultiPolygon(c) => from_vec4(c, coords),
 			geojson::Value::GeometryCollection(c) => {
 				let mut bbox = GeoBBox::new_empty();
-				c.iter()
-					.for_each(|geometry| bbox.include_bbox(&from_geometry(geometry)));
-				bbox
+				for geometry in c {
+					bbox.include_bbox(&from_geometry(geometry, coords)?);
+				}
+				Ok(bbox)
 			}
 		}
 	}
 
 	// Create a GeoBBox from a 4D vector, encapsulating all points in the vector
-	fn from_vec4(v4: &[Vec<Vec<Vec<f64>>>]) -> GeoBBox {
+	fn from_vec4(v4: &[Vec<Vec<Vec<f64>>>], coords: &Coords) -> Result<GeoBBox, LineError> {
 		let mut bbox = GeoBBox::new_empty();
-		v4.iter().for_each(|v3| bbox.include_bbox(&from_vec3(v3)));
-		bbox
+		for v3 in v4 {
+			bbox.include_bbox(&from_vec3(v3, coords)?);
+		}
+		Ok(bbox)
 	}
 
 	// Create a GeoBBox from a 3D vector, encapsulating all points in the vector
-	fn from_vec3(v3: &[Vec<Vec<f64>>]) -> GeoBBox {
+	fn from_vec3(v3: &[Vec<Vec<f64>>], coords: &Coords) -> Result<GeoBBox, LineError> {
 		let mut bbox = GeoBBox::new_empty();
-		v3.iter().for_each(|v2| bbox.include_bbox(&from_vec2(v2)));
-		bbox
+		for v2 in v3 {
+			bbox.include_bbox(&from_vec2(v2, coords)?);
+		}
+		Ok(bbox)
 	}
 
 	// Create a GeoBBox from a 2D vector, encapsulating all points in the vector
-	fn from_vec2(v2: &[Vec<f64>]) -> GeoBBox {
+	fn from_vec2(v2: &[Vec<f64>], coords: &Coords) -> Result<GeoBBox, LineError> {
 		let mut bbox = GeoBBox::new_empty();
-		v2.iter().for_each(|v1| bbox.include_point(v1[0] as f32, v1[1] as f32));
-		bbox
+		for v1 in v2 {
+			bbox.include_bbox(&from_vec(v1, coords)?);
+		}
+		Ok(bbox)
 	}
 
 	// Create a GeoBBox from a 1D vector, treating both x and y as same
-	fn from_vec(v1: &[f64]) -> GeoBBox {
-		GeoBBox::new_point(v1[0] as f32, v1[1] as f32)
+	fn from_vec(v1: &[f64], coords: &Coords) -> Result<GeoBBox, LineError> {
+		let (x, y) = coords.get(v1)?;
+		Ok(GeoBBox::from_f64_point(x, y))
 	}
 
-	pub fn make_from_csv(separator: String, col_x: usize, col_y: usize) -> BboxExtractor {
-		Box::new(move |line: &str| -> GeoBBox {
+	pub fn make_from_csv(separator: String, col_x: usize, col_y: usize, transform: Option<Transform>) -> BboxExtractor {
+		Box::new(move |line: &str| -> Result<GeoBBox, LineError> {
 			let fields: Vec<&str> = line.split(&separator).collect();
-			let x: f32 = fields[col_x].parse().unwrap();
-			let y: f32 = fields[col_y].parse().unwrap();
-			GeoBBox::new_point(x, y)
+			let coordinate = |col: usize| -> Result<f64, LineError> {
+				let field = fields
+					.get(col)
+					.ok_or_else(|| format!("missing coordinate column {}", col))?;
+				Ok(field.parse().map_err(|_| format!("invalid coordinate: {}", field))?)
+			};
+			let (x, y) = (coordinate(col_x)?, coordinate(col_y)?);
+			let (x, y) = match &transform {
+				Some(transform) => transform.apply(x, y),
+				None => (x, y),
+			};
+			if !x.is_finite() || !y.is_finite() {
+				return Err(format!("invalid coordinates: {}, {}", x, y).into());
+			}
+			Ok(GeoBBox::from_f64_point(x, y))
 		})
 	}
 }
 
+// Rewrite the coordinates of stored lines into the target CRS
+mod reproject {
+	use crate::geo::crs::Transform;
+	use geojson::{Feature, Position, Value};
+	use std::{error::Error, str::FromStr};
+
+	pub fn geojson_line(line: &str, transform: &Transform) -> Result<String, Box<dyn Error>> {
+		let mut feature = Feature::from_str(line)?;
+		feature.bbox = None;
+		if let Some(geometry) = feature.geometry.as_mut() {
+			geometry.bbox = None;
+			geometry_value(&mut geometry.value, transform);
+		}
+		Ok(feature.to_string())
+	}
+
+	fn geometry_value(value: &mut Value, transform: &Transform) {
+		match value {
+			Value::Point(c) => position(c, transform),
+			Value::MultiPoint(c) | Value::LineString(c) => c.iter_mut().for_each(|p| position(p, transform)),
+			Value::MultiLineString(c) | Value::Polygon(c) => c.iter_mut().flatten().for_each(|p| position(p, transform)),
+			Value::MultiPolygon(c) => c.iter_mut().flatten().flatten().for_each(|p| position(p, transform)),
+			Value::GeometryCollection(c) => c
+				.iter_mut()
+				.for_each(|geometry| geometry_value(&mut geometry.value, transform)),
+		}
+	}
+
+	fn position(position: &mut Position, transform: &Transform) {
+		let (x, y) = transform.apply(position[0], position[1]);
+		position[0] = x;
+		position[1] = y;
+	}
+
+	pub fn csv_line(
+		line: &str, separator: &str, col_x: usize, col_y: usize, transform: &Transform,
+	) -> Result<String, Box<dyn Error>> {
+		let mut fields: Vec<String> = line.split(separator).map(String::from).collect();
+		if fields.len() <= col_x.max(col_y) {
+			return Err(format!("missing coordinate column in line: {}", line).into());
+		}
+		let (x, y) = transform.apply(fields[col_x].parse()?, fields[col_y].parse()?);
+		fields[col_x] = x.to_string();
+		fields[col_y] = y.to_string();
+		Ok(fields.join(separator))
+	}
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
+	use crate::geo::GeoNode;
 	use std::path::PathBuf;
 
 	// Testing GeoFileOptions struct
@@ -254,17 +976,17 @@ mod tests {
 			"\n9.82855,48.18889\n9.8237,48.18951\n9.8251,48.19072\n"
 		);
 
-		let entries = geo_file.get_entries()?;
+		let entries = geo_file.get_entries(None)?;
 		assert_eq!(entries.len(), 100000);
 
 		assert_eq!(
-			entries[0],
-			GeoNode::new_leaf(GeoBBox::new_point(11.39979, 52.47553), 0, 17)
+			entries[0].leaf,
+			GeoNode::new_leaf(GeoBBox::from_f64_point(11.39979, 52.47553), 0, 17)
 		);
 
 		assert_eq!(
-			entries[entries.len() - 1],
-			GeoNode::new_leaf(GeoBBox::new_point(9.8251, 48.19072), 1719773, 15)
+			entries[entries.len() - 1].leaf,
+			GeoNode::new_leaf(GeoBBox::from_f64_point(9.8251, 48.19072), 1719773, 15)
 		);
 
 		Ok(())
@@ -290,19 +1012,336 @@ mod tests {
 			",[13.348388671875,52.52004009949795]]]}}\n"
 		);
 
-		let entries = geo_file.get_entries()?;
+		let entries = geo_file.get_entries(None)?;
 		assert_eq!(entries.len(), 3578);
 
 		assert_eq!(
-			entries[0],
-			GeoNode::new_leaf(GeoBBox::new(8.709154, 8.710508, 47.935436, 47.936913), 0, 566)
+			entries[0].leaf,
+			GeoNode::new_leaf(GeoBBox::new(8.709154, 8.710509, 47.935432, 47.936916), 0, 566)
+		);
+
+		assert_eq!(
+			entries[entries.len() - 1].leaf,
+			GeoNode::new_leaf(GeoBBox::new(13.348389, 13.359375, 52.519386, 52.520916), 2901065, 1377)
+		);
+
+		Ok(())
+	}
+
+	// Testing loading a GeoPackage layer
+	#[test]
+	fn geo_file_load_gpkg() -> Result<(), Box<dyn Error>> {
+		let filename = assert_fs::NamedTempFile::new("test.gpkg")?;
+		formats::gpkg::tests::create_geopackage(filename.path())?;
+
+		let geo_file = GeoFile::load(filename.path(), GeoFileOptions::empty())?;
+		let entries = geo_file.get_entries(None)?;
+		assert_eq!(entries.len(), 2);
+		assert_eq!(entries[0].leaf.bbox, GeoBBox::from_f64_point(9.18, 48.78));
+		assert_eq!(entries[1].leaf.bbox, GeoBBox::from_f64_point(8.27, 50.0));
+
+		// the envelope of the geometry header is indexed, even if it is larger than the geometry
+		let mut blob = formats::gpkg::tests::point_blob(9.18, 48.78, true);
+		for (i, v) in [9.0f64, 10.0, 48.0, 49.0].iter().enumerate() {
+			blob[8 + i * 8..16 + i * 8].copy_from_slice(&v.to_le_bytes());
+		}
+		rusqlite::Connection::open(filename.path())?
+			.execute("INSERT INTO cities VALUES (4, ?1, 'Envelope', 0)", [blob])?;
+		let geo_file = GeoFile::load(filename.path(), GeoFileOptions::empty())?;
+		let entries = geo_file.get_entries(None)?;
+		assert_eq!(entries.len(), 3);
+		assert_eq!(entries[2].leaf.bbox, GeoBBox::new(9.0, 10.0, 48.0, 49.0));
+
+		let mut options = GeoFileOptions::empty();
+		options.layer = Some(String::from("rivers"));
+		let geo_file = GeoFile::load(filename.path(), options)?;
+		let entries = geo_file.get_entries(None)?;
+		assert_eq!(entries.len(), 1);
+		assert_eq!(entries[0].leaf.bbox, GeoBBox::from_f64_point(7.6, 50.36));
+
+		Ok(())
+	}
+
+	// Testing loading a GPX file
+	#[test]
+	fn geo_file_load_gpx() -> Result<(), Box<dyn Error>> {
+		let filename = assert_fs::NamedTempFile::new("test.gpx")?;
+		std::fs::write(
+			filename.path(),
+			"<gpx><wpt lat=\"48.78\" lon=\"9.18\"/><trk><trkseg><trkpt lat=\"1\" lon=\"2\"/><trkpt lat=\"3\" lon=\"4\"/></trkseg></trk></gpx>",
+		)?;
+
+		let geo_file = GeoFile::load(filename.path(), GeoFileOptions::empty())?;
+		let entries = geo_file.get_entries(None)?;
+		assert_eq!(entries.len(), 2);
+		assert_eq!(entries[0].leaf.bbox, GeoBBox::from_f64_point(9.18, 48.78));
+		assert_eq!(entries[1].leaf.bbox, GeoBBox::new(2.0, 4.0, 1.0, 3.0));
+
+		Ok(())
+	}
+
+	// Testing all compression codecs, given by the extension or detected by magic bytes
+	#[test]
+	fn geo_file_load_compressed() -> Result<(), Box<dyn Error>> {
+		use std::io::Write;
+
+		let csv = b"1,2\n3,4\n";
+		let mut gzip = libflate::gzip::Encoder::new(Vec::new())?;
+		gzip.write_all(csv)?;
+		let mut xz = xz2::write::XzEncoder::new(Vec::new(), 6);
+		xz.write_all(csv)?;
+		let mut bzip2 = bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::default());
+		bzip2.write_all(csv)?;
+		let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+		zip.start_file("points.csv", zip::write::FileOptions::default())?;
+		zip.write_all(csv)?;
+
+		let files: Vec<(&str, Vec<u8>)> = vec![
+			("points.csv.gz", gzip.finish().into_result()?),
+			("points.csv.zst", zstd::encode_all(&csv[..], 3)?),
+			("points.csv.xz", xz.finish()?),
+			("points.csv.bz2", bzip2.finish()?),
+			("points.csv.zip", zip.finish()?.into_inner()),
+			("points.csv", zstd::encode_all(&csv[..], 3)?),
+			("points.csv", csv.to_vec()),
+		];
+
+		for (name, content) in files {
+			let filename = assert_fs::NamedTempFile::new(name)?;
+			std::fs::write(filename.path(), content)?;
+			let geo_file = GeoFile::load(filename.path(), GeoFileOptions::empty())?;
+			assert_eq!(geo_file.data, csv, "{}", name);
+			assert_eq!(geo_file.get_entries(None)?.len(), 2);
+		}
+
+		// an explicit extension wins over magic bytes, since brotli data can't be told apart
+		let mut gzip = libflate::gzip::Encoder::new(Vec::new())?;
+		gzip.write_all(csv)?;
+		let filename = assert_fs::NamedTempFile::new("points.csv.br")?;
+		std::fs::write(filename.path(), gzip.finish().into_result()?)?;
+		assert!(GeoFile::load(filename.path(), GeoFileOptions::empty()).is_err());
+
+		Ok(())
+	}
+
+	// Testing multi-member gzip and concatenated brotli streams, e.g. from "cat a.gz b.gz"
+	#[test]
+	fn geo_file_load_concatenated_streams() -> Result<(), Box<dyn Error>> {
+		use std::io::Write;
+
+		let gzip = |data: &[u8]| -> Result<Vec<u8>, Box<dyn Error>> {
+			let mut encoder = libflate::gzip::Encoder::new(Vec::new())?;
+			encoder.write_all(data)?;
+			Ok(encoder.finish().into_result()?)
+		};
+		let brotli = |data: &[u8]| -> Result<Vec<u8>, Box<dyn Error>> {
+			let mut output = Vec::new();
+			brotli::BrotliCompress(&mut &data[..], &mut output, &Default::default())?;
+			Ok(output)
+		};
+
+		let files: Vec<(&str, Vec<u8>, usize)> = vec![
+			("points.csv.gz", [gzip(b"1,2\n")?, gzip(b"3,4\n")?].concat(), 0),
+			(
+				"points.csv.gz",
+				[gzip(b"1,2\n")?, gzip(b"3,4\n")?, vec![0; 10]].concat(),
+				0,
+			),
+			(
+				"points.csv.gz",
+				[gzip(b"1,2\n3,4\n")?, b"garbage after the gzip member".to_vec()].concat(),
+				1,
+			),
+			("points.csv.br", [brotli(b"1,2\n")?, brotli(b"3,4\n")?].concat(), 0),
+			("points.csv.br", [brotli(b"1,2\n3,4\n")?, vec![0; 10]].concat(), 0),
+			(
+				"points.csv.br",
+				[brotli(b"1,2\n3,4\n")?, b"garbage".to_vec()].concat(),
+				1,
+			),
+		];
+
+		for (name, content, warnings) in files {
+			let filename = assert_fs::NamedTempFile::new(name)?;
+			std::fs::write(filename.path(), content)?;
+			let geo_file = GeoFile::load(filename.path(), GeoFileOptions::empty())?;
+			assert_eq!(geo_file.data, b"1,2\n3,4\n");
+			assert_eq!(geo_file.warnings().len(), warnings);
+		}
+
+		let files: Vec<(&str, Vec<u8>)> = vec![
+			("points.csv.gz", gzip(b"1,2\n3,4\n")?[0..5].to_vec()),
+			("points.csv.br", brotli(b"1,2\n3,4\n")?[0..5].to_vec()),
+		];
+		for (name, content) in files {
+			let filename = assert_fs::NamedTempFile::new(name)?;
+			std::fs::write(filename.path(), content)?;
+			assert!(
+				GeoFile::load(filename.path(), GeoFileOptions::empty()).is_err(),
+				"{}",
+				name
+			);
+		}
+
+		Ok(())
+	}
+
+	// Testing line framing with LF, CRLF, a missing final line break and a byte order mark
+	#[test]
+	fn geo_file_line_endings() -> Result<(), Box<dyn Error>> {
+		let files: Vec<&[u8]> = vec![
+			b"x,y\n1,2\n3,4\n",
+			b"x,y\n1,2\n3,4",
+			b"x,y\r\n1,2\r\n3,4\r\n",
+			b"x,y\r\n1,2\r\n3,4",
+			b"\xef\xbb\xbfx,y\n1,2\r\n\r\n3,4",
+		];
+
+		for content in files {
+			let filename = assert_fs::NamedTempFile::new("points.csv")?;
+			std::fs::write(filename.path(), content)?;
+			let geo_file = GeoFile::load(filename.path(), GeoFileOptions::new(",", 0, 1, 1))?;
+			let entries = geo_file.get_entries(None)?;
+			let lines: Vec<&[u8]> = entries
+				.iter()
+				.map(|e| geo_file.read_range(e.leaf.value1, e.leaf.value2))
+				.collect();
+			assert_eq!(lines, vec![b"1,2", b"3,4"]);
+			assert_eq!(entries[1].leaf.bbox, GeoBBox::from_f64_point(3.0, 4.0));
+		}
+
+		Ok(())
+	}
+
+	// Testing reprojection of bboxes and stored geometries from UTM32N to WGS84
+	#[test]
+	fn geo_file_reproject() -> Result<(), Box<dyn Error>> {
+		let (x, y) = Transform::new("EPSG:4326", "EPSG:25832")?.apply(9.18, 48.78);
+		let csv = format!("name;x;y\r\nStuttgart;{};{}\r\n", x, y);
+		let geojson = format!(
+			"{{\"type\":\"Feature\",\"bbox\":[0,0,1,1],\"properties\":{{}},\"geometry\":{{\"type\":\"Point\",\"coordinates\":[{},{}]}}}}\n",
+			x, y
+		);
+
+		for rewrite in [false, true] {
+			let mut options = GeoFileOptions::new(";", 1, 2, 1);
+			options.source_crs = Some(String::from("EPSG:25832"));
+			options.rewrite_geometries = Some(rewrite);
+			let filename = assert_fs::NamedTempFile::new("points.csv")?;
+			std::fs::write(filename.path(), &csv)?;
+			let geo_file = GeoFile::load(filename.path(), options)?;
+			let entries = geo_file.get_entries(None)?;
+			assert_eq!(
+				entries,
+				vec![GeoEntry::new(GeoNode::new_leaf(
+					GeoBBox::from_f64_point(9.18, 48.78),
+					entries[0].leaf.value1,
+					entries[0].leaf.value2
+				))]
+			);
+
+			let line = from_utf8(geo_file.read_range(entries[0].leaf.value1, entries[0].leaf.value2))?;
+			if rewrite {
+				assert!(line.starts_with("Stuttgart;9.1"));
+			} else {
+				assert!(line.starts_with("Stuttgart;51"));
+			}
+
+			let mut options = GeoFileOptions::empty();
+			options.source_crs = Some(String::from("EPSG:25832"));
+			options.rewrite_geometries = Some(rewrite);
+			let filename = assert_fs::NamedTempFile::new("points.geojsonl")?;
+			std::fs::write(filename.path(), &geojson)?;
+			let geo_file = GeoFile::load(filename.path(), options)?;
+			let entries = geo_file.get_entries(None)?;
+			assert_eq!(entries.len(), 1);
+			assert_eq!(entries[0].leaf.bbox, GeoBBox::from_f64_point(9.18, 48.78));
+			let line = from_utf8(geo_file.read_range(entries[0].leaf.value1, entries[0].leaf.value2))?;
+			assert_eq!(line.contains("\"coordinates\":[9.1"), rewrite);
+		}
+
+		Ok(())
+	}
+
+	// Testing that an outdated "bbox" member doesn't override the geometry
+	#[test]
+	fn geo_file_ignores_bbox_member() -> Result<(), Box<dyn Error>> {
+		let filename = assert_fs::NamedTempFile::new("moved.geojsonl")?;
+		std::fs::write(
+			filename.path(),
+			"{\"type\":\"Feature\",\"bbox\":[1,1,2,2],\"properties\":{},\"geometry\":{\"type\":\"Point\",\"coordinates\":[9.18,48.78]}}\n",
+		)?;
+		let entries = GeoFile::load(filename.path(), GeoFileOptions::empty())?.get_entries(None)?;
+		assert_eq!(entries[0].leaf.bbox, GeoBBox::from_f64_point(9.18, 48.78));
+		Ok(())
+	}
+
+	// Testing features crossing the antimeridian
+	#[test]
+	fn geo_file_antimeridian() -> Result<(), Box<dyn Error>> {
+		let filename = assert_fs::NamedTempFile::new("pacific.geojsonl")?;
+		std::fs::write(
+			filename.path(),
+			concat!(
+				"{\"type\":\"Feature\",\"properties\":{},\"geometry\":{\"type\":\"LineString\",\"coordinates\":[[170,0],[-170,10]]}}\n",
+				"{\"type\":\"Feature\",\"properties\":{},\"geometry\":{\"type\":\"LineString\",\"coordinates\":[[-10,0],[10,10]]}}\n",
+				"{\"type\":\"Feature\",\"properties\":{},\"geometry\":{\"type\":\"MultiPoint\",\"coordinates\":[[175,0],[-175,10]]}}\n",
+			),
+		)?;
+		let entries = GeoFile::load(filename.path(), GeoFileOptions::empty())?.get_entries(None)?;
+		assert_eq!(entries.len(), 5);
+		assert_eq!(entries[0].leaf.bbox, GeoBBox::new(170.0, 180.0, 0.0, 10.0));
+		assert_eq!(entries[1].leaf.bbox, GeoBBox::new(-180.0, -170.0, 0.0, 10.0));
+		assert_eq!(entries[1].twin, Some(entries[0].leaf.bbox.clone()));
+		assert_eq!(entries[2].leaf.bbox, GeoBBox::new(-10.0, 10.0, 0.0, 10.0));
+		assert_eq!(entries[3].leaf.bbox, GeoBBox::new(175.0, 180.0, 0.0, 10.0));
+		assert_eq!(entries[4].leaf.bbox, GeoBBox::new(-180.0, -175.0, 0.0, 10.0));
+
+		// projected coordinates are never wrapped
+		let mut options = GeoFileOptions::empty();
+		options.source_crs = Some(String::from("EPSG:3857"));
+		options.target_crs = Some(String::from("EPSG:3857"));
+		let entries = GeoFile::load(filename.path(), options)?.get_entries(None)?;
+		assert_eq!(entries.len(), 3);
+		assert_eq!(entries[0].leaf.bbox, GeoBBox::new(-170.0, 170.0, 0.0, 10.0));
+
+		Ok(())
+	}
+
+	// Testing id extraction
+	#[test]
+	fn geo_file_id_extractor() -> Result<(), Box<dyn Error>> {
+		let geojson = GeoFile::get_id_extractor(Path::new("a.geojsonl.gz"), &GeoFileOptions::empty())?;
+		assert_eq!(
+			geojson("{\"type\":\"Feature\",\"id\":\"a\",\"properties\":{},\"geometry\":null}"),
+			Some(String::from("a"))
+		);
+		assert_eq!(
+			geojson("{\"type\":\"Feature\",\"id\":5,\"properties\":{},\"geometry\":null}"),
+			Some(String::from("5"))
+		);
+		assert_eq!(
+			geojson("{\"type\":\"Feature\",\"properties\":{},\"geometry\":null}"),
+			None
 		);
 
+		let mut options = GeoFileOptions::empty();
+		options.id_property = Some(String::from("ags"));
+		let property = GeoFile::get_id_extractor(Path::new("a.gpkg"), &options)?;
+		assert_eq!(
+			property("{\"type\":\"Feature\",\"id\":1,\"properties\":{\"ags\":\"08111000\"},\"geometry\":null}"),
+			Some(String::from("08111000"))
+		);
 		assert_eq!(
-			entries[entries.len() - 1],
-			GeoNode::new_leaf(GeoBBox::new(13.348389, 13.359375, 52.519386, 52.520912), 2901065, 1377)
+			property("{\"type\":\"Feature\",\"properties\":{\"ags\":8111000},\"geometry\":null}"),
+			Some(String::from("8111000"))
 		);
 
+		assert!(GeoFile::get_id_extractor(Path::new("a.csv"), &GeoFileOptions::empty()).is_err());
+		let mut options = GeoFileOptions::new(";", 1, 2, 1);
+		options.col_id = Some(0);
+		let csv = GeoFile::get_id_extractor(Path::new("a.csv"), &options)?;
+		assert_eq!(csv("a;1;2"), Some(String::from("a")));
 		Ok(())
 	}
 }
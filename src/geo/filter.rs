@@ -0,0 +1,670 @@
+use super::time::parse_time;
+use serde::Deserialize;
+use serde_json::{Map, Value as JsonValue};
+use std::{
+	cmp::Ordering,
+	error::Error,
+	io::{Error as IoError, ErrorKind},
+	result::Result,
+};
+
+// A filter expression over GeoJSON properties or CSV columns, e.g.
+// `klasse = 'Historische Siedlung' and (einwohner >= 1000 or land in ('BW', 'BY')) and name is not null`
+#[derive(Clone, Debug, PartialEq)]
+pub enum Filter {
+	And(Box<Filter>, Box<Filter>),
+	Or(Box<Filter>, Box<Filter>),
+	Not(Box<Filter>),
+	Compare(Field, Operator, Value),
+	In(Field, Vec<Value>),
+	IsNull(Field),
+}
+
+// A property path of GeoJSON features, or a column of CSV/TSV lines
+#[derive(Clone, Debug, PartialEq)]
+pub enum Field {
+	Property(Vec<String>),
+	Column(usize),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Operator {
+	Eq,
+	Ne,
+	Lt,
+	Le,
+	Gt,
+	Ge,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+	String(String),
+	Number(f64),
+	Bool(bool),
+}
+
+// Reads the values of lines: GeoJSON properties, or the columns of CSV/TSV lines split by the separator
+#[derive(Clone)]
+pub struct RecordReader {
+	csv_separator: Option<String>,
+}
+
+/// A parsed filter with the reader for the lines it is evaluated on, see [`GeoDB::get_filter`](crate::GeoDB::get_filter)
+#[derive(Clone)]
+pub struct LineFilter {
+	filter: Filter,
+	reader: RecordReader,
+}
+
+// The values of a line the filter is evaluated on
+pub enum Record<'a> {
+	Json(Map<String, JsonValue>),
+	Csv(Vec<&'a str>),
+}
+
+// A value of a record, as far as filters can compare it
+enum RecordValue<'a> {
+	Null,
+	String(&'a str),
+	Number(f64),
+	Bool(bool),
+	Other,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+	Identifier(String),
+	// a double quoted identifier, which is never a keyword
+	QuotedIdentifier(String),
+	Column(usize),
+	String(String),
+	Number(f64),
+	Operator(Operator),
+	Open,
+	Close,
+	Comma,
+}
+
+impl RecordReader {
+	pub fn new(csv_separator: Option<String>) -> Self {
+		RecordReader { csv_separator }
+	}
+
+	pub fn read<'a>(&self, line: &'a str) -> Option<Record<'a>> {
+		match &self.csv_separator {
+			Some(separator) => Some(Record::from_csv(line, separator)),
+			None => Record::from_geojson(line),
+		}
+	}
+}
+
+impl LineFilter {
+	pub fn new(filter: Filter, reader: RecordReader) -> Self {
+		LineFilter { filter, reader }
+	}
+
+	pub fn matches(&self, line: &str) -> bool {
+		match self.reader.read(line) {
+			Some(record) => self.filter.matches(&record),
+			None => false,
+		}
+	}
+
+	// Conditions every matching line fulfills: the field equals one of the values.
+	// These are the "=" and "in" conditions of the top level "and".
+	pub fn equalities(&self) -> Vec<(&Field, Vec<&Value>)> {
+		fn collect<'a>(filter: &'a Filter, result: &mut Vec<(&'a Field, Vec<&'a Value>)>) {
+			match filter {
+				Filter::And(a, b) => {
+					collect(a, result);
+					collect(b, result);
+				}
+				Filter::Compare(field, Operator::Eq, value) => result.push((field, vec![value])),
+				Filter::In(field, values) => result.push((field, values.iter().collect())),
+				_ => {}
+			}
+		}
+		let mut result = Vec::new();
+		collect(&self.filter, &mut result);
+		result
+	}
+}
+
+impl Field {
+	// A field by name: a column name or "$n" with a 0-based column number for CSV/TSV files, a property path with dots otherwise
+	pub fn parse(name: &str, csv_columns: Option<&[String]>) -> Result<Self, Box<dyn Error>> {
+		match csv_columns {
+			None => Ok(Field::Property(name.split('.').map(String::from).collect())),
+			Some(columns) => match columns.iter().position(|column| column == name) {
+				Some(column) => Ok(Field::Column(column)),
+				None => match name.strip_prefix('$').map(str::parse::<usize>) {
+					Some(Ok(column)) => Ok(Field::Column(column)),
+					_ => Err(invalid(format!("unknown column {}", name))),
+				},
+			},
+		}
+	}
+}
+
+impl Value {
+	// The key of the value in attribute indexes, see key()
+	pub fn key(&self) -> String {
+		match self {
+			Value::String(s) => key(s),
+			Value::Number(n) => number_key(*n),
+			Value::Bool(b) => b.to_string(),
+		}
+	}
+}
+
+// Keys of attribute indexes. Strings containing numbers are keyed like the number, so the index finds
+// every line a filter could match, e.g. the CSV value "1000.0" for the filter value 1000.
+fn key(s: &str) -> String {
+	match s.trim().parse::<f64>() {
+		Ok(n) if !s.trim().is_empty() => number_key(n),
+		_ => s.to_string(),
+	}
+}
+
+// -0 equals 0 in comparisons, so both have the key "0"
+fn number_key(n: f64) -> String {
+	if n == 0. {
+		String::from("0")
+	} else {
+		n.to_string()
+	}
+}
+
+impl Filter {
+	// Parse an expression. CSV/TSV files pass their column names, so fields are resolved to columns.
+	pub fn parse(expression: &str, csv_columns: Option<&[String]>) -> Result<Self, Box<dyn Error>> {
+		let mut parser = Parser {
+			tokens: tokenize(expression)?,
+			position: 0,
+			csv_columns,
+		};
+		let filter = parser.parse_or()?;
+		match parser.peek() {
+			None => Ok(filter),
+			Some(token) => Err(invalid(format!("unexpected {:?}", token))),
+		}
+	}
+
+	pub fn matches(&self, record: &Record) -> bool {
+		self.evaluate(record) == Some(true)
+	}
+
+	// Evaluate with three-valued logic like SQL: comparisons with null or with a value of another type
+	// are unknown (None), and so is their negation, so "not (x = 1)" doesn't match null either
+	fn evaluate(&self, record: &Record) -> Option<bool> {
+		match self {
+			Filter::And(a, b) => match (a.evaluate(record), b.evaluate(record)) {
+				(Some(false), _) | (_, Some(false)) => Some(false),
+				(Some(true), Some(true)) => Some(true),
+				_ => None,
+			},
+			Filter::Or(a, b) => match (a.evaluate(record), b.evaluate(record)) {
+				(Some(true), _) | (_, Some(true)) => Some(true),
+				(Some(false), Some(false)) => Some(false),
+				_ => None,
+			},
+			Filter::Not(a) => a.evaluate(record).map(|value| !value),
+			Filter::Compare(field, operator, value) => {
+				let ordering = compare(&record.get(field), value)?;
+				Some(match operator {
+					Operator::Eq => ordering == Ordering::Equal,
+					Operator::Ne => ordering != Ordering::Equal,
+					Operator::Lt => ordering == Ordering::Less,
+					Operator::Le => ordering != Ordering::Greater,
+					Operator::Gt => ordering == Ordering::Greater,
+					Operator::Ge => ordering != Ordering::Less,
+				})
+			}
+			// like "field = a or field = b"
+			Filter::In(field, values) => {
+				let field_value = record.get(field);
+				let equal: Vec<Option<bool>> = values
+					.iter()
+					.map(|value| compare(&field_value, value).map(|ordering| ordering == Ordering::Equal))
+					.collect();
+				if equal.contains(&Some(true)) {
+					Some(true)
+				} else if equal.contains(&None) {
+					None
+				} else {
+					Some(false)
+				}
+			}
+			Filter::IsNull(field) => Some(matches!(record.get(field), RecordValue::Null)),
+		}
+	}
+}
+
+impl<'a> Record<'a> {
+	// Read the properties of a GeoJSON feature, skipping its geometry
+	pub fn from_geojson(line: &str) -> Option<Self> {
+		#[derive(Deserialize)]
+		struct Feature {
+			properties: Option<Map<String, JsonValue>>,
+		}
+		let feature: Feature = serde_json::from_str(line).ok()?;
+		Some(Record::Json(feature.properties.unwrap_or_default()))
+	}
+
+	pub fn from_csv(line: &'a str, separator: &str) -> Self {
+		Record::Csv(line.trim_end_matches('\r').split(separator).collect())
+	}
+
+	// The time of a field value in milliseconds since 1970: numbers are taken as they are, strings are
+	// parsed as ISO 8601 dates, or as numbers for CSV/TSV columns
+	pub fn time(&self, field: &Field) -> Option<i64> {
+		match self.get(field) {
+			RecordValue::Number(n) => Some(n as i64),
+			RecordValue::String(s) => parse_time(s).or_else(|| s.trim().parse::<f64>().ok().map(|n| n as i64)),
+			_ => None,
+		}
+	}
+
+	// The key of a field value in attribute indexes. Null and objects have none.
+	pub fn key(&self, field: &Field) -> Option<String> {
+		match self.get(field) {
+			RecordValue::String(s) => Some(key(s)),
+			RecordValue::Number(n) => Some(number_key(n)),
+			RecordValue::Bool(b) => Some(b.to_string()),
+			RecordValue::Null | RecordValue::Other => None,
+		}
+	}
+
+	fn get(&self, field: &Field) -> RecordValue<'_> {
+		match (self, field) {
+			(Record::Json(properties), Field::Property(path)) => {
+				let mut value = properties.get(&path[0]);
+				for key in path[1..].iter() {
+					value = value.and_then(|v| v.get(key));
+				}
+				match value {
+					None | Some(JsonValue::Null) => RecordValue::Null,
+					Some(JsonValue::String(s)) => RecordValue::String(s),
+					Some(JsonValue::Number(n)) => n.as_f64().map_or(RecordValue::Other, RecordValue::Number),
+					Some(JsonValue::Bool(b)) => RecordValue::Bool(*b),
+					Some(_) => RecordValue::Other,
+				}
+			}
+			(Record::Csv(columns), Field::Column(column)) => match columns.get(*column) {
+				None | Some(&"") => RecordValue::Null,
+				Some(s) => RecordValue::String(s),
+			},
+			_ => RecordValue::Null,
+		}
+	}
+}
+
+// Strings are compared with strings, numbers with numbers. Strings are parsed when compared with a number,
+// as CSV columns are always strings.
+fn compare(field_value: &RecordValue, value: &Value) -> Option<Ordering> {
+	match (field_value, value) {
+		(RecordValue::String(a), Value::String(b)) => Some((*a).cmp(b.as_str())),
+		(RecordValue::String(a), Value::Number(b)) => a.trim().parse::<f64>().ok()?.partial_cmp(b),
+		(RecordValue::Number(a), Value::Number(b)) => a.partial_cmp(b),
+		(RecordValue::Bool(a), Value::Bool(b)) => Some(a.cmp(b)),
+		_ => None,
+	}
+}
+
+fn invalid(message: String) -> Box<dyn Error> {
+	Box::new(IoError::new(
+		ErrorKind::InvalidInput,
+		format!("Invalid filter: {}", message),
+	))
+}
+
+fn tokenize(expression: &str) -> Result<Vec<Token>, Box<dyn Error>> {
+	let chars: Vec<char> = expression.chars().collect();
+	let mut tokens: Vec<Token> = Vec::new();
+	let mut i = 0;
+
+	// read a quoted string, where a doubled quote stands for the quote itself
+	let read_quoted = |i: &mut usize, quote: char| -> Result<String, Box<dyn Error>> {
+		let mut text = String::new();
+		*i += 1;
+		loop {
+			match chars.get(*i) {
+				None => return Err(invalid(format!("missing closing {}", quote))),
+				Some(&c) if c == quote => {
+					if chars.get(*i + 1) == Some(&quote) {
+						text.push(quote);
+						*i += 2;
+					} else {
+						*i += 1;
+						return Ok(text);
+					}
+				}
+				Some(&c) => {
+					text.push(c);
+					*i += 1;
+				}
+			}
+		}
+	};
+
+	while i < chars.len() {
+		let c = chars[i];
+		let next = chars.get(i + 1).copied();
+		match c {
+			_ if c.is_whitespace() => i += 1,
+			'(' | ')' | ',' => {
+				tokens.push(match c {
+					'(' => Token::Open,
+					')' => Token::Close,
+					_ => Token::Comma,
+				});
+				i += 1;
+			}
+			'=' => {
+				tokens.push(Token::Operator(Operator::Eq));
+				i += if next == Some('=') { 2 } else { 1 };
+			}
+			'!' if next == Some('=') => {
+				tokens.push(Token::Operator(Operator::Ne));
+				i += 2;
+			}
+			'<' | '>' => {
+				let (operator, length) = match (c, next) {
+					('<', Some('=')) => (Operator::Le, 2),
+					('<', Some('>')) => (Operator::Ne, 2),
+					('<', _) => (Operator::Lt, 1),
+					(_, Some('=')) => (Operator::Ge, 2),
+					_ => (Operator::Gt, 1),
+				};
+				tokens.push(Token::Operator(operator));
+				i += length;
+			}
+			'\'' => tokens.push(Token::String(read_quoted(&mut i, '\'')?)),
+			'"' => tokens.push(Token::QuotedIdentifier(read_quoted(&mut i, '"')?)),
+			'$' => {
+				let start = i + 1;
+				i = start;
+				while i < chars.len() && chars[i].is_ascii_digit() {
+					i += 1;
+				}
+				let digits: String = chars[start..i].iter().collect();
+				let column = digits
+					.parse()
+					.map_err(|_| invalid(String::from("$ must be followed by a column")))?;
+				tokens.push(Token::Column(column));
+			}
+			_ if c.is_ascii_digit() || (c == '-' || c == '.') && next.is_some_and(|n| n.is_ascii_digit()) => {
+				let start = i;
+				i += 1;
+				while i < chars.len() {
+					let c = chars[i];
+					let exponent_sign = (c == '-' || c == '+') && matches!(chars[i - 1], 'e' | 'E');
+					if c.is_ascii_digit() || c == '.' || c == 'e' || c == 'E' || exponent_sign {
+						i += 1;
+					} else {
+						break;
+					}
+				}
+				let text: String = chars[start..i].iter().collect();
+				let number = text.parse().map_err(|_| invalid(format!("invalid number {}", text)))?;
+				tokens.push(Token::Number(number));
+			}
+			_ if c.is_alphabetic() || c == '_' => {
+				let start = i;
+				while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_' || chars[i] == '.') {
+					i += 1;
+				}
+				tokens.push(Token::Identifier(chars[start..i].iter().collect()));
+			}
+			_ => return Err(invalid(format!("unexpected character {}", c))),
+		}
+	}
+	Ok(tokens)
+}
+
+struct Parser<'a> {
+	tokens: Vec<Token>,
+	position: usize,
+	csv_columns: Option<&'a [String]>,
+}
+
+impl<'a> Parser<'a> {
+	fn peek(&self) -> Option<&Token> {
+		self.tokens.get(self.position)
+	}
+
+	fn next(&mut self) -> Result<Token, Box<dyn Error>> {
+		let token = self.tokens.get(self.position).cloned();
+		self.position += 1;
+		token.ok_or_else(|| invalid(String::from("unexpected end")))
+	}
+
+	// Keywords are unquoted identifiers in any case
+	fn is_keyword(&self, keyword: &str) -> bool {
+		matches!(self.peek(), Some(Token::Identifier(name)) if name.eq_ignore_ascii_case(keyword))
+	}
+
+	fn expect_keyword(&mut self, keyword: &str) -> Result<(), Box<dyn Error>> {
+		if !self.is_keyword(keyword) {
+			return Err(invalid(format!("expected {}", keyword)));
+		}
+		self.position += 1;
+		Ok(())
+	}
+
+	fn expect(&mut self, expected: Token) -> Result<(), Box<dyn Error>> {
+		let token = self.next()?;
+		if token != expected {
+			return Err(invalid(format!("expected {:?}, found {:?}", expected, token)));
+		}
+		Ok(())
+	}
+
+	fn parse_or(&mut self) -> Result<Filter, Box<dyn Error>> {
+		let mut filter = self.parse_and()?;
+		while self.is_keyword("or") {
+			self.position += 1;
+			filter = Filter::Or(Box::new(filter), Box::new(self.parse_and()?));
+		}
+		Ok(filter)
+	}
+
+	fn parse_and(&mut self) -> Result<Filter, Box<dyn Error>> {
+		let mut filter = self.parse_not()?;
+		while self.is_keyword("and") {
+			self.position += 1;
+			filter = Filter::And(Box::new(filter), Box::new(self.parse_not()?));
+		}
+		Ok(filter)
+	}
+
+	fn parse_not(&mut self) -> Result<Filter, Box<dyn Error>> {
+		if self.is_keyword("not") {
+			self.position += 1;
+			return Ok(Filter::Not(Box::new(self.parse_not()?)));
+		}
+		if self.peek() == Some(&Token::Open) {
+			self.position += 1;
+			let filter = self.parse_or()?;
+			self.expect(Token::Close)?;
+			return Ok(filter);
+		}
+		self.parse_condition()
+	}
+
+	fn parse_condition(&mut self) -> Result<Filter, Box<dyn Error>> {
+		let field = self.parse_field()?;
+		if self.is_keyword("is") {
+			self.position += 1;
+			let negate = self.is_keyword("not");
+			if negate {
+				self.position += 1;
+			}
+			self.expect_keyword("null")?;
+			let filter = Filter::IsNull(field);
+			return Ok(if negate { Filter::Not(Box::new(filter)) } else { filter });
+		}
+
+		let negate = self.is_keyword("not");
+		if negate {
+			self.position += 1;
+		}
+		if self.is_keyword("in") {
+			self.position += 1;
+			self.expect(Token::Open)?;
+			let mut values = vec![self.parse_value()?];
+			while self.peek() == Some(&Token::Comma) {
+				self.position += 1;
+				values.push(self.parse_value()?);
+			}
+			self.expect(Token::Close)?;
+			let filter = Filter::In(field, values);
+			return Ok(if negate { Filter::Not(Box::new(filter)) } else { filter });
+		}
+		if negate {
+			return Err(invalid(String::from("expected in")));
+		}
+
+		match self.next()? {
+			Token::Operator(operator) => Ok(Filter::Compare(field, operator, self.parse_value()?)),
+			token => Err(invalid(format!("expected an operator, found {:?}", token))),
+		}
+	}
+
+	fn parse_field(&mut self) -> Result<Field, Box<dyn Error>> {
+		match (self.next()?, self.csv_columns) {
+			(Token::Identifier(name) | Token::QuotedIdentifier(name), _) => Field::parse(&name, self.csv_columns),
+			(Token::Column(column), Some(_)) => Ok(Field::Column(column)),
+			(Token::Column(_), None) => Err(invalid(String::from("columns can only be used with CSV/TSV files"))),
+			(token, _) => Err(invalid(format!("expected a field, found {:?}", token))),
+		}
+	}
+
+	fn parse_value(&mut self) -> Result<Value, Box<dyn Error>> {
+		match self.next()? {
+			Token::String(text) => Ok(Value::String(text)),
+			Token::Number(number) => Ok(Value::Number(number)),
+			Token::Identifier(name) if name.eq_ignore_ascii_case("true") => Ok(Value::Bool(true)),
+			Token::Identifier(name) if name.eq_ignore_ascii_case("false") => Ok(Value::Bool(false)),
+			token => Err(invalid(format!("expected a value, found {:?}", token))),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn matches_json(expression: &str, line: &str) -> Result<bool, Box<dyn Error>> {
+		let record = Record::from_geojson(line).unwrap();
+		Ok(Filter::parse(expression, None)?.matches(&record))
+	}
+
+	#[test]
+	fn test_parse() -> Result<(), Box<dyn Error>> {
+		let property = |name: &str| Field::Property(vec![String::from(name)]);
+		assert_eq!(
+			Filter::parse("a = 'x' or b >= -1.5e2 and not c is null", None)?,
+			Filter::Or(
+				Box::new(Filter::Compare(
+					property("a"),
+					Operator::Eq,
+					Value::String(String::from("x"))
+				)),
+				Box::new(Filter::And(
+					Box::new(Filter::Compare(property("b"), Operator::Ge, Value::Number(-150.))),
+					Box::new(Filter::Not(Box::new(Filter::IsNull(property("c"))))),
+				)),
+			)
+		);
+		assert_eq!(
+			Filter::parse("\"name \"\"kurz\"\"\" NOT IN ('it''s', 2, TRUE)", None)?,
+			Filter::Not(Box::new(Filter::In(
+				property("name \"kurz\""),
+				vec![
+					Value::String(String::from("it's")),
+					Value::Number(2.),
+					Value::Bool(true)
+				]
+			)))
+		);
+		assert_eq!(Filter::parse("\"and\" is null", None)?, Filter::IsNull(property("and")));
+		assert_eq!(
+			Filter::parse("a.b <> 1", None)?,
+			Filter::Compare(
+				Field::Property(vec![String::from("a"), String::from("b")]),
+				Operator::Ne,
+				Value::Number(1.)
+			)
+		);
+
+		for expression in [
+			"",
+			"a =",
+			"a = 'x",
+			"(a = 1",
+			"a = 1 b",
+			"a ~ 1",
+			"a in ()",
+			"$1 = 1",
+			"a is not 1",
+		] {
+			assert!(Filter::parse(expression, None).is_err(), "{}", expression);
+		}
+		Ok(())
+	}
+
+	#[test]
+	fn test_matches_geojson() -> Result<(), Box<dyn Error>> {
+		let line = "{\"type\":\"Feature\",\"properties\":{\"klasse\":\"Wald\",\"einwohner\":1200,\"aktiv\":true,\"leer\":null,\"adresse\":{\"ort\":\"Mainz\"}},\"geometry\":{\"type\":\"Point\",\"coordinates\":[8.2,50]}}";
+		assert!(matches_json("klasse = 'Wald'", line)?);
+		assert!(!matches_json("klasse = 'Feld'", line)?);
+		assert!(matches_json(
+			"klasse != 'Feld' and einwohner > 1000 and einwohner <= 1200",
+			line
+		)?);
+		assert!(matches_json("einwohner in (1, 1200) and aktiv = true", line)?);
+		assert!(matches_json(
+			"leer is null and fehlt is null and klasse is not null",
+			line
+		)?);
+		assert!(matches_json("adresse.ort = 'Mainz'", line)?);
+		assert!(matches_json("klasse < 'Wiese' or einwohner < 0", line)?);
+		// comparisons with null or other types never match
+		assert!(!matches_json("fehlt != 'Wald'", line)?);
+		assert!(!matches_json("einwohner = '1200'", line)?);
+		assert!(!matches_json("adresse = 'Mainz'", line)?);
+		// not even when negated, while a known result still decides "and" and "or"
+		assert!(!matches_json("not (fehlt = 'Wald')", line)?);
+		assert!(!matches_json("not (einwohner = '1200')", line)?);
+		assert!(!matches_json("fehlt not in ('Wald')", line)?);
+		assert!(!matches_json("not (fehlt = 1 and klasse = 'Wald')", line)?);
+		assert!(matches_json("not (fehlt = 1 and klasse = 'Feld')", line)?);
+		assert!(matches_json(
+			"not (fehlt = 1 or klasse = 'Wald') or aktiv = true",
+			line
+		)?);
+		assert!(!matches_json("not (fehlt = 1 or klasse = 'Feld')", line)?);
+
+		let line = "{\"type\":\"Feature\",\"properties\":null,\"geometry\":null}";
+		assert!(matches_json("klasse is null", line)?);
+		Ok(())
+	}
+
+	#[test]
+	fn test_matches_csv() -> Result<(), Box<dyn Error>> {
+		let columns = vec![String::from("name"), String::from("x"), String::from("y")];
+		let filter = Filter::parse("name = 'Berlin' and x between", Some(&columns));
+		assert!(filter.is_err());
+		let filter = Filter::parse(
+			"name in ('Berlin', 'Hamburg') and x < 12 and $3 is null",
+			Some(&columns),
+		)?;
+		assert!(filter.matches(&Record::from_csv("Hamburg;10.0;53.55\r", ";")));
+		assert!(!filter.matches(&Record::from_csv("Berlin;13.4;52.5", ";")));
+		assert!(!filter.matches(&Record::from_csv("Hamburg;10.0;53.55;x", ";")));
+		assert!(Filter::parse("plz = 1", Some(&columns)).is_err());
+		Ok(())
+	}
+}
@@ -0,0 +1,227 @@
+use super::{invalid, wkb::parse_wkb};
+use geojson::{feature::Id, Bbox, Feature, Geometry, JsonObject, JsonValue, Value};
+use rusqlite::{types::ValueRef, Connection, OpenFlags};
+use std::{convert::TryInto, error::Error, path::Path, result::Result};
+
+// Read all features of a GeoPackage layer and return them as GeoJSON lines.
+// If no layer name is given, the first feature table is used.
+pub fn read_layer(filename: &Path, layer: Option<&str>) -> Result<Vec<u8>, Box<dyn Error>> {
+	let connection = Connection::open_with_flags(filename, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+	let (table, column) = find_layer(&connection, layer)?;
+	let primary_key = find_primary_key(&connection, &table)?;
+
+	let mut statement = connection.prepare(&format!("SELECT * FROM {}", quote(&table)))?;
+	let names: Vec<String> = statement.column_names().iter().map(|n| n.to_string()).collect();
+	let mut rows = statement.query([])?;
+	let mut data: Vec<u8> = Vec::new();
+
+	while let Some(row) = rows.next()? {
+		let mut feature = Feature {
+			bbox: None,
+			geometry: None,
+			id: None,
+			properties: Some(JsonObject::new()),
+			foreign_members: None,
+		};
+
+		for (i, name) in names.iter().enumerate() {
+			let value = row.get_ref(i)?;
+			if *name == column {
+				if let ValueRef::Blob(blob) = value {
+					let (bbox, geometry) = parse_geometry(blob)?;
+					feature.bbox = bbox;
+					feature.geometry = geometry.map(Geometry::new);
+				}
+			} else if Some(name) == primary_key.as_ref() {
+				if let ValueRef::Integer(fid) = value {
+					feature.id = Some(Id::Number(fid.into()));
+				}
+			} else if let Some(json) = to_json(value) {
+				feature.properties.as_mut().unwrap().insert(name.clone(), json);
+			}
+		}
+
+		// features without geometry can't be indexed
+		if feature.geometry.is_none() {
+			continue;
+		}
+
+		data.extend_from_slice(feature.to_string().as_bytes());
+		data.push(b'\n');
+	}
+
+	Ok(data)
+}
+
+// Find the table and geometry column of the requested (or first) feature layer
+fn find_layer(connection: &Connection, layer: Option<&str>) -> Result<(String, String), Box<dyn Error>> {
+	let mut statement = connection.prepare(
+		"SELECT c.table_name, g.column_name FROM gpkg_contents c \
+		JOIN gpkg_geometry_columns g ON c.table_name = g.table_name \
+		WHERE c.data_type = 'features' ORDER BY c.table_name",
+	)?;
+	let layers = statement
+		.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?
+		.collect::<Result<Vec<_>, _>>()?;
+
+	let found = match layer {
+		Some(name) => layers.into_iter().find(|(table, _)| table == name),
+		None => layers.into_iter().next(),
+	};
+	found.ok_or_else(|| {
+		invalid(format!(
+			"GeoPackage layer not found: {}",
+			layer.unwrap_or("no feature table")
+		))
+	})
+}
+
+// Find the integer primary key column, which is used as feature id
+fn find_primary_key(connection: &Connection, table: &str) -> Result<Option<String>, Box<dyn Error>> {
+	let mut statement = connection.prepare(&format!("PRAGMA table_info({})", quote(table)))?;
+	let columns = statement
+		.query_map([], |row| {
+			Ok((
+				row.get::<_, String>(1)?,
+				row.get::<_, String>(2)?,
+				row.get::<_, i64>(5)?,
+			))
+		})?
+		.collect::<Result<Vec<_>, _>>()?;
+	Ok(columns
+		.into_iter()
+		.find(|(_, column_type, pk)| *pk == 1 && column_type.eq_ignore_ascii_case("INTEGER"))
+		.map(|(name, _, _)| name))
+}
+
+type ParsedGeometry = (Option<Bbox>, Option<Value>);
+
+// Parse a GeoPackage geometry blob: header with optional envelope, followed by WKB.
+// Returns the envelope as GeoJSON bbox member of the feature.
+fn parse_geometry(blob: &[u8]) -> Result<ParsedGeometry, Box<dyn Error>> {
+	if blob.len() < 8 || &blob[0..2] != b"GP" {
+		return Err(invalid("invalid GeoPackage geometry header".to_string()));
+	}
+	let flags = blob[3];
+	if flags & 0b0010_0000 != 0 {
+		return Err(invalid("extended GeoPackage geometries are not supported".to_string()));
+	}
+	if flags & 0b0001_0000 != 0 {
+		return Ok((None, None));
+	}
+
+	let envelope_size = match (flags >> 1) & 0b111 {
+		0 => 0,
+		1 => 32,
+		2 | 3 => 48,
+		4 => 64,
+		_ => return Err(invalid("invalid GeoPackage envelope indicator".to_string())),
+	};
+	if blob.len() < 8 + envelope_size {
+		return Err(invalid("unexpected end of GeoPackage geometry".to_string()));
+	}
+
+	let bbox = if envelope_size > 0 {
+		let little_endian = flags & 1 == 1;
+		let read = |i: usize| {
+			let bytes: [u8; 8] = blob[8 + i * 8..16 + i * 8].try_into().unwrap();
+			if little_endian {
+				f64::from_le_bytes(bytes)
+			} else {
+				f64::from_be_bytes(bytes)
+			}
+		};
+		// envelope order is [min_x, max_x, min_y, max_y], GeoJSON wants [min_x, min_y, max_x, max_y]
+		Some(vec![read(0), read(2), read(1), read(3)])
+	} else {
+		None
+	};
+
+	Ok((bbox, Some(parse_wkb(&blob[8 + envelope_size..])?)))
+}
+
+fn to_json(value: ValueRef) -> Option<JsonValue> {
+	match value {
+		ValueRef::Null => Some(JsonValue::Null),
+		ValueRef::Integer(v) => Some(JsonValue::from(v)),
+		ValueRef::Real(v) => Some(JsonValue::from(v)),
+		ValueRef::Text(v) => Some(JsonValue::from(String::from_utf8_lossy(v).into_owned())),
+		ValueRef::Blob(_) => None,
+	}
+}
+
+fn quote(identifier: &str) -> String {
+	format!("\"{}\"", identifier.replace('"', "\"\""))
+}
+
+#[cfg(test)]
+pub mod tests {
+	use super::*;
+	use assert_fs::NamedTempFile;
+	use rusqlite::params;
+
+	// Build a little endian GeoPackage point blob, optionally with an xy envelope
+	pub fn point_blob(x: f64, y: f64, envelope: bool) -> Vec<u8> {
+		let mut blob = vec![b'G', b'P', 0, if envelope { 0b11 } else { 0b01 }, 0, 0, 0, 0];
+		if envelope {
+			for v in [x, x, y, y] {
+				blob.extend_from_slice(&v.to_le_bytes());
+			}
+		}
+		blob.extend_from_slice(&[1, 1, 0, 0, 0]);
+		blob.extend_from_slice(&x.to_le_bytes());
+		blob.extend_from_slice(&y.to_le_bytes());
+		blob
+	}
+
+	// Create a minimal GeoPackage with two feature layers
+	pub fn create_geopackage(filename: &Path) -> Result<(), Box<dyn Error>> {
+		let connection = Connection::open(filename)?;
+		connection.execute_batch(
+			"CREATE TABLE gpkg_contents (table_name TEXT PRIMARY KEY, data_type TEXT NOT NULL);
+			CREATE TABLE gpkg_geometry_columns (table_name TEXT, column_name TEXT);
+			INSERT INTO gpkg_contents VALUES ('cities', 'features'), ('rivers', 'features'), ('meta', 'attributes');
+			INSERT INTO gpkg_geometry_columns VALUES ('cities', 'geom'), ('rivers', 'shape');
+			CREATE TABLE cities (fid INTEGER PRIMARY KEY, geom BLOB, name TEXT, population INTEGER);
+			CREATE TABLE rivers (id INTEGER PRIMARY KEY, shape BLOB, name TEXT);",
+		)?;
+		connection.execute(
+			"INSERT INTO cities VALUES (1, ?1, 'Stuttgart', 630000), (2, ?2, 'Mainz', NULL), (3, NULL, 'Nowhere', 0)",
+			params![point_blob(9.18, 48.78, true), point_blob(8.27, 50.0, false)],
+		)?;
+		connection.execute(
+			"INSERT INTO rivers VALUES (7, ?1, 'Rhein')",
+			params![point_blob(7.6, 50.36, true)],
+		)?;
+		Ok(())
+	}
+
+	#[test]
+	fn test_read_layer() -> Result<(), Box<dyn Error>> {
+		let filename = NamedTempFile::new("test.gpkg")?;
+		create_geopackage(filename.path())?;
+
+		let data = String::from_utf8(read_layer(filename.path(), None)?)?;
+		assert_eq!(
+			data,
+			"{\"bbox\":[9.18,48.78,9.18,48.78],\"geometry\":{\"coordinates\":[9.18,48.78],\"type\":\"Point\"},\"id\":1,\"properties\":{\"name\":\"Stuttgart\",\"population\":630000},\"type\":\"Feature\"}\n\
+			{\"geometry\":{\"coordinates\":[8.27,50.0],\"type\":\"Point\"},\"id\":2,\"properties\":{\"name\":\"Mainz\",\"population\":null},\"type\":\"Feature\"}\n"
+		);
+
+		let data = String::from_utf8(read_layer(filename.path(), Some("rivers"))?)?;
+		assert!(data.starts_with("{\"bbox\":[7.6,50.36,7.6,50.36]"));
+		assert!(data.contains("\"id\":7"));
+
+		assert!(read_layer(filename.path(), Some("meta")).is_err());
+		Ok(())
+	}
+
+	#[test]
+	fn test_parse_geometry_empty() -> Result<(), Box<dyn Error>> {
+		let (bbox, geometry) = parse_geometry(&[b'G', b'P', 0, 0b10001, 0, 0, 0, 0])?;
+		assert_eq!(bbox, None);
+		assert_eq!(geometry, None);
+		assert!(parse_geometry(b"XX\0\0\0\0\0\0").is_err());
+		Ok(())
+	}
+}
@@ -0,0 +1,139 @@
+use super::{feature_line, invalid};
+use geojson::{JsonObject, JsonValue, Position, Value};
+use quick_xml::{events::BytesStart, events::Event, Reader};
+use std::{error::Error, result::Result};
+
+// Convert a GPX document into GeoJSON lines:
+// waypoints become Points, routes LineStrings and tracks MultiLineStrings (one line per segment)
+pub fn convert(data: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+	let mut reader = Reader::from_reader(data);
+	reader.trim_text(true).expand_empty_elements(true);
+
+	let mut output: Vec<u8> = Vec::new();
+	let mut stack: Vec<String> = Vec::new();
+	let mut text = String::new();
+	let mut properties = JsonObject::new();
+	let mut lines: Vec<Vec<Position>> = Vec::new();
+	let mut point: Option<Position> = None;
+
+	loop {
+		match reader.read_event()? {
+			Event::Start(e) => {
+				let name = String::from_utf8_lossy(e.local_name().as_ref()).into_owned();
+				match name.as_str() {
+					"wpt" => {
+						properties = JsonObject::new();
+						point = Some(read_position(&e)?);
+					}
+					"rte" => {
+						properties = JsonObject::new();
+						lines = vec![Vec::new()];
+					}
+					"trk" => {
+						properties = JsonObject::new();
+						lines = Vec::new();
+					}
+					"trkseg" => lines.push(Vec::new()),
+					"rtept" | "trkpt" => {
+						let position = read_position(&e)?;
+						lines
+							.last_mut()
+							.ok_or_else(|| invalid(format!("unexpected GPX element: {}", name)))?
+							.push(position)
+					}
+					_ => {}
+				}
+				stack.push(name);
+				text.clear();
+			}
+			Event::Text(e) => text.push_str(&e.unescape()?),
+			Event::CData(e) => text.push_str(&String::from_utf8_lossy(&e.into_inner())),
+			Event::End(_) => {
+				let name = stack.pop().unwrap_or_default();
+				match name.as_str() {
+					"wpt" => {
+						if let Some(position) = point.take() {
+							feature_line(&mut output, Value::Point(position), &properties);
+						}
+					}
+					"rte" => {
+						let line = lines.pop().unwrap_or_default();
+						if !line.is_empty() {
+							feature_line(&mut output, Value::LineString(line), &properties);
+						}
+					}
+					"trk" => {
+						lines.retain(|line| !line.is_empty());
+						if !lines.is_empty() {
+							feature_line(&mut output, Value::MultiLineString(lines.split_off(0)), &properties);
+						}
+					}
+					_ => {
+						// simple child elements of waypoints, routes and tracks become properties
+						let parent = stack.last().map(String::as_str);
+						if matches!(parent, Some("wpt" | "rte" | "trk")) && !text.is_empty() {
+							properties.insert(name, JsonValue::from(text.clone()));
+						}
+					}
+				}
+				text.clear();
+			}
+			Event::Eof => break,
+			_ => {}
+		}
+	}
+
+	Ok(output)
+}
+
+fn read_position(e: &BytesStart) -> Result<Position, Box<dyn Error>> {
+	let read = |key: &str| -> Result<f64, Box<dyn Error>> {
+		let attribute = e
+			.try_get_attribute(key)?
+			.ok_or_else(|| invalid(format!("GPX point without {} attribute", key)))?;
+		Ok(attribute.unescape_value()?.trim().parse()?)
+	};
+	Ok(vec![read("lon")?, read("lat")?])
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_convert() -> Result<(), Box<dyn Error>> {
+		let gpx = r#"<?xml version="1.0"?>
+			<gpx version="1.1" xmlns="http://www.topografix.com/GPX/1/1">
+				<wpt lat="48.78" lon="9.18"><name>Stuttgart &amp; more</name><ele>245</ele></wpt>
+				<wpt lat="50.0" lon="8.27"/>
+				<rte><name>Route</name><rtept lat="1" lon="2"/><rtept lat="3" lon="4"/></rte>
+				<trk>
+					<name><![CDATA[Track]]></name>
+					<trkseg><trkpt lat="1" lon="2"><ele>5</ele></trkpt><trkpt lat="3" lon="4"/></trkseg>
+					<trkseg><trkpt lat="5" lon="6"/></trkseg>
+					<trkseg></trkseg>
+				</trk>
+			</gpx>"#;
+
+		let lines: Vec<String> = String::from_utf8(convert(gpx.as_bytes())?)?
+			.lines()
+			.map(String::from)
+			.collect();
+
+		assert_eq!(
+			lines,
+			vec![
+				"{\"geometry\":{\"coordinates\":[9.18,48.78],\"type\":\"Point\"},\"properties\":{\"ele\":\"245\",\"name\":\"Stuttgart & more\"},\"type\":\"Feature\"}",
+				"{\"geometry\":{\"coordinates\":[8.27,50.0],\"type\":\"Point\"},\"properties\":{},\"type\":\"Feature\"}",
+				"{\"geometry\":{\"coordinates\":[[2.0,1.0],[4.0,3.0]],\"type\":\"LineString\"},\"properties\":{\"name\":\"Route\"},\"type\":\"Feature\"}",
+				"{\"geometry\":{\"coordinates\":[[[2.0,1.0],[4.0,3.0]],[[6.0,5.0]]],\"type\":\"MultiLineString\"},\"properties\":{\"name\":\"Track\"},\"type\":\"Feature\"}",
+			]
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn test_missing_coordinate() {
+		assert!(convert(b"<gpx><wpt lat=\"1\"/></gpx>").is_err());
+	}
+}
@@ -0,0 +1,248 @@
+use super::{feature_line, invalid};
+use geojson::{Geometry, JsonObject, JsonValue, Position, Value};
+use quick_xml::{events::Event, Reader};
+use std::{error::Error, result::Result};
+
+// Geometry element that is currently being parsed
+enum Frame {
+	Point(Option<Position>),
+	LineString(Vec<Position>),
+	Polygon(Vec<Vec<Position>>),
+	MultiGeometry(Vec<Value>),
+}
+
+// Convert a KML document into GeoJSON lines, one feature per Placemark.
+// Supports Point, LineString, LinearRing, Polygon and (nested) MultiGeometry.
+pub fn convert(data: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+	let mut reader = Reader::from_reader(data);
+	reader.trim_text(true).expand_empty_elements(true);
+
+	let mut output: Vec<u8> = Vec::new();
+	let mut stack: Vec<String> = Vec::new();
+	let mut text = String::new();
+	let mut properties = JsonObject::new();
+	let mut frames: Vec<Frame> = Vec::new();
+	let mut geometry: Option<Value> = None;
+	let mut data_name: Option<String> = None;
+
+	loop {
+		match reader.read_event()? {
+			Event::Start(e) => {
+				let name = String::from_utf8_lossy(e.local_name().as_ref()).into_owned();
+				match name.as_str() {
+					"Placemark" => {
+						properties = JsonObject::new();
+						frames.clear();
+						geometry = None;
+					}
+					"Point" => frames.push(Frame::Point(None)),
+					"LineString" | "LinearRing" if !matches!(frames.last(), Some(Frame::Polygon(_))) => {
+						frames.push(Frame::LineString(Vec::new()))
+					}
+					"Polygon" => frames.push(Frame::Polygon(Vec::new())),
+					"MultiGeometry" => frames.push(Frame::MultiGeometry(Vec::new())),
+					"Data" | "SimpleData" => {
+						data_name = match e.try_get_attribute("name")? {
+							Some(attribute) => Some(attribute.unescape_value()?.into_owned()),
+							None => None,
+						}
+					}
+					_ => {}
+				}
+				stack.push(name);
+				text.clear();
+			}
+			Event::Text(e) => text.push_str(&e.unescape()?),
+			Event::CData(e) => text.push_str(&String::from_utf8_lossy(&e.into_inner())),
+			Event::End(_) => {
+				let name = stack.pop().unwrap_or_default();
+				match name.as_str() {
+					"Placemark" => {
+						if let Some(value) = geometry.take() {
+							feature_line(&mut output, value, &properties);
+						}
+					}
+					"coordinates" => {
+						let positions = parse_coordinates(&text)?;
+						match frames.last_mut() {
+							Some(Frame::Point(point)) => *point = positions.into_iter().next(),
+							Some(Frame::LineString(line)) => *line = positions,
+							Some(Frame::Polygon(rings)) => rings.push(positions),
+							_ => {}
+						}
+					}
+					"Point" | "LineString" | "LinearRing" | "Polygon" | "MultiGeometry" => {
+						let is_frame = matches!(
+							(name.as_str(), frames.last()),
+							("Point", Some(Frame::Point(_)))
+								| ("LineString" | "LinearRing", Some(Frame::LineString(_)))
+								| ("Polygon", Some(Frame::Polygon(_)))
+								| ("MultiGeometry", Some(Frame::MultiGeometry(_)))
+						);
+						if is_frame {
+							if let Some(value) = finish_frame(frames.pop().unwrap()) {
+								match frames.last_mut() {
+									Some(Frame::MultiGeometry(values)) => values.push(value),
+									_ => geometry = Some(value),
+								}
+							}
+						}
+					}
+					"name" | "description" if stack.last().map(String::as_str) == Some("Placemark") => {
+						properties.insert(name, JsonValue::from(text.clone()));
+					}
+					"value" if stack.last().map(String::as_str) == Some("Data") => {
+						if let Some(key) = data_name.take() {
+							properties.insert(key, JsonValue::from(text.clone()));
+						}
+					}
+					"SimpleData" => {
+						if let Some(key) = data_name.take() {
+							properties.insert(key, JsonValue::from(text.clone()));
+						}
+					}
+					_ => {}
+				}
+				text.clear();
+			}
+			Event::Eof => break,
+			_ => {}
+		}
+	}
+
+	Ok(output)
+}
+
+// Turn a finished geometry element into a geojson::Value, ignoring empty geometries
+fn finish_frame(frame: Frame) -> Option<Value> {
+	match frame {
+		Frame::Point(point) => point.map(Value::Point),
+		Frame::LineString(line) if !line.is_empty() => Some(Value::LineString(line)),
+		Frame::Polygon(rings) if !rings.is_empty() => Some(Value::Polygon(rings)),
+		Frame::MultiGeometry(values) if !values.is_empty() => Some(merge_values(values)),
+		_ => None,
+	}
+}
+
+// Merge the parts of a MultiGeometry into a Multi* geometry if all parts share the same type
+fn merge_values(values: Vec<Value>) -> Value {
+	if values.iter().all(|v| matches!(v, Value::Point(_))) {
+		Value::MultiPoint(
+			values
+				.into_iter()
+				.filter_map(|v| match v {
+					Value::Point(p) => Some(p),
+					_ => None,
+				})
+				.collect(),
+		)
+	} else if values.iter().all(|v| matches!(v, Value::LineString(_))) {
+		Value::MultiLineString(
+			values
+				.into_iter()
+				.filter_map(|v| match v {
+					Value::LineString(p) => Some(p),
+					_ => None,
+				})
+				.collect(),
+		)
+	} else if values.iter().all(|v| matches!(v, Value::Polygon(_))) {
+		Value::MultiPolygon(
+			values
+				.into_iter()
+				.filter_map(|v| match v {
+					Value::Polygon(p) => Some(p),
+					_ => None,
+				})
+				.collect(),
+		)
+	} else {
+		Value::GeometryCollection(values.into_iter().map(Geometry::new).collect())
+	}
+}
+
+// Parse a KML coordinate list: whitespace separated tuples of "lon,lat[,alt]"
+fn parse_coordinates(text: &str) -> Result<Vec<Position>, Box<dyn Error>> {
+	text
+		.split_whitespace()
+		.map(|tuple| {
+			let position = tuple
+				.split(',')
+				.map(|v| v.parse::<f64>())
+				.collect::<Result<Position, _>>()?;
+			if position.len() < 2 {
+				return Err(invalid(format!("invalid KML coordinates: {}", tuple)));
+			}
+			Ok(position)
+		})
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_convert() -> Result<(), Box<dyn Error>> {
+		let kml = r#"<?xml version="1.0" encoding="UTF-8"?>
+			<kml xmlns="http://www.opengis.net/kml/2.2"><Document><Folder>
+				<Placemark>
+					<name>Point</name>
+					<ExtendedData><Data name="ags"><value>08111000</value></Data></ExtendedData>
+					<Point><coordinates>9.18,48.78,245</coordinates></Point>
+				</Placemark>
+				<Placemark>
+					<description><![CDATA[<b>Line</b>]]></description>
+					<LineString><coordinates>1,2 3,4
+						5,6</coordinates></LineString>
+				</Placemark>
+				<Placemark>
+					<Polygon>
+						<outerBoundaryIs><LinearRing><coordinates>0,0 4,0 4,4 0,0</coordinates></LinearRing></outerBoundaryIs>
+						<innerBoundaryIs><LinearRing><coordinates>1,1 2,1 2,2 1,1</coordinates></LinearRing></innerBoundaryIs>
+					</Polygon>
+				</Placemark>
+				<Placemark>
+					<MultiGeometry>
+						<Point><coordinates>1,2</coordinates></Point>
+						<Point><coordinates>3,4</coordinates></Point>
+					</MultiGeometry>
+				</Placemark>
+				<Placemark>
+					<MultiGeometry>
+						<Point><coordinates>1,2</coordinates></Point>
+						<LineString><coordinates>3,4 5,6</coordinates></LineString>
+					</MultiGeometry>
+				</Placemark>
+				<Placemark><name>No geometry</name></Placemark>
+			</Folder></Document></kml>"#;
+
+		let lines: Vec<String> = String::from_utf8(convert(kml.as_bytes())?)?
+			.lines()
+			.map(String::from)
+			.collect();
+
+		assert_eq!(
+			lines,
+			vec![
+				"{\"geometry\":{\"coordinates\":[9.18,48.78,245.0],\"type\":\"Point\"},\"properties\":{\"ags\":\"08111000\",\"name\":\"Point\"},\"type\":\"Feature\"}",
+				"{\"geometry\":{\"coordinates\":[[1.0,2.0],[3.0,4.0],[5.0,6.0]],\"type\":\"LineString\"},\"properties\":{\"description\":\"<b>Line</b>\"},\"type\":\"Feature\"}",
+				"{\"geometry\":{\"coordinates\":[[[0.0,0.0],[4.0,0.0],[4.0,4.0],[0.0,0.0]],[[1.0,1.0],[2.0,1.0],[2.0,2.0],[1.0,1.0]]],\"type\":\"Polygon\"},\"properties\":{},\"type\":\"Feature\"}",
+				"{\"geometry\":{\"coordinates\":[[1.0,2.0],[3.0,4.0]],\"type\":\"MultiPoint\"},\"properties\":{},\"type\":\"Feature\"}",
+				"{\"geometry\":{\"geometries\":[{\"coordinates\":[1.0,2.0],\"type\":\"Point\"},{\"coordinates\":[[3.0,4.0],[5.0,6.0]],\"type\":\"LineString\"}],\"type\":\"GeometryCollection\"},\"properties\":{},\"type\":\"Feature\"}",
+			]
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn test_parse_coordinates() -> Result<(), Box<dyn Error>> {
+		assert_eq!(
+			parse_coordinates(" 1,2\n\t3.5,4,5 ")?,
+			vec![vec![1.0, 2.0], vec![3.5, 4.0, 5.0]]
+		);
+		assert!(parse_coordinates("1").is_err());
+		assert!(parse_coordinates("a,b").is_err());
+		Ok(())
+	}
+}
@@ -0,0 +1,28 @@
+use geojson::{Feature, Geometry, JsonObject, Value};
+use std::{
+	error::Error,
+	io::{Error as IoError, ErrorKind},
+};
+
+pub mod gpkg;
+pub mod gpx;
+pub mod kml;
+pub mod topojson;
+mod wkb;
+
+// Append a geometry with properties as a GeoJSON feature line
+fn feature_line(output: &mut Vec<u8>, value: Value, properties: &JsonObject) {
+	let feature = Feature {
+		bbox: None,
+		geometry: Some(Geometry::new(value)),
+		id: None,
+		properties: Some(properties.clone()),
+		foreign_members: None,
+	};
+	output.extend_from_slice(feature.to_string().as_bytes());
+	output.push(b'\n');
+}
+
+fn invalid(message: String) -> Box<dyn Error> {
+	Box::new(IoError::new(ErrorKind::InvalidData, message))
+}
@@ -0,0 +1,260 @@
+use super::invalid;
+use geojson::{feature::Id, Feature, Geometry, JsonObject, JsonValue, Position, Value};
+use std::{error::Error, result::Result};
+
+// Decoded TopoJSON topology: absolute arc coordinates and the optional quantisation transform
+struct Topology {
+	arcs: Vec<Vec<Position>>,
+	transform: Option<([f64; 2], [f64; 2])>,
+}
+
+// Convert a TopoJSON topology into GeoJSON lines.
+// Every member of a GeometryCollection object becomes a feature, other objects become a single feature.
+// If an object name is given, only this object is converted.
+pub fn convert(data: &[u8], object: Option<&str>) -> Result<Vec<u8>, Box<dyn Error>> {
+	let json: JsonValue = serde_json::from_slice(data)?;
+	if json["type"] != "Topology" {
+		return Err(invalid("TopoJSON must be of type \"Topology\"".to_string()));
+	}
+
+	let transform = match json.get("transform") {
+		Some(transform) => Some((read_pair(&transform["scale"])?, read_pair(&transform["translate"])?)),
+		None => None,
+	};
+	let mut topology = Topology {
+		arcs: Vec::new(),
+		transform,
+	};
+	topology.arcs = match json["arcs"].as_array() {
+		Some(arcs) => arcs
+			.iter()
+			.map(|arc| topology.decode_arc(arc))
+			.collect::<Result<_, _>>()?,
+		None => Vec::new(),
+	};
+
+	let objects = json["objects"]
+		.as_object()
+		.ok_or_else(|| invalid("TopoJSON without objects".to_string()))?;
+	let selected: Vec<&JsonValue> = match object {
+		Some(name) => vec![objects
+			.get(name)
+			.ok_or_else(|| invalid(format!("TopoJSON object not found: {}", name)))?],
+		None => objects.values().collect(),
+	};
+
+	let mut output: Vec<u8> = Vec::new();
+	for object in selected {
+		if object["type"] == "GeometryCollection" {
+			for member in object["geometries"].as_array().into_iter().flatten() {
+				topology.write_feature(&mut output, member)?;
+			}
+		} else {
+			topology.write_feature(&mut output, object)?;
+		}
+	}
+	Ok(output)
+}
+
+impl Topology {
+	// Write a TopoJSON geometry object as feature line, skipping null geometries
+	fn write_feature(&self, output: &mut Vec<u8>, object: &JsonValue) -> Result<(), Box<dyn Error>> {
+		let value = match self.geometry(object)? {
+			Some(value) => value,
+			None => return Ok(()),
+		};
+		let id = match &object["id"] {
+			JsonValue::String(id) => Some(Id::String(id.clone())),
+			JsonValue::Number(id) => Some(Id::Number(id.clone())),
+			_ => None,
+		};
+		let feature = Feature {
+			bbox: None,
+			geometry: Some(Geometry::new(value)),
+			id,
+			properties: Some(
+				object["properties"]
+					.as_object()
+					.cloned()
+					.unwrap_or_else(JsonObject::new),
+			),
+			foreign_members: None,
+		};
+		output.extend_from_slice(feature.to_string().as_bytes());
+		output.push(b'\n');
+		Ok(())
+	}
+
+	fn geometry(&self, object: &JsonValue) -> Result<Option<Value>, Box<dyn Error>> {
+		let coordinates = &object["coordinates"];
+		let arcs = &object["arcs"];
+		Ok(Some(match object["type"].as_str() {
+			Some("Point") => Value::Point(self.point(coordinates)?),
+			Some("MultiPoint") => Value::MultiPoint(map_array(coordinates, |p| self.point(p))?),
+			Some("LineString") => Value::LineString(self.line(arcs)?),
+			Some("MultiLineString") => Value::MultiLineString(map_array(arcs, |a| self.line(a))?),
+			Some("Polygon") => Value::Polygon(map_array(arcs, |a| self.line(a))?),
+			Some("MultiPolygon") => Value::MultiPolygon(map_array(arcs, |rings| map_array(rings, |a| self.line(a)))?),
+			Some("GeometryCollection") => {
+				let mut geometries = Vec::new();
+				for member in object["geometries"].as_array().into_iter().flatten() {
+					if let Some(value) = self.geometry(member)? {
+						geometries.push(Geometry::new(value));
+					}
+				}
+				Value::GeometryCollection(geometries)
+			}
+			None => return Ok(None),
+			Some(name) => return Err(invalid(format!("unsupported TopoJSON geometry type: {}", name))),
+		}))
+	}
+
+	// Decode a position that is quantised, but not delta-encoded
+	fn point(&self, position: &JsonValue) -> Result<Position, Box<dyn Error>> {
+		let mut position = read_position(position)?;
+		if let Some((scale, translate)) = self.transform {
+			position[0] = position[0] * scale[0] + translate[0];
+			position[1] = position[1] * scale[1] + translate[1];
+		}
+		Ok(position)
+	}
+
+	// Decode an arc, which is quantised and delta-encoded if the topology has a transform
+	fn decode_arc(&self, arc: &JsonValue) -> Result<Vec<Position>, Box<dyn Error>> {
+		let mut positions = map_array(arc, read_position)?;
+		if let Some((scale, translate)) = self.transform {
+			let (mut x, mut y) = (0.0, 0.0);
+			for position in positions.iter_mut() {
+				x += position[0];
+				y += position[1];
+				position[0] = x * scale[0] + translate[0];
+				position[1] = y * scale[1] + translate[1];
+			}
+		}
+		Ok(positions)
+	}
+
+	// Stitch a list of arc indexes into a line. Negative indexes (~i) reference reversed arcs.
+	fn line(&self, indexes: &JsonValue) -> Result<Vec<Position>, Box<dyn Error>> {
+		let mut line: Vec<Position> = Vec::new();
+		for index in indexes.as_array().into_iter().flatten() {
+			let index = index
+				.as_i64()
+				.ok_or_else(|| invalid(format!("invalid TopoJSON arc index: {}", index)))?;
+			let arc = self
+				.arcs
+				.get(if index < 0 { !index } else { index } as usize)
+				.ok_or_else(|| invalid(format!("TopoJSON arc index out of range: {}", index)))?;
+
+			// consecutive arcs share their first and last position
+			if !line.is_empty() {
+				line.pop();
+			}
+			if index < 0 {
+				line.extend(arc.iter().rev().cloned());
+			} else {
+				line.extend(arc.iter().cloned());
+			}
+		}
+		Ok(line)
+	}
+}
+
+fn map_array<T>(
+	value: &JsonValue, f: impl Fn(&JsonValue) -> Result<T, Box<dyn Error>>,
+) -> Result<Vec<T>, Box<dyn Error>> {
+	value
+		.as_array()
+		.ok_or_else(|| invalid(format!("expected TopoJSON array: {}", value)))?
+		.iter()
+		.map(f)
+		.collect()
+}
+
+fn read_position(value: &JsonValue) -> Result<Position, Box<dyn Error>> {
+	let position = map_array(value, |v| {
+		v.as_f64()
+			.ok_or_else(|| invalid(format!("invalid TopoJSON coordinate: {}", v)))
+	})?;
+	if position.len() < 2 {
+		return Err(invalid(format!("invalid TopoJSON position: {}", value)));
+	}
+	Ok(position)
+}
+
+fn read_pair(value: &JsonValue) -> Result<[f64; 2], Box<dyn Error>> {
+	let position = read_position(value)?;
+	Ok([position[0], position[1]])
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	// Example topology from the TopoJSON specification
+	const TOPOLOGY: &str = r#"{
+		"type": "Topology",
+		"transform": { "scale": [0.0005, 0.0001], "translate": [100, 0] },
+		"objects": {
+			"example": {
+				"type": "GeometryCollection",
+				"geometries": [
+					{ "type": "Point", "properties": { "prop0": "value0" }, "coordinates": [4000, 5000] },
+					{ "type": "LineString", "id": "line", "arcs": [0] },
+					{ "type": "Polygon", "id": 3, "arcs": [[-2]] },
+					{ "type": null }
+				]
+			},
+			"other": { "type": "MultiLineString", "arcs": [[0, -2]] }
+		},
+		"arcs": [
+			[[4000, 0], [1999, 9999], [2000, -9999], [2000, 9999]],
+			[[0, 0], [0, 9999], [2000, 0], [0, -9999], [-2000, 0]]
+		]
+	}"#;
+
+	fn convert_lines(object: Option<&str>) -> Result<Vec<String>, Box<dyn Error>> {
+		Ok(String::from_utf8(convert(TOPOLOGY.as_bytes(), object)?)?
+			.lines()
+			.map(String::from)
+			.collect())
+	}
+
+	#[test]
+	fn test_convert_object() -> Result<(), Box<dyn Error>> {
+		let lines = convert_lines(Some("example"))?;
+		assert_eq!(lines.len(), 3);
+		assert_eq!(
+			lines[0],
+			"{\"geometry\":{\"coordinates\":[102.0,0.5],\"type\":\"Point\"},\"properties\":{\"prop0\":\"value0\"},\"type\":\"Feature\"}"
+		);
+		assert_eq!(
+			lines[1],
+			"{\"geometry\":{\"coordinates\":[[102.0,0.0],[102.9995,0.9999],[103.9995,0.0],[104.9995,0.9999]],\"type\":\"LineString\"},\"id\":\"line\",\"properties\":{},\"type\":\"Feature\"}"
+		);
+		assert_eq!(
+			lines[2],
+			"{\"geometry\":{\"coordinates\":[[[100.0,0.0],[101.0,0.0],[101.0,0.9999],[100.0,0.9999],[100.0,0.0]]],\"type\":\"Polygon\"},\"id\":3,\"properties\":{},\"type\":\"Feature\"}"
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn test_convert_all() -> Result<(), Box<dyn Error>> {
+		let lines = convert_lines(None)?;
+		assert_eq!(lines.len(), 4);
+		assert!(lines[3].contains("\"type\":\"MultiLineString\""));
+		assert!(convert_lines(Some("missing")).is_err());
+		Ok(())
+	}
+
+	#[test]
+	fn test_without_transform() -> Result<(), Box<dyn Error>> {
+		let topology = r#"{"type":"Topology","objects":{"a":{"type":"LineString","arcs":[0,1]}},"arcs":[[[0,0],[1,1]],[[1,1],[2,0]]]}"#;
+		assert_eq!(
+			String::from_utf8(convert(topology.as_bytes(), None)?)?,
+			"{\"geometry\":{\"coordinates\":[[0.0,0.0],[1.0,1.0],[2.0,0.0]],\"type\":\"LineString\"},\"properties\":{},\"type\":\"Feature\"}\n"
+		);
+		Ok(())
+	}
+}
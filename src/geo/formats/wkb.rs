@@ -0,0 +1,195 @@
+use super::invalid;
+use geojson::{Geometry, Position, Value};
+use std::{convert::TryInto, error::Error, result::Result};
+
+// Parse a Well-Known Binary geometry (ISO or EWKB flavour) into a geojson::Value
+pub fn parse_wkb(data: &[u8]) -> Result<Value, Box<dyn Error>> {
+	WkbReader { data, pos: 0 }.read_geometry()
+}
+
+struct WkbReader<'a> {
+	data: &'a [u8],
+	pos: usize,
+}
+
+// Byte order and dimensions of the geometry currently being read
+#[derive(Clone, Copy)]
+struct Layout {
+	little_endian: bool,
+	has_z: bool,
+	has_m: bool,
+}
+
+impl<'a> WkbReader<'a> {
+	fn read_geometry(&mut self) -> Result<Value, Box<dyn Error>> {
+		let little_endian = self.read_bytes(1)?[0] == 1;
+		let code = self.read_u32(little_endian)?;
+
+		// EWKB stores dimensions and SRID as high bit flags, ISO WKB adds multiples of 1000
+		let mut layout = Layout {
+			little_endian,
+			has_z: code & 0x8000_0000 != 0,
+			has_m: code & 0x4000_0000 != 0,
+		};
+		if code & 0x2000_0000 != 0 {
+			self.read_u32(little_endian)?;
+		}
+		let code = code & 0x0fff_ffff;
+		match code / 1000 {
+			0 => {}
+			1 => layout.has_z = true,
+			2 => layout.has_m = true,
+			3 => {
+				layout.has_z = true;
+				layout.has_m = true;
+			}
+			_ => return Err(invalid(format!("unsupported WKB geometry type: {}", code))),
+		}
+
+		Ok(match code % 1000 {
+			1 => Value::Point(self.read_position(layout)?),
+			2 => Value::LineString(self.read_positions(layout)?),
+			3 => Value::Polygon(self.read_rings(layout)?),
+			4 => Value::MultiPoint(self.read_parts(layout, |v| match v {
+				Value::Point(p) => Some(p),
+				_ => None,
+			})?),
+			5 => Value::MultiLineString(self.read_parts(layout, |v| match v {
+				Value::LineString(p) => Some(p),
+				_ => None,
+			})?),
+			6 => Value::MultiPolygon(self.read_parts(layout, |v| match v {
+				Value::Polygon(p) => Some(p),
+				_ => None,
+			})?),
+			7 => Value::GeometryCollection(self.read_parts(layout, |v| Some(Geometry::new(v)))?),
+			_ => return Err(invalid(format!("unsupported WKB geometry type: {}", code))),
+		})
+	}
+
+	// Read the nested geometries of a multi geometry or collection
+	fn read_parts<T>(&mut self, layout: Layout, convert: impl Fn(Value) -> Option<T>) -> Result<Vec<T>, Box<dyn Error>> {
+		let count = self.read_u32(layout.little_endian)?;
+		let mut parts = Vec::new();
+		for _ in 0..count {
+			let part = convert(self.read_geometry()?).ok_or_else(|| invalid("unexpected WKB part type".to_string()))?;
+			parts.push(part);
+		}
+		Ok(parts)
+	}
+
+	fn read_rings(&mut self, layout: Layout) -> Result<Vec<Vec<Position>>, Box<dyn Error>> {
+		let count = self.read_u32(layout.little_endian)?;
+		(0..count).map(|_| self.read_positions(layout)).collect()
+	}
+
+	fn read_positions(&mut self, layout: Layout) -> Result<Vec<Position>, Box<dyn Error>> {
+		let count = self.read_u32(layout.little_endian)?;
+		(0..count).map(|_| self.read_position(layout)).collect()
+	}
+
+	// Read a single position, keeping z but dropping m values
+	fn read_position(&mut self, layout: Layout) -> Result<Position, Box<dyn Error>> {
+		let mut position = vec![
+			self.read_f64(layout.little_endian)?,
+			self.read_f64(layout.little_endian)?,
+		];
+		if layout.has_z {
+			position.push(self.read_f64(layout.little_endian)?);
+		}
+		if layout.has_m {
+			self.read_f64(layout.little_endian)?;
+		}
+		Ok(position)
+	}
+
+	fn read_u32(&mut self, little_endian: bool) -> Result<u32, Box<dyn Error>> {
+		let bytes: [u8; 4] = self.read_bytes(4)?.try_into()?;
+		Ok(if little_endian {
+			u32::from_le_bytes(bytes)
+		} else {
+			u32::from_be_bytes(bytes)
+		})
+	}
+
+	fn read_f64(&mut self, little_endian: bool) -> Result<f64, Box<dyn Error>> {
+		let bytes: [u8; 8] = self.read_bytes(8)?.try_into()?;
+		Ok(if little_endian {
+			f64::from_le_bytes(bytes)
+		} else {
+			f64::from_be_bytes(bytes)
+		})
+	}
+
+	fn read_bytes(&mut self, length: usize) -> Result<&'a [u8], Box<dyn Error>> {
+		if self.pos + length > self.data.len() {
+			return Err(invalid("unexpected end of WKB data".to_string()));
+		}
+		let bytes = &self.data[self.pos..self.pos + length];
+		self.pos += length;
+		Ok(bytes)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn point_le(x: f64, y: f64) -> Vec<u8> {
+		let mut wkb = vec![1, 1, 0, 0, 0];
+		wkb.extend_from_slice(&x.to_le_bytes());
+		wkb.extend_from_slice(&y.to_le_bytes());
+		wkb
+	}
+
+	#[test]
+	fn test_point() -> Result<(), Box<dyn Error>> {
+		assert_eq!(parse_wkb(&point_le(1.5, 2.5))?, Value::Point(vec![1.5, 2.5]));
+		Ok(())
+	}
+
+	#[test]
+	fn test_point_big_endian_z() -> Result<(), Box<dyn Error>> {
+		let mut wkb = vec![0, 0, 0, 0x03, 0xe9];
+		for v in [1.0f64, 2.0, 3.0] {
+			wkb.extend_from_slice(&v.to_be_bytes());
+		}
+		assert_eq!(parse_wkb(&wkb)?, Value::Point(vec![1.0, 2.0, 3.0]));
+		Ok(())
+	}
+
+	#[test]
+	fn test_multi_point() -> Result<(), Box<dyn Error>> {
+		let mut wkb = vec![1, 4, 0, 0, 0, 2, 0, 0, 0];
+		wkb.extend(point_le(1.0, 2.0));
+		wkb.extend(point_le(3.0, 4.0));
+		assert_eq!(
+			parse_wkb(&wkb)?,
+			Value::MultiPoint(vec![vec![1.0, 2.0], vec![3.0, 4.0]])
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn test_polygon() -> Result<(), Box<dyn Error>> {
+		let mut wkb = vec![1, 3, 0, 0, 0, 1, 0, 0, 0, 4, 0, 0, 0];
+		for v in [0.0f64, 0.0, 1.0, 0.0, 1.0, 1.0, 0.0, 0.0] {
+			wkb.extend_from_slice(&v.to_le_bytes());
+		}
+		assert_eq!(
+			parse_wkb(&wkb)?,
+			Value::Polygon(vec![vec![
+				vec![0.0, 0.0],
+				vec![1.0, 0.0],
+				vec![1.0, 1.0],
+				vec![0.0, 0.0]
+			]])
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn test_truncated() {
+		assert!(parse_wkb(&point_le(1.0, 2.0)[0..12]).is_err());
+	}
+}
@@ -0,0 +1,116 @@
+use super::{
+	file::IdExtractor,
+	index::{load_versioned, save_versioned},
+	GeoIndex, GeoTable,
+};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::{error::Error, path::Path, result::Result, str::from_utf8};
+
+// A feature id with the range of its line in the table
+#[derive(Serialize, Deserialize, Debug)]
+struct IdEntry {
+	id: String,
+	start: usize,
+	length: usize,
+}
+
+// Ids of the features in a table, sorted for binary search
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GeoIdIndex {
+	source: String,
+	entries: Vec<IdEntry>,
+}
+impl GeoIdIndex {
+	// Extract the ids of all lines referenced by the leaves of the tree, and save them.
+	// The source describes the id option, see GeoFile::get_id_source.
+	pub fn create(
+		index: &GeoIndex, table: &GeoTable, id_extractor: &IdExtractor, source: &str, filename_ids: &Path,
+	) -> Result<Self, Box<dyn Error>> {
+		let data = table.get_data();
+		// features crossing the antimeridian have a second leaf for the same line, which is skipped
+		let ranges: Vec<(usize, usize)> = index.features().map(|leaf| (leaf.value1, leaf.value2)).collect();
+		let mut entries: Vec<IdEntry> = ranges
+			.par_iter()
+			.filter_map(|&(start, length)| {
+				let line = from_utf8(&data[start..start + length]).ok()?;
+				let id = id_extractor(line)?;
+				Some(IdEntry { id, start, length })
+			})
+			.collect();
+		entries.par_sort_by(|a, b| a.id.cmp(&b.id).then(a.start.cmp(&b.start)));
+
+		let ids = GeoIdIndex {
+			source: source.to_string(),
+			entries,
+		};
+		save_versioned(&ids, filename_ids)?;
+		Ok(ids)
+	}
+	// None if the file has another format version, see FORMAT_VERSION
+	pub fn load(filename_ids: &Path) -> Result<Option<Self>, Box<dyn Error>> {
+		load_versioned(filename_ids)
+	}
+	// The option the ids were taken from, to detect a changed colId or idProperty
+	pub fn source(&self) -> &str {
+		&self.source
+	}
+	// Ranges of all lines with this id, in table order
+	pub fn get<'a>(&'a self, id: &'a str) -> impl Iterator<Item = (usize, usize)> + 'a {
+		let first = self.entries.partition_point(|entry| entry.id.as_str() < id);
+		self.entries[first..]
+			.iter()
+			.take_while(move |entry| entry.id == id)
+			.map(|entry| (entry.start, entry.length))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::geo::{index::TreeType, GeoFile, GeoFileOptions};
+	use assert_fs::TempDir;
+	use std::fs::write;
+
+	#[test]
+	fn test_create_and_load_ids() -> Result<(), Box<dyn Error>> {
+		let dir = TempDir::new()?;
+		let filename = dir.path().join("lines.geojsonl");
+		let lines = [
+			"{\"type\":\"Feature\",\"id\":\"b\",\"properties\":{},\"geometry\":{\"type\":\"Point\",\"coordinates\":[1,1]}}",
+			"{\"type\":\"Feature\",\"id\":\"a\",\"properties\":{},\"geometry\":{\"type\":\"LineString\",\"coordinates\":[[179,1],[-179,1]]}}",
+			"{\"type\":\"Feature\",\"properties\":{},\"geometry\":{\"type\":\"Point\",\"coordinates\":[2,2]}}",
+			"{\"type\":\"Feature\",\"id\":\"b\",\"properties\":{},\"geometry\":{\"type\":\"Point\",\"coordinates\":[3,3]}}",
+		];
+		write(&filename, lines.join("\n") + "\n")?;
+		let options = GeoFileOptions::empty();
+		let mut geo_file = GeoFile::load(&filename, options.clone())?;
+		let filename_index = dir.path().join("lines.idx");
+		let filename_table = dir.path().join("lines.dat");
+		let filename_ids = dir.path().join("lines.ids");
+		let index = GeoIndex::create(&mut geo_file, TreeType::Binary, &filename_index, &filename_table, None)?;
+		let table = GeoTable::load(&filename_table)?;
+		let id_extractor = GeoFile::get_id_extractor(&filename, &options)?;
+
+		GeoIdIndex::create(&index, &table, &id_extractor, "id", &filename_ids)?;
+		let ids = GeoIdIndex::load(&filename_ids)?.unwrap();
+		assert_eq!(ids.source(), "id");
+		let get = |id: &str| -> Vec<&str> {
+			ids.get(id)
+				.map(|(start, length)| from_utf8(&table.get_data()[start..start + length]).unwrap())
+				.collect()
+		};
+		// the feature crossing the antimeridian has two leaves, but only one id entry
+		assert_eq!(ids.entries.len(), 3);
+		assert_eq!(get("a"), vec![lines[1]]);
+		let mut b = get("b");
+		b.sort();
+		assert_eq!(b, vec![lines[0], lines[3]]);
+		assert!(get("c").is_empty());
+
+		// ids of older versions are rebuilt
+		write(&filename_ids, bincode::serialize(&ids)?)?;
+		assert!(GeoIdIndex::load(&filename_ids)?.is_none());
+		Ok(())
+	}
+}
@@ -1,64 +1,321 @@
-use super::{GeoBBox, GeoFile, GeoNode};
-use serde::{Deserialize, Serialize};
+use super::{
+	file::TimeExtractor,
+	progress::{Progress, ProgressTracker},
+	GeoBBox, GeoEntry, GeoFile, GeoFileOptions, GeoNode, TimeRange,
+};
+use rayon::prelude::*;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use std::{
+	collections::{BTreeMap, HashMap},
 	error::Error,
 	fs::{read, write, File},
-	io::{BufWriter, Seek, Write},
+	io::{BufWriter, Error as IoError, ErrorKind, Read, Write},
 	path::Path,
 	result::Result,
-	time::Instant,
+	str::from_utf8,
 };
 
+// Subtrees with fewer leaves are built sequentially
+const PARALLEL_MIN_LEAVES: usize = 65536;
+
+// Index files start with these bytes and the format version
+const MAGIC: &[u8; 4] = b"GJDB";
+// Version of the index files, increased whenever their layout changes, so outdated indexes are rebuilt.
+// 1: twin leaves of features crossing the antimeridian. Older files have no header at all.
+// 2: time ranges of nodes, and the time property they were taken from.
+// 3: twin bboxes beside the nodes instead of in every node.
+// 4: time ranges beside the nodes as well.
+pub const FORMAT_VERSION: u32 = 4;
+
+// Write a value with the magic bytes and the format version, like all index files
+pub fn save_versioned<T: Serialize>(value: &T, filename: &Path) -> Result<(), Box<dyn Error>> {
+	let mut bytes = MAGIC.to_vec();
+	bytes.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+	bytes.extend(bincode::serialize(value)?);
+	write(filename, bytes)?;
+	Ok(())
+}
+
+// Read a value written by save_versioned, or None if the file has another format version and must be rebuilt
+pub fn load_versioned<T: DeserializeOwned>(filename: &Path) -> Result<Option<T>, Box<dyn Error>> {
+	let bytes = read(filename)?;
+	if GeoIndex::parse_version(&bytes) != Some(FORMAT_VERSION) {
+		return Ok(None);
+	}
+	Ok(Some(bincode::deserialize(&bytes[8..])?))
+}
+
+// How the tree is built: a binary tree by median splits, or a packed R-tree with a given node size,
+// with leaves ordered by Sort-Tile-Recursive or by Hilbert curve
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TreeType {
+	Binary,
+	Str(usize),
+	Hilbert(usize),
+}
+
+impl TreeType {
+	pub fn from_options(opt: &GeoFileOptions) -> Result<Self, Box<dyn Error>> {
+		let node_size = opt.node_size.unwrap_or(16);
+		if node_size < 2 {
+			return Err(Box::new(IoError::new(
+				ErrorKind::InvalidInput,
+				format!("nodeSize must be at least 2: {}", node_size),
+			)));
+		}
+		match opt.index_type.as_deref() {
+			None | Some("binary") => Ok(TreeType::Binary),
+			Some("str") => Ok(TreeType::Str(node_size)),
+			Some("hilbert") => Ok(TreeType::Hilbert(node_size)),
+			Some(name) => Err(Box::new(IoError::new(
+				ErrorKind::InvalidInput,
+				format!("Unsupported index type: {}", name),
+			))),
+		}
+	}
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct GeoIndex {
 	nodes: Vec<GeoNode>,
+	// the bboxes of the eastern twins of western leaves of features crossing the antimeridian, by node
+	twins: BTreeMap<usize, GeoBBox>,
+	// the time range of every node, for leaves the time of their feature, empty if no feature has a time
+	times: Vec<Option<TimeRange>>,
+	// the timeProperty option the times of the leaves were taken from
+	time_property: Option<String>,
 }
 impl GeoIndex {
-	pub fn create(geo_data: &mut GeoFile, filename_index: &Path, filename_table: &Path) -> Result<Self, Box<dyn Error>> {
-		let mut entries = geo_data.get_entries()?;
-		let mut index = GeoIndex { nodes: Vec::new() };
-		index.create_tree(entries.as_mut_slice());
-		index.rewrite_table(geo_data, filename_table)?;
+	pub fn create(
+		geo_data: &mut GeoFile, tree_type: TreeType, filename_index: &Path, filename_table: &Path,
+		progress: Option<&dyn Progress>,
+	) -> Result<Self, Box<dyn Error>> {
+		let entries = geo_data.get_entries(progress)?;
+		GeoIndex::create_from_entries(
+			entries,
+			geo_data.get_data(),
+			tree_type,
+			geo_data.time_property(),
+			filename_index,
+			filename_table,
+			progress,
+		)
+	}
+	// Build the tree from entries referencing ranges in the data, and write index and table.
+	// The time property names the option the times of the leaves were taken from.
+	pub fn create_from_entries(
+		entries: Vec<GeoEntry>, data: &[u8], tree_type: TreeType, time_property: Option<&str>, filename_index: &Path,
+		filename_table: &Path, progress: Option<&dyn Progress>,
+	) -> Result<Self, Box<dyn Error>> {
+		let mut index = GeoIndex {
+			nodes: Vec::new(),
+			twins: BTreeMap::new(),
+			times: Vec::new(),
+			time_property: time_property.map(String::from),
+		};
+		// while building, value1 of the leaves holds the number of their entry
+		let mut leaves: Vec<GeoNode> = entries
+			.iter()
+			.enumerate()
+			.map(|(i, entry)| GeoNode {
+				value1: i,
+				..entry.leaf.clone()
+			})
+			.collect();
+		match tree_type {
+			TreeType::Binary => index.create_tree(leaves.as_mut_slice()),
+			TreeType::Str(node_size) => index.create_packed_tree(leaves, node_size, sort_str),
+			TreeType::Hilbert(node_size) => index.create_packed_tree(leaves, node_size, sort_hilbert),
+		}
+		index.set_entries(&entries);
+		index.rewrite_table(data, filename_table, progress)?;
 		index.save(filename_index)?;
 		Ok(index)
 	}
 	pub fn load(filename_index: &Path) -> Result<Self, Box<dyn Error>> {
 		let bytes = read(filename_index)?;
-		let index = bincode::deserialize(&bytes)?;
-		Ok(index)
+		match GeoIndex::parse_version(&bytes) {
+			Some(FORMAT_VERSION) => Ok(bincode::deserialize(&bytes[8..])?),
+			version => Err(Box::new(IoError::new(
+				ErrorKind::InvalidData,
+				format!(
+					"Unsupported index format version {} in {}, expected {}",
+					version.map_or(String::from("unknown"), |version| version.to_string()),
+					filename_index.to_string_lossy(),
+					FORMAT_VERSION
+				),
+			))),
+		}
 	}
-	fn save(&self, filename_index: &Path) -> Result<(), Box<dyn Error>> {
-		write(filename_index, bincode::serialize(self)?)?;
-		Ok(())
+	pub fn save(&self, filename_index: &Path) -> Result<(), Box<dyn Error>> {
+		save_versioned(self, filename_index)
 	}
-	fn rewrite_table(&mut self, geo_data: &mut GeoFile, filename_table: &Path) -> Result<(), Box<dyn Error>> {
+	// The format version of an index file, None for files written before versions were stored
+	pub fn read_version(filename_index: &Path) -> Result<Option<u32>, Box<dyn Error>> {
+		let mut header = Vec::with_capacity(8);
+		File::open(filename_index)?.take(8).read_to_end(&mut header)?;
+		Ok(GeoIndex::parse_version(&header))
+	}
+	pub fn parse_version(bytes: &[u8]) -> Option<u32> {
+		match bytes.get(..8) {
+			Some(header) if header.starts_with(MAGIC) => {
+				Some(u32::from_le_bytes([header[4], header[5], header[6], header[7]]))
+			}
+			_ => None,
+		}
+	}
+	// The timeProperty option the times of the leaves were taken from
+	pub fn time_property(&self) -> Option<&str> {
+		self.time_property.as_deref()
+	}
+	// Take the times of the leaves from their lines in the table, e.g. after the timeProperty option changed.
+	// The tree keeps its structure, only the time ranges of the nodes are updated.
+	pub fn set_times(&mut self, table: &[u8], time_extractor: Option<&TimeExtractor>, time_property: Option<&str>) {
+		self.times = match time_extractor {
+			Some(time_extractor) => self
+				.nodes
+				.par_iter()
+				.map(|node| {
+					if !node.is_leaf {
+						return None;
+					}
+					let line = from_utf8(&table[node.value1..node.value1 + node.value2]).ok()?;
+					time_extractor(line).map(TimeRange::instant)
+				})
+				.collect(),
+			None => Vec::new(),
+		};
+		self.include_times();
+		self.time_property = time_property.map(String::from);
+	}
+	// Give the leaves the offsets of their entries, and keep the twins and times of the entries beside the nodes
+	fn set_entries(&mut self, entries: &[GeoEntry]) {
+		self.times = vec![None; self.nodes.len()];
+		for (i, leaf) in self.nodes.iter_mut().enumerate().filter(|(_, node)| node.is_leaf) {
+			let entry = &entries[leaf.value1];
+			leaf.value1 = entry.leaf.value1;
+			if let Some(twin) = &entry.twin {
+				self.twins.insert(i, twin.clone());
+			}
+			self.times[i] = entry.time;
+		}
+		self.include_times();
+	}
+	// Give the inner nodes the time ranges of their children, or drop all times if no leaf has one
+	fn include_times(&mut self) {
+		if self.times.iter().all(Option::is_none) {
+			self.times = Vec::new();
+			return;
+		}
+		// nodes are in preorder, so children follow their parent and are updated first in reverse order
+		for i in (0..self.nodes.len()).rev() {
+			let node = &self.nodes[i];
+			if node.is_leaf {
+				continue;
+			}
+			let mut time: Option<TimeRange> = None;
+			let mut child = node.value1;
+			while child != node.next && child > i && child < self.nodes.len() {
+				if let Some(child_time) = &self.times[child] {
+					match &mut time {
+						Some(time) => time.include(child_time),
+						None => time = Some(*child_time),
+					}
+				}
+				child = self.nodes[child].next;
+			}
+			self.times[i] = time;
+		}
+	}
+	// The time range of a node, None without a time property or if none of its leaves has a time
+	pub fn time(&self, index: usize) -> Option<TimeRange> {
+		self.times.get(index).copied().flatten()
+	}
+	// Whether a node has a time within the given time range. Without a time range, every node matches.
+	fn overlap_time(&self, index: usize, time: Option<&TimeRange>) -> bool {
+		match time {
+			Some(time) => self.time(index).is_some_and(|t| t.overlaps(time)),
+			None => true,
+		}
+	}
+	// The leaves as entries, e.g. to build a new index of them
+	pub fn entries(&self) -> impl Iterator<Item = GeoEntry> + '_ {
+		self.enumerate_leaves().map(move |(i, leaf)| GeoEntry {
+			leaf: GeoNode {
+				next: 0,
+				..leaf.clone()
+			},
+			twin: self.twins.get(&i).cloned(),
+			time: self.time(i),
+		})
+	}
+	// One leaf of every feature, without the western twins of features crossing the antimeridian
+	pub fn features(&self) -> impl Iterator<Item = &GeoNode> {
+		self
+			.enumerate_leaves()
+			.filter(move |(i, _)| !self.twins.contains_key(i))
+			.map(|(_, leaf)| leaf)
+	}
+	// Whether a leaf is the western twin of a feature whose eastern leaf overlaps the bbox as well
+	fn is_duplicate(&self, index: usize, bbox: &GeoBBox) -> bool {
+		self.twins.get(&index).is_some_and(|twin| twin.overlap_bbox(bbox))
+	}
+	// Number of nodes, used to address nodes across several indexes
+	pub fn node_count(&self) -> usize {
+		self.nodes.len()
+	}
+	// The root node, spanning the bbox of all leaves, see time(0) for their time range. Empty indexes have none.
+	pub fn root(&self) -> Option<&GeoNode> {
+		self.nodes.first()
+	}
+
+	// Leaves with their node numbers
+	pub fn enumerate_leaves(&self) -> impl Iterator<Item = (usize, &GeoNode)> {
+		self.nodes.iter().enumerate().filter(|(_, node)| node.is_leaf)
+	}
+	fn rewrite_table(
+		&mut self, data: &[u8], filename_table: &Path, progress: Option<&dyn Progress>,
+	) -> Result<(), Box<dyn Error>> {
 		let mut file = BufWriter::new(File::create(filename_table)?);
 		let mut pos: usize = 0;
-		let start = Instant::now();
+		let mut rows: usize = 0;
+		let tracker = ProgressTracker::new(progress, "table");
+		// both leaves of a feature crossing the antimeridian share its line, which is written only once.
+		// Only the western leaf knows of its twin, so the offsets of all such lines are collected first.
+		let mut twin_offsets: HashMap<usize, Option<usize>> =
+			self.twins.keys().map(|&i| (self.nodes[i].value1, None)).collect();
 
 		for i in 0..self.nodes.len() {
-			if i % 1000000 == 0 {
-				println!(
-					"rewrite_table: {}, {:.1}%, {:.0}/s, {:.1}MB/s",
-					i,
-					100. * i as f64 / self.nodes.len() as f64,
-					i as f64 / start.elapsed().as_secs_f64(),
-					file.stream_position()? as f64 / 1048576. / start.elapsed().as_secs_f64()
-				)
+			if i % 1000000 == 0 && i > 0 {
+				tracker.report(rows, pos, 100. * i as f64 / self.nodes.len() as f64);
 			}
 
 			if self.nodes[i].is_leaf {
 				let node = self.nodes.get_mut(i).unwrap();
-				let buffer = geo_data.read_range(node.value1, node.value2);
+				if let Some(offset) = twin_offsets.get_mut(&node.value1) {
+					match offset {
+						Some(offset) => {
+							node.value1 = *offset;
+							continue;
+						}
+						None => *offset = Some(pos),
+					}
+				}
+				let buffer = &data[node.value1..node.value1 + node.value2];
 				node.value1 = pos;
 				file.write_all(buffer)?;
 				pos += node.value2;
+				rows += 1;
 			}
 		}
+		tracker.report(rows, pos, 100.);
 		Ok(())
 	}
 	fn create_tree(&mut self, leaves: &mut [GeoNode]) {
-		create_tree_rec(leaves, &mut self.nodes);
+		if leaves.is_empty() {
+			return;
+		}
+		self.nodes = create_subtree(leaves);
 		for i in 0..self.nodes.len() {
 			if self.nodes[i].is_leaf {
 				continue;
@@ -70,26 +327,48 @@ impl GeoIndex {
 			self.nodes[value2].next = next;
 		}
 
+		// Build large subtrees in parallel and append them with shifted child indexes.
+		// Both halves are split exactly like in the sequential build, so the result is identical.
+		fn create_subtree(leaves: &mut [GeoNode]) -> Vec<GeoNode> {
+			let mut nodes: Vec<GeoNode> = Vec::new();
+			if leaves.len() < PARALLEL_MIN_LEAVES {
+				create_tree_rec(leaves, &mut nodes);
+				return nodes;
+			}
+
+			let mut node = sort_leaves(leaves);
+			let (part1, part2) = leaves.split_at_mut(leaves.len() / 2);
+			let (nodes1, nodes2) = rayon::join(|| create_subtree(part1), || create_subtree(part2));
+			node.value1 = 1;
+			node.value2 = 1 + nodes1.len();
+			nodes.reserve(node.value2 + nodes2.len());
+			nodes.push(node);
+			append_subtree(&mut nodes, nodes1);
+			append_subtree(&mut nodes, nodes2);
+			nodes
+		}
+
+		fn append_subtree(nodes: &mut Vec<GeoNode>, subtree: Vec<GeoNode>) {
+			let offset = nodes.len();
+			nodes.extend(subtree.into_iter().map(|mut node| {
+				if !node.is_leaf {
+					node.value1 += offset;
+					node.value2 += offset;
+				}
+				node
+			}));
+		}
+
 		fn create_tree_rec(leaves: &mut [GeoNode], nodes: &mut Vec<GeoNode>) -> usize {
 			if leaves.len() == 1 {
 				let index = nodes.len();
 				nodes.push(leaves[0].clone());
 				index
 			} else {
-				let mut bbox = GeoBBox::new_empty();
-				for entry in leaves.iter() {
-					bbox.include_bbox(&entry.bbox);
-				}
-				if bbox.is_horizontal() {
-					// sort by x
-					leaves.sort_unstable_by(|a, b| a.bbox.sum_x().partial_cmp(&b.bbox.sum_x()).unwrap())
-				} else {
-					// sort by y
-					leaves.sort_unstable_by(|a, b| a.bbox.sum_y().partial_cmp(&b.bbox.sum_y()).unwrap())
-				}
+				let node = sort_leaves(leaves);
 				let (part1, part2) = leaves.split_at_mut(leaves.len() / 2);
 				let index = nodes.len();
-				nodes.push(GeoNode::new_node(bbox));
+				nodes.push(node);
 				let value1 = create_tree_rec(part1, nodes);
 				let value2 = create_tree_rec(part2, nodes);
 				let node = nodes.get_mut(index).unwrap();
@@ -98,14 +377,114 @@ impl GeoIndex {
 				index
 			}
 		}
+
+		// Create the parent node of all leaves, with their bbox, and sort them along the
+		// longer axis of the bbox. The sort is stable, so its result doesn't depend on the number of threads.
+		fn sort_leaves(leaves: &mut [GeoNode]) -> GeoNode {
+			let empty = || GeoNode::new_node(GeoBBox::new_empty());
+			let node = leaves
+				.par_iter()
+				.with_min_len(4096)
+				.fold(empty, |mut node, entry| {
+					node.bbox.include_bbox(&entry.bbox);
+					node
+				})
+				.reduce(empty, |mut node1, node2| {
+					node1.bbox.include_bbox(&node2.bbox);
+					node1
+				});
+			if node.bbox.is_horizontal() {
+				// sort by x
+				leaves.par_sort_by(|a, b| a.bbox.sum_x().partial_cmp(&b.bbox.sum_x()).unwrap())
+			} else {
+				// sort by y
+				leaves.par_sort_by(|a, b| a.bbox.sum_y().partial_cmp(&b.bbox.sum_y()).unwrap())
+			}
+			node
+		}
 	}
-	pub fn query_bbox(&self, bbox: &GeoBBox, start_index: usize, max_count: usize) -> (Vec<&GeoNode>, usize) {
+	// Build a packed R-tree bottom up: each level is ordered by the sort function and cut into nodes of
+	// node_size children. Nodes are stored in preorder, with value1 pointing to the first child and
+	// next pointing to the next sibling, so queries traverse it just like the binary tree.
+	fn create_packed_tree(&mut self, leaves: Vec<GeoNode>, node_size: usize, sort: fn(&mut [GeoNode], usize)) {
+		if leaves.is_empty() {
+			return;
+		}
+
+		// while building, value1 and value2 of inner nodes hold the range of their children in the level below
+		let mut levels: Vec<Vec<GeoNode>> = vec![leaves];
+		while levels.last().unwrap().len() > 1 {
+			let children = levels.last_mut().unwrap();
+			sort(children, node_size);
+			let mut parents: Vec<GeoNode> = Vec::new();
+			for start in (0..children.len()).step_by(node_size) {
+				let end = (start + node_size).min(children.len());
+				let mut parent = GeoNode::new_node(GeoBBox::new_empty());
+				children[start..end]
+					.iter()
+					.for_each(|child| parent.bbox.include_bbox(&child.bbox));
+				parent.value1 = start;
+				parent.value2 = end;
+				parents.push(parent);
+			}
+			levels.push(parents);
+		}
+
+		let root_level = levels.len() - 1;
+		emit(&levels, root_level, 0, &mut self.nodes);
+
+		// emit nodes in preorder and link the children of each node, returning the index of the node
+		fn emit(levels: &[Vec<GeoNode>], level: usize, position: usize, nodes: &mut Vec<GeoNode>) -> usize {
+			let index = nodes.len();
+			let mut node = levels[level][position].clone();
+			if level == 0 {
+				nodes.push(node);
+				return index;
+			}
+
+			let (start, end) = (node.value1, node.value2);
+			node.value1 = index + 1;
+			node.value2 = 0;
+			nodes.push(node);
+			let mut previous: Option<usize> = None;
+			for child in start..end {
+				let child_index = emit(levels, level - 1, child, nodes);
+				if let Some(previous) = previous {
+					nodes[previous].next = child_index;
+				}
+				previous = Some(child_index);
+			}
+			index
+		}
+
+		// the last child of a node continues with the next sibling of its parent
+		for i in 0..self.nodes.len() {
+			if self.nodes[i].is_leaf {
+				continue;
+			}
+			let mut child = self.nodes[i].value1;
+			while self.nodes[child].next != 0 {
+				child = self.nodes[child].next;
+			}
+			self.nodes[child].next = self.nodes[i].next;
+		}
+	}
+
+	// Find the leaves overlapping the bbox and, if given, the time range
+	pub fn query_bbox(
+		&self, bbox: &GeoBBox, time: Option<&TimeRange>, start_index: usize, max_count: usize,
+	) -> (Vec<&GeoNode>, usize) {
 		let mut leaves: Vec<&GeoNode> = Vec::with_capacity(max_count);
 		let mut index = start_index;
+		// an empty input leaves an index without any nodes
+		if self.nodes.is_empty() {
+			return (leaves, 0);
+		}
 
 		loop {
 			let node = &self.nodes[index];
-			if node.bbox.overlap_bbox(bbox) {
+			// the twin leaf of a feature crossing the antimeridian is skipped if its other half is found anyway
+			if node.bbox.overlap_bbox(bbox) && self.overlap_time(index, time) && !self.is_duplicate(index, bbox) {
 				if node.is_leaf {
 					leaves.push(node);
 					index = node.next;
@@ -124,13 +503,173 @@ impl GeoIndex {
 		}
 		(leaves, index)
 	}
+	// Like query_bbox, but only checks the given leaves, e.g. from an attribute index. They are given as
+	// disjoint lists sorted by node number, which are merged, so the returned index continues with the next leaf.
+	pub fn query_leaves(
+		&self, bbox: &GeoBBox, time: Option<&TimeRange>, candidates: &[&[usize]], start_index: usize, max_count: usize,
+	) -> (Vec<&GeoNode>, usize) {
+		let mut leaves: Vec<&GeoNode> = Vec::new();
+		let mut positions: Vec<usize> = candidates
+			.iter()
+			.map(|list| list.partition_point(|node| *node < start_index))
+			.collect();
+		let next_candidate = |positions: &[usize]| {
+			(0..candidates.len())
+				.filter(|&list| positions[list] < candidates[list].len())
+				.min_by_key(|&list| candidates[list][positions[list]])
+		};
+
+		while let Some(list) = next_candidate(&positions) {
+			let index = candidates[list][positions[list]];
+			let node = &self.nodes[index];
+			positions[list] += 1;
+			if node.bbox.overlap_bbox(bbox) && self.overlap_time(index, time) && !self.is_duplicate(index, bbox) {
+				leaves.push(node);
+				if leaves.len() >= max_count {
+					let next_index = next_candidate(&positions).map_or(0, |list| candidates[list][positions[list]]);
+					return (leaves, next_index);
+				}
+			}
+		}
+		(leaves, 0)
+	}
+	// Check the tree: links within bounds, every node reached exactly once, children within the bbox and
+	// time range of their parent, and leaves referencing lines within a table of this length
+	pub fn verify(&self, table_length: usize) -> Vec<String> {
+		let mut problems: Vec<String> = Vec::new();
+		let count = self.nodes.len();
+		if !self.times.is_empty() && self.times.len() != count {
+			problems.push(format!("times of {} nodes, but {} nodes", self.times.len(), count));
+			return problems;
+		}
+		for &i in self.twins.keys().filter(|&&i| i >= count || !self.nodes[i].is_leaf) {
+			problems.push(format!("node {}: twin of a node that is no leaf", i));
+		}
+		for (i, node) in self.nodes.iter().enumerate() {
+			if node.next >= count || (node.next != 0 && node.next <= i) {
+				problems.push(format!("node {}: invalid next node {}", i, node.next));
+			}
+			if node.is_leaf {
+				if node.value1 + node.value2 > table_length || node.value2 == 0 {
+					problems.push(format!(
+						"leaf {}: line {}..{} outside of the table with {} bytes",
+						i,
+						node.value1,
+						node.value1 + node.value2,
+						table_length
+					));
+				}
+				continue;
+			}
+			if node.value1 <= i || node.value1 >= count {
+				problems.push(format!("node {}: invalid first child {}", i, node.value1));
+				continue;
+			}
+			// children are linked by next, the last one continues with the next node after the parent
+			let mut child = node.value1;
+			while child != node.next && child > i && child < count {
+				let child_node = &self.nodes[child];
+				if !node.bbox.contains_bbox(&child_node.bbox) {
+					problems.push(format!("node {}: bbox doesn't contain child {}", i, child));
+				}
+				if let Some(time) = self.time(child) {
+					if !self
+						.time(i)
+						.is_some_and(|parent| parent.min <= time.min && parent.max >= time.max)
+					{
+						problems.push(format!("node {}: time range doesn't contain child {}", i, child));
+					}
+				}
+				child = child_node.next;
+			}
+		}
+		if !problems.is_empty() || count == 0 {
+			return problems;
+		}
+
+		// a query of everything follows first children and next links through all nodes
+		let mut visited = 0;
+		let mut index = 0;
+		loop {
+			visited += 1;
+			if visited > count {
+				problems.push(String::from("the nodes contain a cycle"));
+				break;
+			}
+			let node = &self.nodes[index];
+			index = if node.is_leaf { node.next } else { node.value1 };
+			if index == 0 {
+				break;
+			}
+		}
+		if visited < count {
+			problems.push(format!("only {} of {} nodes are reachable", visited, count));
+		}
+		problems
+	}
+}
+
+// Sort-Tile-Recursive: sort by x, cut into vertical slices of whole nodes, then sort each slice by y
+fn sort_str(nodes: &mut [GeoNode], node_size: usize) {
+	let node_count = nodes.len().div_ceil(node_size);
+	let slice_count = (node_count as f64).sqrt().ceil() as usize;
+	let slice_size = node_count.div_ceil(slice_count) * node_size;
+	nodes.par_sort_by(|a, b| a.bbox.sum_x().total_cmp(&b.bbox.sum_x()));
+	nodes
+		.par_chunks_mut(slice_size)
+		.for_each(|slice| slice.sort_by(|a, b| a.bbox.sum_y().total_cmp(&b.bbox.sum_y())));
+}
+
+// Sort by the position of the bbox centers along a Hilbert curve covering the extent of all centers
+fn sort_hilbert(nodes: &mut [GeoNode], _node_size: usize) {
+	let centers: Vec<(f32, f32)> = nodes.iter().map(|node| node.bbox.center()).collect();
+	let (x_min, x_max) = centers
+		.iter()
+		.fold((f32::MAX, f32::MIN), |(a, b), c| (a.min(c.0), b.max(c.0)));
+	let (y_min, y_max) = centers
+		.iter()
+		.fold((f32::MAX, f32::MIN), |(a, b), c| (a.min(c.1), b.max(c.1)));
+	let scale = |value: f32, min: f32, max: f32| -> u32 {
+		if max > min {
+			((value - min) / (max - min) * 65535.0) as u32
+		} else {
+			0
+		}
+	};
+	nodes.par_sort_by_cached_key(|node| {
+		let (x, y) = node.bbox.center();
+		hilbert_index(scale(x, x_min, x_max), scale(y, y_min, y_max))
+	});
+}
+
+// Position of a point on a Hilbert curve of order 16
+fn hilbert_index(mut x: u32, mut y: u32) -> u64 {
+	let mut index: u64 = 0;
+	let mut s: u32 = 1 << 15;
+	while s > 0 {
+		let rx = (x & s > 0) as u32;
+		let ry = (y & s > 0) as u32;
+		index += (s as u64) * (s as u64) * ((3 * rx) ^ ry) as u64;
+		// rotate the quadrant, so that the curve stays continuous
+		if ry == 0 {
+			if rx == 1 {
+				x = 65535 - x;
+				y = 65535 - y;
+			}
+			std::mem::swap(&mut x, &mut y);
+		}
+		s >>= 1;
+	}
+	index
 }
 
 #[cfg(test)]
+#[allow(clippy::needless_borrow, clippy::useless_vec)]
 mod tests {
 	use super::*;
-	use crate::geo::GeoFileOptions;
+	use crate::geo::{GeoFileOptions, ProgressEvent};
 	use assert_fs::NamedTempFile;
+	use std::{cell::RefCell, str::from_utf8};
 
 	#[test]
 	fn test_create_and_load_geo_index() -> Result<(), Box<dyn Error>> {
@@ -140,19 +679,25 @@ mod tests {
 
 		let mut geo_data = GeoFile::load(&filename, GeoFileOptions::empty())?;
 
-		let geo_index1 = GeoIndex::create(&mut geo_data, filename_index.path(), filename_table.path())?;
+		let geo_index1 = GeoIndex::create(
+			&mut geo_data,
+			TreeType::Binary,
+			filename_index.path(),
+			filename_table.path(),
+			None,
+		)?;
 		let geo_index2 = GeoIndex::load(&filename_index)?;
 		let bbox = GeoBBox::new(10., 10.2, 51., 51.2);
 		let node1 = GeoNode {
 			is_leaf: true,
-			bbox: GeoBBox::new(10.1946335, 10.1953125, 51.10852, 51.10955),
+			bbox: GeoBBox::new(10.1946335, 10.1953125, 51.108517, 51.10955),
 			value1: 1420116,
 			value2: 696,
 			next: 3914,
 		};
 		let node2 = GeoNode {
 			is_leaf: true,
-			bbox: GeoBBox::new(10.1953125, 10.195699, 51.10852, 51.109306),
+			bbox: GeoBBox::new(10.1953125, 10.195699, 51.108517, 51.109306),
 			value1: 1420812,
 			value2: 648,
 			next: 3915,
@@ -161,23 +706,420 @@ mod tests {
 		for geo_index in vec![geo_index1, geo_index2] {
 			assert_eq!(geo_index.nodes.len(), 7155);
 
-			let (leaves, index) = geo_index.query_bbox(&bbox, 0, 10);
+			let (leaves, index) = geo_index.query_bbox(&bbox, None, 0, 10);
 			assert_eq!(leaves, vec![&node1, &node2]);
 			assert_eq!(index, 0);
 
-			let (leaves, index) = geo_index.query_bbox(&bbox, 0, 1);
+			let (leaves, index) = geo_index.query_bbox(&bbox, None, 0, 1);
 			assert_eq!(leaves, vec![&node1]);
 			assert_eq!(index, 3914);
 
-			let (leaves, index) = geo_index.query_bbox(&bbox, 3914, 1);
+			let (leaves, index) = geo_index.query_bbox(&bbox, None, 3914, 1);
 			assert_eq!(leaves, vec![&node2]);
 			assert_eq!(index, 3915);
 
-			let (leaves, index) = geo_index.query_bbox(&bbox, 3915, 1);
+			let (leaves, index) = geo_index.query_bbox(&bbox, None, 3915, 1);
 			assert_eq!(leaves, Vec::<&GeoNode>::new());
 			assert_eq!(index, 0);
 		}
 
 		Ok(())
 	}
+
+	#[test]
+	fn test_query_antimeridian() -> Result<(), Box<dyn Error>> {
+		let filename = NamedTempFile::new("pacific.geojsonl")?;
+		let filename_index = NamedTempFile::new("temp.idx")?;
+		let filename_table = NamedTempFile::new("temp.dat")?;
+		std::fs::write(
+			filename.path(),
+			concat!(
+				"{\"type\":\"Feature\",\"properties\":{},\"geometry\":{\"type\":\"LineString\",\"coordinates\":[[170,0],[-170,10]]}}\n",
+				"{\"type\":\"Feature\",\"properties\":{},\"geometry\":{\"type\":\"Point\",\"coordinates\":[0,5]}}\n",
+			),
+		)?;
+		let mut geo_data = GeoFile::load(filename.path(), GeoFileOptions::empty())?;
+		let rows = RefCell::new(0);
+		let progress = |event: &ProgressEvent| *rows.borrow_mut() = event.rows;
+		let geo_index = GeoIndex::create(
+			&mut geo_data,
+			TreeType::Binary,
+			filename_index.path(),
+			filename_table.path(),
+			Some(&progress),
+		)?;
+		// progress counts lines, not leaves
+		assert_eq!(rows.into_inner(), 2);
+
+		let count = |bbox: GeoBBox| geo_index.query_bbox(&bbox, None, 0, 10).0.len();
+		assert_eq!(count(GeoBBox::new(175., 176., 0., 10.)), 1);
+		assert_eq!(count(GeoBBox::new(-176., -175., 0., 10.)), 1);
+		assert_eq!(count(GeoBBox::new(170., -170., -20., 20.)), 1);
+		assert_eq!(count(GeoBBox::new(-180., 180., -20., 20.)), 2);
+		assert_eq!(count(GeoBBox::new(-10., 10., -20., 20.)), 1);
+
+		// both halves of the line point to a single copy of it
+		let table = std::fs::read(filename_table.path())?;
+		assert_eq!(table.len(), std::fs::metadata(filename.path())?.len() as usize - 2);
+		assert_eq!(geo_index.twins.len(), 1);
+		let twin = &geo_index.nodes[*geo_index.twins.keys().next().unwrap()];
+		assert_eq!(
+			geo_index
+				.enumerate_leaves()
+				.filter(|(_, leaf)| leaf.value1 == twin.value1)
+				.count(),
+			2
+		);
+		assert_eq!(geo_index.features().count(), 2);
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_node_size() -> Result<(), Box<dyn Error>> {
+		// twins and times are kept beside the nodes, so they don't enlarge every node
+		assert!(std::mem::size_of::<GeoNode>() <= 48);
+
+		let filename_index = NamedTempFile::new("temp.idx")?;
+		let filename_table = NamedTempFile::new("temp.dat")?;
+		let mut entries: Vec<GeoEntry> = (0..3)
+			.map(|i| GeoEntry::new(GeoNode::new_leaf(GeoBBox::from_f64_point(i as f64, 0.), i * 2, 1)))
+			.collect();
+		let create = |entries: Vec<GeoEntry>| {
+			GeoIndex::create_from_entries(
+				entries,
+				b"1\n2\n3\n",
+				TreeType::Binary,
+				Some("time"),
+				filename_index.path(),
+				filename_table.path(),
+				None,
+			)
+		};
+		let geo_index = create(entries.clone())?;
+		assert!(geo_index.twins.is_empty());
+		assert!(geo_index.times.is_empty());
+		assert_eq!(geo_index.time(0), None);
+
+		entries[1].time = Some(TimeRange::instant(5));
+		let geo_index = create(entries)?;
+		assert_eq!(geo_index.times.len(), geo_index.nodes.len());
+		assert_eq!(geo_index.time(0), Some(TimeRange::instant(5)));
+		Ok(())
+	}
+
+	#[test]
+	fn test_empty_input() -> Result<(), Box<dyn Error>> {
+		let filename_index = NamedTempFile::new("temp.idx")?;
+		let filename_table = NamedTempFile::new("temp.dat")?;
+		for tree_type in [TreeType::Binary, TreeType::Str(4), TreeType::Hilbert(3)] {
+			let index = GeoIndex::create_from_entries(
+				Vec::new(),
+				b"",
+				tree_type,
+				None,
+				filename_index.path(),
+				filename_table.path(),
+				None,
+			)?;
+			let bbox = GeoBBox::new(-180., 180., -90., 90.);
+			assert_eq!(index.query_bbox(&bbox, None, 0, 10), (Vec::new(), 0), "{:?}", tree_type);
+			assert!(index.root().is_none());
+			assert_eq!(GeoIndex::load(filename_index.path())?.node_count(), 0);
+		}
+		Ok(())
+	}
+
+	#[test]
+	fn test_packed_trees() -> Result<(), Box<dyn Error>> {
+		let filename = Path::new("testdata/polygons.geojsonl.br");
+		let bboxes = [
+			GeoBBox::new(10., 10.2, 51., 51.2),
+			GeoBBox::new(6., 15., 47., 55.),
+			GeoBBox::new(13.3, 13.4, 52.5, 52.6),
+			GeoBBox::new(0., 1., 0., 1.),
+		];
+
+		type Lines = Vec<Vec<u8>>;
+		let query_all = |tree_type: TreeType| -> Result<(usize, Vec<Lines>), Box<dyn Error>> {
+			let filename_index = NamedTempFile::new("temp.idx")?;
+			let filename_table = NamedTempFile::new("temp.dat")?;
+			let mut geo_data = GeoFile::load(filename, GeoFileOptions::empty())?;
+			let geo_index = GeoIndex::create(
+				&mut geo_data,
+				tree_type,
+				filename_index.path(),
+				filename_table.path(),
+				None,
+			)?;
+			let table = std::fs::read(filename_table.path())?;
+			let results = bboxes
+				.iter()
+				.map(|bbox| {
+					// query in small pages to test the cursor
+					let mut lines: Lines = Vec::new();
+					let mut index = 0;
+					loop {
+						let (leaves, next) = geo_index.query_bbox(bbox, None, index, 7);
+						lines.extend(
+							leaves
+								.iter()
+								.map(|leaf| table[leaf.value1..leaf.value1 + leaf.value2].to_vec()),
+						);
+						if next == 0 {
+							break;
+						}
+						index = next;
+					}
+					lines.sort();
+					lines
+				})
+				.collect();
+			Ok((geo_index.nodes.len(), results))
+		};
+
+		let (binary_size, expected) = query_all(TreeType::Binary)?;
+		assert_eq!(binary_size, 7155);
+		assert!(expected[1].len() > 100);
+		assert!(expected[3].is_empty());
+		for tree_type in [
+			TreeType::Str(16),
+			TreeType::Hilbert(16),
+			TreeType::Str(64),
+			TreeType::Hilbert(2),
+		] {
+			let (size, results) = query_all(tree_type)?;
+			assert_eq!(results, expected);
+			if tree_type != TreeType::Hilbert(2) {
+				assert!(size < binary_size * 6 / 10);
+			}
+		}
+		Ok(())
+	}
+
+	#[test]
+	fn test_tree_type_from_options() -> Result<(), Box<dyn Error>> {
+		let mut options = GeoFileOptions::empty();
+		assert_eq!(TreeType::from_options(&options)?, TreeType::Binary);
+		options.index_type = Some(String::from("hilbert"));
+		assert_eq!(TreeType::from_options(&options)?, TreeType::Hilbert(16));
+		options.index_type = Some(String::from("str"));
+		options.node_size = Some(32);
+		assert_eq!(TreeType::from_options(&options)?, TreeType::Str(32));
+		options.node_size = Some(1);
+		assert!(TreeType::from_options(&options).is_err());
+		options.index_type = Some(String::from("quadtree"));
+		options.node_size = None;
+		assert!(TreeType::from_options(&options).is_err());
+		Ok(())
+	}
+
+	#[test]
+	fn test_hilbert_index() {
+		assert_eq!(hilbert_index(0, 0), 0);
+		assert_eq!(hilbert_index(65535, 0), 65536u64 * 65536 - 1);
+
+		// the curve fills the first 4x4 cells before leaving them
+		let mut indexes: Vec<u64> = (0..16).map(|i| hilbert_index(i % 4, i / 4)).collect();
+		indexes.sort();
+		assert_eq!(indexes, (0..16).collect::<Vec<u64>>());
+	}
+
+	#[test]
+	fn test_parallel_build_is_deterministic() -> Result<(), Box<dyn Error>> {
+		// enough leaves with many equal coordinates to build subtrees in parallel
+		let leaves: Vec<GeoNode> = (0..200000)
+			.map(|i| {
+				let (x, y) = ((i * 7919 % 1000) as f32, (i * 104729 % 997) as f32);
+				GeoNode::new_leaf(GeoBBox::new(x, x + 1.0, y, y + 1.0), i, 1)
+			})
+			.collect();
+
+		let build = |threads: usize| -> Result<Vec<GeoNode>, Box<dyn Error>> {
+			let pool = rayon::ThreadPoolBuilder::new().num_threads(threads).build()?;
+			let mut index = GeoIndex {
+				nodes: Vec::new(),
+				twins: BTreeMap::new(),
+				times: Vec::new(),
+				time_property: None,
+			};
+			pool.install(|| index.create_tree(leaves.clone().as_mut_slice()));
+			Ok(index.nodes)
+		};
+
+		let nodes = build(1)?;
+		assert_eq!(nodes.len(), 2 * leaves.len() - 1);
+		assert!(nodes == build(4)?);
+
+		let index = GeoIndex {
+			nodes,
+			twins: BTreeMap::new(),
+			times: Vec::new(),
+			time_property: None,
+		};
+		let bbox = GeoBBox::new(100.5, 120.5, 200.5, 210.5);
+		let mut found: Vec<usize> = index
+			.query_bbox(&bbox, None, 0, leaves.len())
+			.0
+			.iter()
+			.map(|n| n.value1)
+			.collect();
+		found.sort();
+		let expected: Vec<usize> = leaves
+			.iter()
+			.filter(|leaf| leaf.bbox.overlap_bbox(&bbox))
+			.map(|leaf| leaf.value1)
+			.collect();
+		assert_eq!(found, expected);
+		Ok(())
+	}
+
+	#[test]
+	fn test_verify_and_version() -> Result<(), Box<dyn Error>> {
+		let filename_index = NamedTempFile::new("temp.idx")?;
+		let filename_table = NamedTempFile::new("temp.dat")?;
+		for tree_type in [TreeType::Binary, TreeType::Str(4), TreeType::Hilbert(3)] {
+			let mut geo_data = GeoFile::load(Path::new("testdata/polygons.geojsonl.br"), GeoFileOptions::empty())?;
+			let index = GeoIndex::create(
+				&mut geo_data,
+				tree_type,
+				filename_index.path(),
+				filename_table.path(),
+				None,
+			)?;
+			let table_length = std::fs::metadata(filename_table.path())?.len() as usize;
+			assert_eq!(index.verify(table_length), Vec::<String>::new(), "{:?}", tree_type);
+			assert_eq!(index.verify(table_length - 1).len(), 1);
+		}
+		assert_eq!(GeoIndex::read_version(filename_index.path())?, Some(FORMAT_VERSION));
+
+		let mut index = GeoIndex::load(filename_index.path())?;
+		let last = index.nodes.len() - 1;
+		index.nodes[1].next = last;
+		assert!(!index.verify(usize::MAX).is_empty());
+		index.nodes[0].bbox = GeoBBox::new(0., 1., 0., 1.);
+		assert!(index.verify(usize::MAX).iter().any(|problem| problem.contains("bbox")));
+
+		// indexes written before versions were stored are rejected
+		std::fs::write(filename_index.path(), bincode::serialize(&index)?)?;
+		assert_eq!(GeoIndex::read_version(filename_index.path())?, None);
+		assert!(GeoIndex::load(filename_index.path()).is_err());
+		Ok(())
+	}
+
+	#[test]
+	fn test_progress() -> Result<(), Box<dyn Error>> {
+		let filename_index = NamedTempFile::new("temp.idx")?;
+		let filename_table = NamedTempFile::new("temp.dat")?;
+		let mut geo_data = GeoFile::load(Path::new("testdata/polygons.geojsonl.br"), GeoFileOptions::empty())?;
+
+		let events: RefCell<Vec<ProgressEvent>> = RefCell::new(Vec::new());
+		let progress = |event: &ProgressEvent| events.borrow_mut().push(event.clone());
+		GeoIndex::create(
+			&mut geo_data,
+			TreeType::Binary,
+			filename_index.path(),
+			filename_table.path(),
+			Some(&progress),
+		)?;
+
+		let events = events.into_inner();
+		let last_entries = events.iter().rfind(|e| e.phase == "entries").unwrap();
+		assert_eq!(last_entries.rows, 3578);
+		assert_eq!(last_entries.percent, 100.);
+		let last_table = events.last().unwrap();
+		assert_eq!(last_table.phase, "table");
+		assert_eq!(last_table.rows, 3578);
+		assert_eq!(
+			last_table.bytes,
+			std::fs::metadata(filename_table.path())?.len() as usize
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn test_query_time() -> Result<(), Box<dyn Error>> {
+		// a grid of points with times, some without
+		let mut data: Vec<u8> = Vec::new();
+		let mut entries: Vec<GeoEntry> = Vec::new();
+		for i in 0..400 {
+			let start = data.len();
+			data.extend_from_slice(format!("{}\n", i).as_bytes());
+			let x = (i % 20) as f64;
+			let mut entry = GeoEntry::new(GeoNode::new_leaf(
+				GeoBBox::from_f64_point(x, (i / 20) as f64),
+				start,
+				data.len() - start - 1,
+			));
+			if i % 7 != 0 {
+				entry.time = Some(TimeRange::instant((i * 37 % 400) as i64));
+			}
+			entries.push(entry);
+		}
+		let bbox = GeoBBox::new(2.5, 12.5, 3.5, 15.5);
+		let time = TimeRange::new(100, 199);
+		let mut expected: Vec<usize> = entries
+			.iter()
+			.filter(|entry| entry.leaf.bbox.overlap_bbox(&bbox) && entry.time.is_some_and(|t| t.overlaps(&time)))
+			.map(|entry| entry.leaf.value1)
+			.collect();
+		expected.sort();
+		assert!(!expected.is_empty());
+
+		for tree_type in [TreeType::Binary, TreeType::Str(4), TreeType::Hilbert(8)] {
+			let filename_index = NamedTempFile::new("temp.idx")?;
+			let filename_table = NamedTempFile::new("temp.dat")?;
+			let mut geo_index = GeoIndex::create_from_entries(
+				entries.clone(),
+				&data,
+				tree_type,
+				Some("time"),
+				filename_index.path(),
+				filename_table.path(),
+				None,
+			)?;
+			let table = std::fs::read(filename_table.path())?;
+			let find = |geo_index: &GeoIndex| -> Vec<usize> {
+				let (leaves, next) = geo_index.query_bbox(&bbox, Some(&time), 0, 1000);
+				assert_eq!(next, 0);
+				let mut found: Vec<usize> = leaves
+					.iter()
+					.map(|leaf| {
+						let line = from_utf8(&table[leaf.value1..leaf.value1 + leaf.value2]).unwrap();
+						// the offset of the line in the original data
+						let i: usize = line.parse().unwrap();
+						entries[i].leaf.value1
+					})
+					.collect();
+				found.sort();
+				found
+			};
+			assert_eq!(find(&geo_index), expected);
+			assert_eq!(geo_index.time_property(), Some("time"));
+
+			// inner nodes cover the times of their leaves
+			assert_eq!(geo_index.time(0), Some(TimeRange::new(1, 399)));
+			assert_eq!(geo_index.times.len(), geo_index.nodes.len());
+
+			// times taken again from the lines give the same tree, no times give no matches
+			let time_extractor: TimeExtractor = Box::new(|line: &str| {
+				let i: i64 = line.parse().ok()?;
+				if i % 7 != 0 {
+					Some(i * 37 % 400)
+				} else {
+					None
+				}
+			});
+			geo_index.times.clear();
+			geo_index.set_times(&table, Some(&time_extractor), Some("other"));
+			assert_eq!(find(&geo_index), expected);
+			assert_eq!(geo_index.time_property(), Some("other"));
+			assert_eq!(geo_index.time(0), Some(TimeRange::new(1, 399)));
+			geo_index.set_times(&table, None, None);
+			assert!(find(&geo_index).is_empty());
+			assert!(geo_index.times.is_empty());
+			assert_eq!(geo_index.time_property(), None);
+		}
+		Ok(())
+	}
 }
@@ -1,14 +1,32 @@
+mod attributes;
 mod bbox;
+mod builder;
+mod crs;
 mod database;
 mod file;
+mod filter;
+mod formats;
+mod ids;
 mod index;
 mod node;
+mod progress;
+mod query;
 mod table;
+mod time;
+mod writer;
 
+use attributes::GeoAttributeIndex;
 pub use bbox::GeoBBox;
-pub use database::GeoDB;
+pub use builder::GeoDBBuilder;
+pub use database::{GeoDB, GeoDBInfo, IteratorResult};
 use file::GeoFile;
 pub use file::GeoFileOptions;
+pub use filter::LineFilter;
+use ids::GeoIdIndex;
 use index::GeoIndex;
-use node::GeoNode;
+use node::{GeoEntry, GeoNode};
+pub use progress::{PrintProgress, Progress, ProgressEvent};
+pub use query::{BinaryPage, Lines, Query};
 use table::GeoTable;
+pub use time::{format_time, parse_time, TimeRange};
+pub use writer::FeatureCollectionWriter;
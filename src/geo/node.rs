@@ -1,4 +1,4 @@
-use super::GeoBBox;
+use super::{GeoBBox, TimeRange};
 use serde::{Deserialize, Serialize};
 
 /// The GeoNode struct represents a node in a tree structure that is used for spatial indexing.
@@ -66,7 +66,62 @@ impl GeoNode {
 	}
 }
 
+/// The GeoEntry struct represents a leaf to be indexed, with data that only some leaves have.
+/// The index keeps this data beside its nodes, so that it doesn't enlarge every node.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GeoEntry {
+	/// The leaf node, referencing a range in the file.
+	pub leaf: GeoNode,
+	/// Features crossing the antimeridian are indexed as two leaves, one on each side.
+	/// The western leaf stores the bounding box of the eastern one here, so that queries overlapping both
+	/// return the feature only once: the western leaf is skipped whenever the eastern one matches as well.
+	pub twin: Option<GeoBBox>,
+	/// The time of the feature, if the index has a time property and the feature has a valid time.
+	pub time: Option<TimeRange>,
+}
+
+impl GeoEntry {
+	/// Creates an entry of a leaf without a twin and without a time.
+	pub fn new(leaf: GeoNode) -> Self {
+		Self {
+			leaf,
+			twin: None,
+			time: None,
+		}
+	}
+
+	/// Creates one or two entries for a feature. A bounding box crossing the antimeridian is split
+	/// into an eastern leaf and a western twin leaf, both referencing the same range in the file.
+	///
+	/// # Arguments
+	///
+	/// * `bbox` - A GeoBBox instance, possibly wrapped around the antimeridian.
+	/// * `start` - The offset in the file for the leaf nodes.
+	/// * `length` - The length in the file for the leaf nodes.
+	///
+	/// # Returns
+	///
+	/// A Vec of one or two GeoEntry instances.
+	pub fn new_leaves(bbox: GeoBBox, start: usize, length: usize) -> Vec<Self> {
+		if !bbox.is_wrapped() {
+			return vec![GeoEntry::new(GeoNode::new_leaf(bbox, start, length))];
+		}
+		let mut parts = bbox.split_antimeridian();
+		let west = parts.pop().unwrap();
+		let east = parts.pop().unwrap();
+		vec![
+			GeoEntry::new(GeoNode::new_leaf(east.clone(), start, length)),
+			GeoEntry {
+				leaf: GeoNode::new_leaf(west, start, length),
+				twin: Some(east),
+				time: None,
+			},
+		]
+	}
+}
+
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
 mod tests {
 	use super::*;
 
@@ -81,6 +136,23 @@ mod tests {
 		assert_eq!(leaf.next, 0);
 	}
 
+	#[test]
+	fn test_new_leaves() {
+		let bbox = GeoBBox::new(1.0, 2.0, 3.0, 4.0);
+		assert_eq!(
+			GeoEntry::new_leaves(bbox.clone(), 10, 20),
+			vec![GeoEntry::new(GeoNode::new_leaf(bbox, 10, 20))]
+		);
+
+		let entries = GeoEntry::new_leaves(GeoBBox::new(170.0, -170.0, 3.0, 4.0), 10, 20);
+		let east = GeoBBox::new(170.0, 180.0, 3.0, 4.0);
+		assert_eq!(entries.len(), 2);
+		assert_eq!(entries[0], GeoEntry::new(GeoNode::new_leaf(east.clone(), 10, 20)));
+		assert_eq!(entries[1].leaf.bbox, GeoBBox::new(-180.0, -170.0, 3.0, 4.0));
+		assert_eq!((entries[1].leaf.value1, entries[1].leaf.value2), (10, 20));
+		assert_eq!(entries[1].twin, Some(east));
+	}
+
 	#[test]
 	fn test_new_node() {
 		let bbox = GeoBBox::new(1.0, 2.0, 3.0, 4.0);
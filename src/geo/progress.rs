@@ -0,0 +1,102 @@
+use std::time::Instant;
+
+/// Progress of a phase of the index creation: "entries" parses the lines, "table" writes the .dat file
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProgressEvent {
+	pub phase: &'static str,
+	pub rows: usize,
+	pub bytes: usize,
+	pub percent: f64,
+	pub rows_per_second: f64,
+	pub bytes_per_second: f64,
+}
+
+/// Receives progress events. Events are always reported on the thread that creates the index.
+pub trait Progress {
+	fn report(&self, event: &ProgressEvent);
+	/// Receives problems that didn't prevent creating the index, e.g. undecoded trailing bytes of a file
+	fn warning(&self, _message: &str) {}
+}
+
+impl<F: Fn(&ProgressEvent)> Progress for F {
+	fn report(&self, event: &ProgressEvent) {
+		self(event)
+	}
+}
+
+/// Print progress events to stdout
+pub struct PrintProgress;
+
+impl Progress for PrintProgress {
+	fn report(&self, event: &ProgressEvent) {
+		println!(
+			"{}: {}, {:.1}%, {:.0}/s, {:.1}MB/s",
+			event.phase,
+			event.rows,
+			event.percent,
+			event.rows_per_second,
+			event.bytes_per_second / 1048576.
+		)
+	}
+	fn warning(&self, message: &str) {
+		eprintln!("warning: {}", message)
+	}
+}
+
+// Creates the events of one phase, measuring the rates since its start
+pub struct ProgressTracker<'a> {
+	progress: Option<&'a dyn Progress>,
+	phase: &'static str,
+	start: Instant,
+}
+
+impl<'a> ProgressTracker<'a> {
+	pub fn new(progress: Option<&'a dyn Progress>, phase: &'static str) -> Self {
+		ProgressTracker {
+			progress,
+			phase,
+			start: Instant::now(),
+		}
+	}
+
+	pub fn report(&self, rows: usize, bytes: usize, percent: f64) {
+		if let Some(progress) = self.progress {
+			let seconds = self.start.elapsed().as_secs_f64().max(1e-9);
+			progress.report(&ProgressEvent {
+				phase: self.phase,
+				rows,
+				bytes,
+				percent,
+				rows_per_second: rows as f64 / seconds,
+				bytes_per_second: bytes as f64 / seconds,
+			});
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::cell::RefCell;
+
+	#[test]
+	fn test_tracker() {
+		let events: RefCell<Vec<ProgressEvent>> = RefCell::new(Vec::new());
+		let progress = |event: &ProgressEvent| events.borrow_mut().push(event.clone());
+		let tracker = ProgressTracker::new(Some(&progress), "entries");
+		tracker.report(10, 1000, 50.0);
+		tracker.report(20, 2000, 100.0);
+
+		let events = events.into_inner();
+		assert_eq!(events.len(), 2);
+		assert_eq!(events[1].phase, "entries");
+		assert_eq!((events[1].rows, events[1].bytes, events[1].percent), (20, 2000, 100.0));
+		assert!(events[1].rows_per_second > 0.0);
+		assert!(events[1].bytes_per_second > events[1].rows_per_second);
+	}
+
+	#[test]
+	fn test_without_progress() {
+		ProgressTracker::new(None, "table").report(1, 1, 1.0);
+	}
+}
@@ -0,0 +1,314 @@
+use super::{filter::LineFilter, FeatureCollectionWriter, GeoBBox, GeoDB, IteratorResult, TimeRange};
+use geojson::{Feature, JsonValue};
+use std::{
+	borrow::Cow,
+	error::Error,
+	io::{Error as IoError, ErrorKind, Write},
+	result::Result,
+};
+
+// Number of lines fetched from the index at once
+const PAGE_SIZE: usize = 1000;
+
+/// A query of the features in a bbox, optionally restricted by a time range and a filter.
+/// Created by [`GeoDB::query`].
+///
+/// ```no_run
+/// use geojson_db::{GeoBBox, GeoDB};
+///
+/// let geo_db = GeoDB::builder("features.geojsonl.gz").open()?;
+/// for feature in geo_db.query(&GeoBBox::from_f64(7., 8., 50., 51.)).filter("klasse = 'Wald'")?.features() {
+///     println!("{:?}", feature?.properties);
+/// }
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub struct Query<'a> {
+	geo_db: &'a GeoDB,
+	bbox: GeoBBox,
+	time: Option<TimeRange>,
+	filter: Option<Cow<'a, LineFilter>>,
+}
+
+/// A page of found lines in a single buffer, see [`Query::binary_page`]
+#[derive(Debug, PartialEq)]
+pub struct BinaryPage {
+	/// The lines, each followed by a newline
+	pub data: Vec<u8>,
+	/// The start of every line in `data`, followed by the end of `data`
+	pub offsets: Vec<u32>,
+	/// The cursor of the next page, or 0 if there are no more lines
+	pub next_index: usize,
+}
+
+/// Iterator over the lines found by a [`Query`]
+pub struct Lines<'a> {
+	query: Query<'a>,
+	page: std::vec::IntoIter<&'a [u8]>,
+	next_index: usize,
+	done: bool,
+}
+
+impl<'a> Query<'a> {
+	pub(crate) fn new(geo_db: &'a GeoDB, bbox: &GeoBBox) -> Self {
+		Query {
+			geo_db,
+			bbox: bbox.clone(),
+			time: None,
+			filter: None,
+		}
+	}
+	/// Only find features with a time in this range, see the time property of [`GeoDBBuilder`](super::GeoDBBuilder)
+	pub fn time(mut self, time: TimeRange) -> Self {
+		self.time = Some(time);
+		self
+	}
+	/// Only find features matching a filter expression, e.g. `land = 'BW' and einwohner >= 1000`
+	pub fn filter(mut self, expression: &str) -> Result<Self, Box<dyn Error>> {
+		self.filter = Some(Cow::Owned(self.geo_db.get_filter(expression)?));
+		Ok(self)
+	}
+	/// Only find features matching a filter of [`GeoDB::get_filter`], e.g. to parse it once for several queries
+	pub fn line_filter(mut self, filter: &'a LineFilter) -> Self {
+		self.filter = Some(Cow::Borrowed(filter));
+		self
+	}
+	/// The found lines as they are stored in the file
+	pub fn lines(self) -> Lines<'a> {
+		Lines {
+			query: self,
+			page: Vec::new().into_iter(),
+			next_index: 0,
+			done: false,
+		}
+	}
+	/// Up to `max_count` found lines, starting at a cursor of 0 or one returned by an earlier page.
+	/// Also returns the cursor of the next page, or 0 if there are no more lines.
+	pub fn page(&self, start_index: usize, max_count: usize) -> IteratorResult<'a> {
+		let mut lines: Vec<&'a [u8]> = Vec::new();
+		let mut next_index = start_index;
+		if max_count == 0 {
+			return (lines, next_index);
+		}
+		loop {
+			// filters and deletions can leave fewer lines than the index found, so ask again for the rest
+			let (page, index) = self.geo_db.query_bbox(
+				&self.bbox,
+				self.time.as_ref(),
+				next_index,
+				max_count - lines.len(),
+				self.filter.as_deref(),
+			);
+			lines.extend(page);
+			next_index = index;
+			if next_index == 0 || lines.len() >= max_count {
+				return (lines, next_index);
+			}
+		}
+	}
+	/// Like [`Query::page`], but with the lines packed into one buffer, e.g. to hand them over to Node.js at once.
+	/// Fails if the page exceeds the 4 GB of the offsets.
+	pub fn binary_page(&self, start_index: usize, max_count: usize) -> Result<BinaryPage, Box<dyn Error>> {
+		let (lines, next_index) = self.page(start_index, max_count);
+		let size: usize = lines.iter().map(|line| line.len() + 1).sum();
+		if size > u32::MAX as usize {
+			return Err(Box::new(IoError::new(
+				ErrorKind::InvalidInput,
+				"The lines of a page exceed 4 GB, use a smaller page size",
+			)));
+		}
+		let mut data: Vec<u8> = Vec::with_capacity(size);
+		let mut offsets: Vec<u32> = Vec::with_capacity(lines.len() + 1);
+		for line in lines {
+			offsets.push(data.len() as u32);
+			data.extend_from_slice(line);
+			data.push(b'\n');
+		}
+		offsets.push(data.len() as u32);
+		Ok(BinaryPage {
+			data,
+			offsets,
+			next_index,
+		})
+	}
+	/// Like [`Query::page`], but with the features parsed into JSON values, e.g. to build objects in Node.js.
+	/// CSV/TSV lines are converted like in [`GeoDB::feature_json`]. With a list of properties, only these
+	/// properties of every feature are kept.
+	pub fn json_page(
+		&self, start_index: usize, max_count: usize, properties: Option<&[String]>,
+	) -> Result<(Vec<JsonValue>, usize), Box<dyn Error>> {
+		let (lines, next_index) = self.page(start_index, max_count);
+		let features = lines
+			.into_iter()
+			.map(|line| self.geo_db.feature_json(line, properties))
+			.collect::<Result<_, _>>()?;
+		Ok((features, next_index))
+	}
+	/// The found features, parsed from GeoJSON lines or built from CSV/TSV lines
+	pub fn features(self) -> impl Iterator<Item = Result<Feature, Box<dyn Error>>> + 'a {
+		let geo_db = self.geo_db;
+		self.lines().map(move |line| geo_db.parse_feature(line))
+	}
+	/// Write the found features as a GeoJSON FeatureCollection and return their number, see [`FeatureCollectionWriter`]
+	pub fn write_feature_collection(self, output: &mut dyn Write) -> Result<usize, Box<dyn Error>> {
+		let mut writer = FeatureCollectionWriter::new(self.geo_db, output)?;
+		for line in self.lines() {
+			writer.write_line(line)?;
+		}
+		let count = writer.count();
+		writer.finish()?;
+		Ok(count)
+	}
+}
+
+impl<'a> Iterator for Lines<'a> {
+	type Item = &'a [u8];
+
+	fn next(&mut self) -> Option<Self::Item> {
+		loop {
+			if let Some(line) = self.page.next() {
+				return Some(line);
+			}
+			if self.done {
+				return None;
+			}
+			let query = &self.query;
+			let (lines, next_index) = query.geo_db.query_bbox(
+				&query.bbox,
+				query.time.as_ref(),
+				self.next_index,
+				PAGE_SIZE,
+				query.filter.as_deref(),
+			);
+			self.page = lines.into_iter();
+			self.next_index = next_index;
+			self.done = next_index == 0;
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use assert_fs::TempDir;
+	use serde_json::{json, Value};
+	use std::fs::write;
+
+	#[test]
+	fn test_binary_page() -> Result<(), Box<dyn Error>> {
+		let dir = TempDir::new()?;
+		let filename = dir.path().join("points.csv");
+		write(&filename, "name,x,y\na,1,1\nbb,2,2\nccc,3,3\n")?;
+		let geo_db = GeoDB::builder(&filename).skip_lines(1).col_x(1).col_y(2).open()?;
+		let query = geo_db.query(&GeoBBox::new(0., 10., 0., 10.));
+
+		// pages of two lines and one line, each line followed by a newline
+		let mut lines: Vec<String> = Vec::new();
+		let mut next_index = 0;
+		let mut sizes: Vec<usize> = Vec::new();
+		loop {
+			let page = query.binary_page(next_index, 2)?;
+			assert_eq!(page.offsets.first(), Some(&0));
+			assert_eq!(page.offsets.last(), Some(&(page.data.len() as u32)));
+			for range in page.offsets.windows(2) {
+				let line = &page.data[range[0] as usize..range[1] as usize];
+				assert_eq!(line.last(), Some(&b'\n'));
+				lines.push(String::from_utf8(line[..line.len() - 1].to_vec())?);
+			}
+			sizes.push(page.offsets.len() - 1);
+			next_index = page.next_index;
+			if next_index == 0 {
+				break;
+			}
+		}
+		assert_eq!(sizes, vec![2, 1]);
+		lines.sort();
+		assert_eq!(lines, vec!["a,1,1", "bb,2,2", "ccc,3,3"]);
+
+		// the same pages as lines
+		let (page_lines, page_next_index) = query.page(0, 2);
+		let page = query.binary_page(0, 2)?;
+		assert_eq!(page.data, [page_lines[0], b"\n", page_lines[1], b"\n"].concat());
+		assert_eq!(page.next_index, page_next_index);
+
+		let page = geo_db.query(&GeoBBox::new(20., 30., 20., 30.)).binary_page(0, 2)?;
+		assert_eq!(
+			geo_db.query(&GeoBBox::new(20., 30., 20., 30.)).json_page(0, 2, None)?,
+			(Vec::new(), 0)
+		);
+		assert_eq!(
+			page,
+			BinaryPage {
+				data: Vec::new(),
+				offsets: vec![0],
+				next_index: 0,
+			}
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn test_json_page() -> Result<(), Box<dyn Error>> {
+		let dir = TempDir::new()?;
+		let filename = dir.path().join("points.csv");
+		write(&filename, "name,x,y,land\na,1,1,BW\nb,2,2,BY\nc,3,3,BE\n")?;
+		let geo_db = GeoDB::builder(&filename).skip_lines(1).col_x(1).col_y(2).open()?;
+		let query = geo_db.query(&GeoBBox::new(0., 10., 0., 10.));
+
+		// pages of features with selected properties, until the cursor is 0
+		let properties = vec![String::from("name")];
+		let mut features: Vec<Value> = Vec::new();
+		let mut next_index = 0;
+		loop {
+			let (page, index) = query.json_page(next_index, 2, Some(&properties))?;
+			assert!(!page.is_empty() && page.len() <= 2);
+			features.extend(page);
+			next_index = index;
+			if next_index == 0 {
+				break;
+			}
+		}
+		let mut names: Vec<&Value> = features.iter().map(|feature| &feature["properties"]).collect();
+		names.sort_by_key(|properties| properties.to_string());
+		assert_eq!(
+			names,
+			vec![
+				&json!({ "name": "a" }),
+				&json!({ "name": "b" }),
+				&json!({ "name": "c" })
+			]
+		);
+
+		// without properties, features are complete
+		let (features, _) = query.json_page(0, 3, None)?;
+		assert_eq!(features.len(), 3);
+		assert!(features
+			.iter()
+			.all(|feature| feature["properties"].as_object().unwrap().len() == 2));
+
+		// GeoJSON members other than the properties are kept
+		let filename = dir.path().join("places.geojsonl");
+		let lines = [
+			r#"{"type":"Feature","id":"\u0031","properties":{"name":"Mainz","ad\"resse":{"ort":"Mainz"},"land":"RP"},"geometry":{"type":"Point","coordinates":[8.27,50.0]}}"#,
+			r#"{"type":"Feature","properties":null,"geometry":{"type":"Point","coordinates":[8,50]}}"#,
+		];
+		write(&filename, lines.join("\n") + "\n")?;
+		let geo_db = GeoDB::builder(&filename).open()?;
+		let query = geo_db.query(&GeoBBox::new(0., 10., 40., 60.));
+		let properties = vec![String::from("name"), String::from("ad\"resse")];
+		let (mut features, _) = query.json_page(0, 10, Some(&properties))?;
+		features.sort_by_key(|feature| feature["id"].is_null());
+		assert_eq!(
+			features,
+			vec![
+				json!({
+					"type": "Feature",
+					"id": "1",
+					"properties": { "name": "Mainz", "ad\"resse": { "ort": "Mainz" } },
+					"geometry": { "type": "Point", "coordinates": [8.27, 50.0] },
+				}),
+				serde_json::from_str(lines[1])?,
+			]
+		);
+		Ok(())
+	}
+}
@@ -10,6 +10,10 @@ impl GeoTable {
 		Ok(Self { data: read(filename)? })
 	}
 
+	pub fn get_data(&self) -> &[u8] {
+		&self.data
+	}
+
 	pub fn read_ranges(&self, leaves: Vec<&GeoNode>) -> Vec<&[u8]> {
 		leaves
 			.iter()
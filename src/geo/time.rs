@@ -0,0 +1,212 @@
+use serde::{Deserialize, Serialize};
+
+/// A closed range of times in milliseconds since 1970-01-01T00:00:00Z
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct TimeRange {
+	pub min: i64,
+	pub max: i64,
+}
+
+impl TimeRange {
+	pub fn new(min: i64, max: i64) -> Self {
+		TimeRange { min, max }
+	}
+	pub fn instant(time: i64) -> Self {
+		TimeRange { min: time, max: time }
+	}
+	pub fn include(&mut self, time: &TimeRange) {
+		self.min = self.min.min(time.min);
+		self.max = self.max.max(time.max);
+	}
+	pub fn overlaps(&self, time: &TimeRange) -> bool {
+		self.min <= time.max && self.max >= time.min
+	}
+}
+
+/// Parse an ISO 8601 date or date time, e.g. "2024-05-17", "2024-05-17T13:45:00Z", "2024-05-17 13:45:00.250+02:00",
+/// into milliseconds since 1970. Times without a time zone are UTC.
+pub fn parse_time(text: &str) -> Option<i64> {
+	let text = text.trim();
+	let bytes = text.as_bytes();
+	let number = |range: std::ops::Range<usize>| -> Option<i64> {
+		let digits = text.get(range)?;
+		if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+			return None;
+		}
+		digits.parse().ok()
+	};
+
+	let year = number(0..4)?;
+	if bytes.get(4) != Some(&b'-') || bytes.get(7) != Some(&b'-') {
+		return None;
+	}
+	let month = number(5..7)?;
+	let day = number(8..10)?;
+	if !(1..=12).contains(&month) || day < 1 || day > days_in_month(year, month) {
+		return None;
+	}
+	let mut millis = days_from_civil(year, month, day) * 86_400_000;
+	if bytes.len() == 10 {
+		return Some(millis);
+	}
+
+	if !matches!(bytes[10], b'T' | b't' | b' ') || bytes.get(13) != Some(&b':') {
+		return None;
+	}
+	let hour = number(11..13)?;
+	let minute = number(14..16)?;
+	let mut pos = 16;
+	let mut second = 0;
+	if bytes.get(pos) == Some(&b':') {
+		second = number(17..19)?;
+		pos = 19;
+	}
+	if hour > 23 || minute > 59 || second > 60 {
+		return None;
+	}
+	millis += ((hour * 60 + minute) * 60 + second) * 1000;
+
+	// fractions of seconds, rounded down to milliseconds
+	if matches!(bytes.get(pos), Some(b'.' | b',')) && pos == 19 {
+		let start = pos + 1;
+		pos = start;
+		while pos < bytes.len() && bytes[pos].is_ascii_digit() {
+			pos += 1;
+		}
+		let fraction = text.get(start..pos.min(start + 3))?;
+		if fraction.is_empty() {
+			return None;
+		}
+		millis += fraction.parse::<i64>().ok()? * 10_i64.pow(3 - fraction.len() as u32);
+	}
+
+	match bytes.get(pos) {
+		None => Some(millis),
+		Some(b'Z' | b'z') if pos + 1 == bytes.len() => Some(millis),
+		Some(&sign @ (b'+' | b'-')) => {
+			let offset_hour = number(pos + 1..pos + 3)?;
+			let offset_minute = match (bytes.len() - pos, bytes.get(pos + 3)) {
+				(3, _) => 0,
+				(5, _) => number(pos + 3..pos + 5)?,
+				(6, Some(b':')) => number(pos + 4..pos + 6)?,
+				_ => return None,
+			};
+			let offset = (offset_hour * 60 + offset_minute) * 60_000;
+			Some(if sign == b'+' { millis - offset } else { millis + offset })
+		}
+		_ => None,
+	}
+}
+
+/// Format milliseconds since 1970 as an ISO 8601 date time in UTC, e.g. "2024-05-17T13:45:00.250Z"
+pub fn format_time(time: i64) -> String {
+	let days = time.div_euclid(86_400_000);
+	let millis = time.rem_euclid(86_400_000);
+	let (year, month, day) = civil_from_days(days);
+	format!(
+		"{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+		year,
+		month,
+		day,
+		millis / 3_600_000,
+		millis / 60_000 % 60,
+		millis / 1000 % 60,
+		millis % 1000
+	)
+}
+
+fn is_leap_year(year: i64) -> bool {
+	year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+	match month {
+		2 if is_leap_year(year) => 29,
+		2 => 28,
+		4 | 6 | 9 | 11 => 30,
+		_ => 31,
+	}
+}
+
+// Days since 1970-01-01 of a date in the proleptic Gregorian calendar
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+	let year = if month <= 2 { year - 1 } else { year };
+	let era = year.div_euclid(400);
+	let year_of_era = year - era * 400;
+	let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+	let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+	era * 146_097 + day_of_era - 719_468
+}
+
+// Date in the proleptic Gregorian calendar of days since 1970-01-01, the inverse of days_from_civil
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+	let days = days + 719_468;
+	let era = days.div_euclid(146_097);
+	let day_of_era = days - era * 146_097;
+	let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+	let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+	let month_index = (5 * day_of_year + 2) / 153;
+	let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+	let month = if month_index < 10 {
+		month_index + 3
+	} else {
+		month_index - 9
+	};
+	let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+	(year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_parse_time() {
+		assert_eq!(parse_time("1970-01-01"), Some(0));
+		assert_eq!(parse_time("2024-05-17"), Some(1_715_904_000_000));
+		assert_eq!(parse_time("2024-05-17T13:45:00Z"), Some(1_715_953_500_000));
+		assert_eq!(parse_time("2024-05-17 13:45"), Some(1_715_953_500_000));
+		assert_eq!(parse_time("2024-05-17T15:45:00.25+02:00"), Some(1_715_953_500_250));
+		assert_eq!(parse_time("2024-05-17T13:45:00.123456-0130"), Some(1_715_958_900_123));
+		assert_eq!(parse_time("2000-02-29T00:00:00+01"), Some(951_778_800_000));
+		assert_eq!(parse_time("1969-12-31T23:59:59Z"), Some(-1000));
+		assert_eq!(parse_time(" 1600-03-01 "), Some(-11_670_912_000_000));
+
+		for text in [
+			"",
+			"2024",
+			"2024-5-17",
+			"2023-02-29",
+			"2024-13-01",
+			"2024-05-17T",
+			"2024-05-17T25:00",
+			"2024-05-17T13:45Zx",
+			"2024-05-17T13:45+2",
+			"2024-05-17X13:45",
+			"yesterday",
+		] {
+			assert_eq!(parse_time(text), None, "{}", text);
+		}
+	}
+
+	#[test]
+	fn test_format_time() {
+		assert_eq!(format_time(0), "1970-01-01T00:00:00.000Z");
+		assert_eq!(format_time(1_715_953_500_250), "2024-05-17T13:45:00.250Z");
+		assert_eq!(format_time(-1000), "1969-12-31T23:59:59.000Z");
+		assert_eq!(format_time(951_782_400_000), "2000-02-29T00:00:00.000Z");
+		for time in [-11_670_912_000_000, 0, 86_399_999, 1_715_953_500_250, 4_102_444_800_001] {
+			assert_eq!(parse_time(&format_time(time)), Some(time));
+		}
+	}
+
+	#[test]
+	fn test_time_range() {
+		let mut range = TimeRange::instant(10);
+		range.include(&TimeRange::new(-5, 3));
+		assert_eq!(range, TimeRange::new(-5, 10));
+		assert!(range.overlaps(&TimeRange::new(10, 20)));
+		assert!(range.overlaps(&TimeRange::new(i64::MIN, -5)));
+		assert!(!range.overlaps(&TimeRange::new(11, i64::MAX)));
+	}
+}
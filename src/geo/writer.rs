@@ -0,0 +1,105 @@
+use super::GeoDB;
+use serde_json::{Map, Value};
+use std::{error::Error, io::Write, result::Result};
+
+/// Writes a GeoJSON FeatureCollection feature by feature, without holding the features in memory.
+/// GeoJSON lines are written as they are, CSV/TSV lines are converted to point features.
+///
+/// ```no_run
+/// use geojson_db::{FeatureCollectionWriter, GeoBBox, GeoDB};
+///
+/// let geo_db = GeoDB::builder("features.csv.gz").skip_lines(1).open()?;
+/// let mut writer = FeatureCollectionWriter::new(&geo_db, std::io::stdout().lock())?;
+/// for line in geo_db.query(&GeoBBox::from_f64(7., 8., 50., 51.)).lines() {
+///     writer.write_line(line)?;
+/// }
+/// writer.finish()?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub struct FeatureCollectionWriter<'a, W: Write> {
+	geo_db: &'a GeoDB,
+	output: W,
+	count: usize,
+}
+
+impl<'a, W: Write> FeatureCollectionWriter<'a, W> {
+	/// Start a FeatureCollection of lines of this file
+	pub fn new(geo_db: &'a GeoDB, mut output: W) -> Result<Self, Box<dyn Error>> {
+		output.write_all(b"{\"type\":\"FeatureCollection\",\"features\":[")?;
+		Ok(FeatureCollectionWriter {
+			geo_db,
+			output,
+			count: 0,
+		})
+	}
+	/// Write a line returned by a query or a lookup as the next feature
+	pub fn write_line(&mut self, line: &[u8]) -> Result<(), Box<dyn Error>> {
+		if self.count > 0 {
+			self.output.write_all(b",")?;
+		}
+		self.geo_db.write_feature(line, &mut self.output)?;
+		self.count += 1;
+		Ok(())
+	}
+	/// Number of features written so far
+	pub fn count(&self) -> usize {
+		self.count
+	}
+	/// End the FeatureCollection and return the output
+	pub fn finish(self) -> Result<W, Box<dyn Error>> {
+		self.finish_with_members(&Map::new())
+	}
+	/// End the FeatureCollection with more members after the features, e.g. "numberReturned"
+	pub fn finish_with_members(mut self, members: &Map<String, Value>) -> Result<W, Box<dyn Error>> {
+		self.output.write_all(b"]")?;
+		for (key, value) in members {
+			write!(self.output, ",{}:{}", Value::from(key.as_str()), value)?;
+		}
+		self.output.write_all(b"}")?;
+		self.output.flush()?;
+		Ok(self.output)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::GeoBBox;
+	use assert_fs::TempDir;
+	use serde_json::json;
+	use std::fs::write;
+
+	#[test]
+	fn test_write_feature_collection() -> Result<(), Box<dyn Error>> {
+		let dir = TempDir::new()?;
+		let filename = dir.path().join("points.csv");
+		write(&filename, "name,x,y\nMainz,8.27,50\n")?;
+		let geo_db = GeoDB::builder(&filename).skip_lines(1).col_x(1).col_y(2).open()?;
+		let all = GeoBBox::new(-180., 180., -90., 90.);
+
+		let mut writer = FeatureCollectionWriter::new(&geo_db, Vec::new())?;
+		for line in geo_db.query(&all).lines() {
+			writer.write_line(line)?;
+		}
+		assert_eq!(writer.count(), 1);
+		let mut members = Map::new();
+		members.insert("numberReturned".to_string(), json!(1));
+		let value: Value = serde_json::from_slice(&writer.finish_with_members(&members)?)?;
+		assert_eq!(
+			value,
+			json!({
+				"type": "FeatureCollection",
+				"features": [{
+					"type": "Feature",
+					"geometry": { "type": "Point", "coordinates": [8.27, 50.0] },
+					"properties": { "name": "Mainz" },
+				}],
+				"numberReturned": 1,
+			})
+		);
+
+		let output = FeatureCollectionWriter::new(&geo_db, Vec::new())?.finish()?;
+		assert_eq!(output, b"{\"type\":\"FeatureCollection\",\"features\":[]}");
+		Ok(())
+	}
+}
@@ -0,0 +1,417 @@
+use clap::{Args, Parser, Subcommand};
+#[cfg(feature = "server")]
+use geojson_db::GeoServer;
+use geojson_db::{format_time, parse_time, GeoBBox, GeoDB, GeoFileOptions, PrintProgress, Progress, TimeRange};
+use std::{
+	error::Error,
+	io::{stdout, BufWriter, ErrorKind, Write},
+	path::PathBuf,
+	process::exit,
+	result::Result,
+};
+
+#[derive(Parser, Debug)]
+#[command(
+	name = "geojson_db",
+	version,
+	about = "Build, query and check spatial indexes of GeoJSON and CSV/TSV files"
+)]
+struct Cli {
+	#[command(subcommand)]
+	command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+	/// Build the index of a file, next to the file
+	Build {
+		#[command(flatten)]
+		file: FileArgs,
+		/// Replace an existing index, including appended segments and deleted ids
+		#[arg(long)]
+		force: bool,
+		/// Don't print the progress
+		#[arg(long)]
+		quiet: bool,
+	},
+	/// Print the lines of the features in a bbox, building the index if needed
+	Query {
+		#[command(flatten)]
+		file: FileArgs,
+		/// x_min,y_min,x_max,y_max, everything by default
+		#[arg(long, value_parser = parse_bbox, allow_hyphen_values = true)]
+		bbox: Option<GeoBBox>,
+		/// Filter expression over properties or columns, e.g. "land = 'BW' and einwohner >= 1000"
+		#[arg(long = "where")]
+		filter: Option<String>,
+		/// Only features with a time at or after this ISO 8601 time or milliseconds since 1970
+		#[arg(long, value_parser = parse_time_arg, allow_hyphen_values = true)]
+		from: Option<i64>,
+		/// Only features with a time at or before this ISO 8601 time or milliseconds since 1970
+		#[arg(long, value_parser = parse_time_arg, allow_hyphen_values = true)]
+		to: Option<i64>,
+		/// Maximum number of lines
+		#[arg(long)]
+		limit: Option<usize>,
+	},
+	/// Print the format version, size, extent and time range of the index
+	Info {
+		#[command(flatten)]
+		file: FileArgs,
+	},
+	/// Check the trees and lines of the index, exiting with status 1 if there are problems
+	Verify {
+		#[command(flatten)]
+		file: FileArgs,
+	},
+	/// Serve files as collections named like the files up to their first dot, with the same index options
+	#[cfg(feature = "server")]
+	Serve {
+		#[arg(required = true)]
+		files: Vec<PathBuf>,
+		#[command(flatten)]
+		index: IndexArgs,
+		/// Address to listen on
+		#[arg(long, default_value = "127.0.0.1:8080")]
+		address: String,
+		/// Number of threads answering requests
+		#[arg(long, default_value_t = 4)]
+		threads: usize,
+		/// Largest number of features in a vector tile
+		#[arg(long, default_value_t = 100_000)]
+		max_tile_features: usize,
+		/// URL at the start of all links, e.g. behind a reverse proxy. By default, links use the requested host
+		#[arg(long)]
+		base_url: Option<String>,
+	},
+}
+
+// A file and the options of its index
+#[derive(Args, Debug)]
+struct FileArgs {
+	/// GeoJSON lines, CSV/TSV, GeoPackage, GPX, KML or TopoJSON file, optionally compressed
+	file: PathBuf,
+	#[command(flatten)]
+	index: IndexArgs,
+}
+
+// The options of an index, as in the JavaScript constructor
+#[derive(Args, Debug)]
+struct IndexArgs {
+	/// Field separator of CSV/TSV files
+	#[arg(long)]
+	separator: Option<String>,
+	/// Column of x values in CSV/TSV files
+	#[arg(long)]
+	col_x: Option<usize>,
+	/// Column of y values in CSV/TSV files
+	#[arg(long)]
+	col_y: Option<usize>,
+	/// Number of lines to skip, e.g. a CSV header line
+	#[arg(long)]
+	skip_lines: Option<usize>,
+	/// GeoPackage layer or TopoJSON object
+	#[arg(long)]
+	layer: Option<String>,
+	/// CRS of the input coordinates, e.g. EPSG:25832
+	#[arg(long)]
+	source_crs: Option<String>,
+	/// CRS of the index and of bbox queries
+	#[arg(long)]
+	target_crs: Option<String>,
+	/// Also reproject the coordinates of the stored lines
+	#[arg(long)]
+	rewrite_geometries: bool,
+	/// binary, str or hilbert
+	#[arg(long)]
+	index_type: Option<String>,
+	/// Number of children per node of str and hilbert trees
+	#[arg(long)]
+	node_size: Option<usize>,
+	/// Column of feature ids in CSV/TSV files
+	#[arg(long)]
+	col_id: Option<usize>,
+	/// Property with the feature id in GeoJSON
+	#[arg(long)]
+	id_property: Option<String>,
+	/// Comma separated properties or columns with an attribute index
+	#[arg(long, value_delimiter = ',')]
+	index_properties: Vec<String>,
+	/// Property or column with the time of features
+	#[arg(long)]
+	time_property: Option<String>,
+}
+
+impl FileArgs {
+	fn open(&self, progress: Option<&dyn Progress>) -> Result<GeoDB, Box<dyn Error>> {
+		GeoDB::open(&self.file, self.index.options(), progress)
+	}
+	// Report a missing or outdated index, which info and verify don't create
+	fn check_index(&self, output: &mut dyn Write) -> Result<bool, Box<dyn Error>> {
+		let has_index = GeoDB::has_index(&self.file)?;
+		if !has_index {
+			writeln!(output, "no index of the current format version, create it with build")?;
+		}
+		Ok(has_index)
+	}
+}
+
+impl IndexArgs {
+	fn options(&self) -> GeoFileOptions {
+		let mut options = GeoFileOptions::empty();
+		options.separator = self.separator.clone();
+		options.col_x = self.col_x;
+		options.col_y = self.col_y;
+		options.skip_lines = self.skip_lines;
+		options.layer = self.layer.clone();
+		options.source_crs = self.source_crs.clone();
+		options.target_crs = self.target_crs.clone();
+		options.rewrite_geometries = Some(self.rewrite_geometries);
+		options.index_type = self.index_type.clone();
+		options.node_size = self.node_size;
+		options.col_id = self.col_id;
+		options.id_property = self.id_property.clone();
+		if !self.index_properties.is_empty() {
+			options.index_properties = Some(self.index_properties.clone());
+		}
+		options.time_property = self.time_property.clone();
+		options
+	}
+}
+
+fn parse_bbox(text: &str) -> Result<GeoBBox, String> {
+	let values: Vec<f64> = text
+		.split(',')
+		.map(|value| value.trim().parse::<f64>())
+		.collect::<Result<_, _>>()
+		.map_err(|error| format!("Invalid bbox {}: {}", text, error))?;
+	match values[..] {
+		[x_min, y_min, x_max, y_max] => Ok(GeoBBox::from_f64(x_min, x_max, y_min, y_max)),
+		_ => Err(format!("Invalid bbox {}: expected x_min,y_min,x_max,y_max", text)),
+	}
+}
+
+fn parse_time_arg(text: &str) -> Result<i64, String> {
+	text
+		.parse::<i64>()
+		.ok()
+		.or_else(|| parse_time(text))
+		.ok_or_else(|| format!("Invalid time: {}", text))
+}
+
+// Run a command, writing its output. Returns false if verify found problems.
+fn run(command: &Command, output: &mut dyn Write) -> Result<bool, Box<dyn Error>> {
+	match command {
+		Command::Build { file, force, quiet } => {
+			if *force {
+				GeoDB::remove_index(&file.file)?;
+			}
+			let progress: Option<&dyn Progress> = if *quiet { None } else { Some(&PrintProgress) };
+			let info = file.open(progress)?.info();
+			writeln!(
+				output,
+				"{} features, {} nodes, {} segments",
+				info.features, info.nodes, info.segments
+			)?;
+		}
+		Command::Query {
+			file,
+			bbox,
+			filter,
+			from,
+			to,
+			limit,
+		} => {
+			let geo_db = file.open(None)?;
+			let bbox = bbox
+				.clone()
+				.unwrap_or(GeoBBox::new(f32::MIN, f32::MAX, f32::MIN, f32::MAX));
+			let mut query = geo_db.query(&bbox);
+			if let Some(filter) = filter {
+				query = query.filter(filter)?;
+			}
+			if from.is_some() || to.is_some() {
+				query = query.time(TimeRange::new(from.unwrap_or(i64::MIN), to.unwrap_or(i64::MAX)));
+			}
+			for line in query.lines().take(limit.unwrap_or(usize::MAX)) {
+				output.write_all(line)?;
+				output.write_all(b"\n")?;
+			}
+		}
+		Command::Info { file } => {
+			if !file.check_index(output)? {
+				return Ok(false);
+			}
+			let info = file.open(None)?.info();
+			writeln!(output, "format version: {}", info.format_version)?;
+			writeln!(output, "segments: {}", info.segments)?;
+			writeln!(output, "nodes: {}", info.nodes)?;
+			writeln!(output, "features: {}", info.features)?;
+			writeln!(output, "deleted ids: {}", info.deleted_ids)?;
+			if let Some(extent) = info.extent {
+				let [x_min, y_min, x_max, y_max] = extent.to_array();
+				writeln!(output, "extent: {},{},{},{}", x_min, y_min, x_max, y_max)?;
+			}
+			if let Some(time) = info.time {
+				writeln!(output, "time: {} - {}", format_time(time.min), format_time(time.max))?;
+			}
+		}
+		Command::Verify { file } => {
+			if !file.check_index(output)? {
+				return Ok(false);
+			}
+			let geo_db = file.open(None)?;
+			let problems = geo_db.verify();
+			for problem in &problems {
+				writeln!(output, "{}", problem)?;
+			}
+			if !problems.is_empty() {
+				return Ok(false);
+			}
+			writeln!(output, "ok, {} features", geo_db.info().features)?;
+		}
+		#[cfg(feature = "server")]
+		Command::Serve {
+			files,
+			index,
+			address,
+			threads,
+			max_tile_features,
+			base_url,
+		} => {
+			let mut server = GeoServer::new();
+			server.set_max_tile_features(*max_tile_features);
+			if let Some(base_url) = base_url {
+				server.set_base_url(base_url);
+			}
+			for file in files {
+				let name = file
+					.file_name()
+					.and_then(|name| name.to_str())
+					.and_then(|name| name.split('.').next())
+					.unwrap_or_default();
+				server.add_collection(name, GeoDB::open(file, index.options(), Some(&PrintProgress))?)?;
+				writeln!(output, "collection {}: {}", name, file.to_string_lossy())?;
+			}
+			writeln!(output, "listening on http://{}", address)?;
+			output.flush()?;
+			server.serve(address, *threads)?;
+		}
+	}
+	Ok(true)
+}
+
+fn main() {
+	let cli = Cli::parse();
+	let mut output = BufWriter::new(stdout().lock());
+	let result = run(&cli.command, &mut output).and_then(|success| {
+		output.flush()?;
+		Ok(success)
+	});
+	match result {
+		Ok(true) => {}
+		Ok(false) => exit(1),
+		// e.g. piped into head
+		Err(error) if error.downcast_ref::<std::io::Error>().map(|e| e.kind()) == Some(ErrorKind::BrokenPipe) => {}
+		Err(error) => {
+			eprintln!("Error: {}", error);
+			exit(1);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use assert_fs::TempDir;
+	use std::fs::{remove_file, write};
+
+	fn run_args(args: &[&str]) -> Result<(bool, String), Box<dyn Error>> {
+		let cli = Cli::try_parse_from([&["geojson_db"], args].concat())?;
+		let mut output: Vec<u8> = Vec::new();
+		let success = run(&cli.command, &mut output)?;
+		Ok((success, String::from_utf8(output)?))
+	}
+
+	#[test]
+	fn test_commands() -> Result<(), Box<dyn Error>> {
+		let dir = TempDir::new()?;
+		let filename = dir.path().join("points.csv");
+		write(
+			&filename,
+			"name;x;y;time\na;1;1;2024-05-01\nb;2;2;2024-06-01\nc;-3;3;2024-07-01\n",
+		)?;
+		let file = filename.to_str().unwrap();
+		let options = ["--separator", ";", "--col-x", "1", "--col-y", "2", "--skip-lines", "1"];
+		let with_options = |args: &[&str]| -> Vec<String> {
+			let mut args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+			args.extend(options.iter().map(|arg| arg.to_string()));
+			args
+		};
+		let run_with_options = |args: &[&str]| -> Result<(bool, String), Box<dyn Error>> {
+			let args = with_options(args);
+			run_args(&args.iter().map(String::as_str).collect::<Vec<&str>>())
+		};
+
+		// info and verify don't create a missing index
+		for command in ["info", "verify"] {
+			let (success, output) = run_with_options(&[command, file])?;
+			assert!(!success);
+			assert_eq!(output, "no index of the current format version, create it with build\n");
+		}
+		assert!(!dir.path().join("points.csv.idx").exists());
+
+		let (success, output) = run_with_options(&["build", file, "--quiet", "--time-property", "time"])?;
+		assert!(success);
+		assert_eq!(output, "3 features, 5 nodes, 1 segments\n");
+		assert!(dir.path().join("points.csv.idx").exists());
+
+		// lines come in the order of the tree
+		let (_, output) = run_with_options(&["query", file, "--bbox", "-5,0,1.5,10"])?;
+		let mut lines: Vec<&str> = output.lines().collect();
+		lines.sort();
+		assert_eq!(lines, vec!["a;1;1;2024-05-01", "c;-3;3;2024-07-01"]);
+		let (_, output) = run_with_options(&["query", file, "--where", "name = 'b'"])?;
+		assert_eq!(output, "b;2;2;2024-06-01\n");
+		let (_, output) = run_with_options(&[
+			"query",
+			file,
+			"--time-property",
+			"time",
+			"--from",
+			"2024-05-15",
+			"--to",
+			"2024-06-30",
+		])?;
+		assert_eq!(output, "b;2;2;2024-06-01\n");
+		let (_, output) = run_with_options(&["query", file, "--limit", "2"])?;
+		assert_eq!(output.lines().count(), 2);
+		assert!(run_with_options(&["query", file, "--bbox", "1,2,3"]).is_err());
+		assert!(run_with_options(&["query", file, "--from", "soon"]).is_err());
+
+		let (_, output) = run_with_options(&["info", file, "--time-property", "time"])?;
+		assert_eq!(
+			output,
+			"format version: 4\nsegments: 1\nnodes: 5\nfeatures: 3\ndeleted ids: 0\nextent: -3,1,2,3\n\
+			time: 2024-05-01T00:00:00.000Z - 2024-07-01T00:00:00.000Z\n"
+		);
+
+		let (success, output) = run_with_options(&["verify", file])?;
+		assert!(success);
+		assert_eq!(output, "ok, 3 features\n");
+
+		// a corrupt table is reported
+		write(dir.path().join("points.csv.dat"), "a;1;1;2024-05-01")?;
+		let (success, output) = run_with_options(&["verify", file])?;
+		assert!(!success);
+		assert!(output.contains("outside of the table"), "{}", output);
+
+		// --force rebuilds it
+		run_with_options(&["build", file, "--quiet", "--force"])?;
+		let (success, _) = run_with_options(&["verify", file])?;
+		assert!(success);
+		remove_file(&filename)?;
+		assert!(run_with_options(&["build", file, "--quiet", "--force"]).is_err());
+		Ok(())
+	}
+}
@@ -0,0 +1,491 @@
+use crate::{
+	parse_time, GeoBBox, GeoDB, GeoFileOptions, LineFilter, PrintProgress, Progress, ProgressEvent, Query, TimeRange,
+};
+use geojson::JsonValue;
+use neon::{
+	context::Context,
+	handle::Handle,
+	prelude::{FunctionContext, ModuleContext, Object},
+	result::{JsResult, NeonResult},
+	types::{
+		buffer::TypedArray, Finalize, JsArray, JsArrayBuffer, JsBoolean, JsBox, JsBuffer, JsFunction, JsNumber, JsObject,
+		JsString, JsUndefined, JsValue,
+	},
+};
+use std::{cell::RefCell, collections::HashMap, path::PathBuf, str::from_utf8};
+
+type BoxedGeoDB = JsBox<RefCell<GeoDB>>;
+type BoxedFilter = JsBox<LineFilter>;
+
+impl Finalize for GeoDB {}
+impl Finalize for LineFilter {}
+
+impl GeoDB {
+	pub fn js_open(mut cx: FunctionContext) -> JsResult<BoxedGeoDB> {
+		let filename = PathBuf::from(cx.argument::<JsString>(0)?.value(&mut cx));
+		let options = cx.argument::<JsObject>(1).unwrap_or(cx.empty_object());
+
+		let get_usize = |name: &str, cx: &mut FunctionContext| -> Option<usize> {
+			options
+				.get_opt::<JsNumber, _, _>(cx, name)
+				.unwrap()
+				.map(|v| v.value(cx) as usize)
+		};
+
+		let opt = GeoFileOptions {
+			separator: options
+				.get_opt::<JsString, _, _>(&mut cx, "separator")?
+				.map(|v| v.value(&mut cx)),
+			col_x: get_usize("colX", &mut cx),
+			col_y: get_usize("colY", &mut cx),
+			skip_lines: get_usize("skipLines", &mut cx),
+			layer: options
+				.get_opt::<JsString, _, _>(&mut cx, "layer")?
+				.map(|v| v.value(&mut cx)),
+			source_crs: options
+				.get_opt::<JsString, _, _>(&mut cx, "sourceCrs")?
+				.map(|v| v.value(&mut cx)),
+			target_crs: options
+				.get_opt::<JsString, _, _>(&mut cx, "targetCrs")?
+				.map(|v| v.value(&mut cx)),
+			rewrite_geometries: options
+				.get_opt::<JsBoolean, _, _>(&mut cx, "rewriteGeometries")?
+				.map(|v| v.value(&mut cx)),
+			index_type: options
+				.get_opt::<JsString, _, _>(&mut cx, "indexType")?
+				.map(|v| v.value(&mut cx)),
+			node_size: get_usize("nodeSize", &mut cx),
+			col_id: get_usize("colId", &mut cx),
+			id_property: options
+				.get_opt::<JsString, _, _>(&mut cx, "idProperty")?
+				.map(|v| v.value(&mut cx)),
+			index_properties: match options.get_opt::<JsArray, _, _>(&mut cx, "indexProperties")? {
+				Some(array) => Some(
+					array
+						.to_vec(&mut cx)?
+						.into_iter()
+						.map(|v| Ok(v.downcast_or_throw::<JsString, _>(&mut cx)?.value(&mut cx)))
+						.collect::<NeonResult<_>>()?,
+				),
+				None => None,
+			},
+			time_property: options
+				.get_opt::<JsString, _, _>(&mut cx, "timeProperty")?
+				.map(|v| v.value(&mut cx)),
+		};
+
+		let on_progress = options.get_opt::<JsFunction, _, _>(&mut cx, "onProgress")?;
+		let print_progress = options
+			.get_opt::<JsBoolean, _, _>(&mut cx, "printProgress")?
+			.is_some_and(|v| v.value(&mut cx));
+		let result = match on_progress {
+			Some(callback) => {
+				// the index is created on this thread, so the callback can be called directly.
+				// If it throws, progress reporting stops and the exception is rethrown afterwards.
+				let state: RefCell<(&mut FunctionContext, Option<Handle<JsValue>>)> = RefCell::new((&mut cx, None));
+				let progress = |event: &ProgressEvent| {
+					if print_progress {
+						PrintProgress.report(event);
+					}
+					let (cx, exception) = &mut *state.borrow_mut();
+					if exception.is_some() {
+						return;
+					}
+					if let Err(error) = cx.try_catch(|cx| {
+						let event = progress_event_to_js(cx, event)?;
+						let this = cx.undefined();
+						callback.call(cx, this, [event.upcast::<JsValue>()])?;
+						Ok(())
+					}) {
+						*exception = Some(error);
+					}
+				};
+				let result = GeoDB::open(&filename, opt, Some(&progress));
+				if let (_, Some(exception)) = state.into_inner() {
+					return cx.throw(exception);
+				}
+				result
+			}
+			None if print_progress => GeoDB::open(&filename, opt, Some(&PrintProgress)),
+			None => GeoDB::open(&filename, opt, None),
+		};
+
+		match result {
+			Ok(geo_file) => Ok(cx.boxed(RefCell::new(geo_file))),
+			Err(err) => cx.throw_error(err.to_string()),
+		}
+	}
+	// Parse a where expression once, so the pages of a query can share it
+	pub fn js_filter(mut cx: FunctionContext) -> JsResult<BoxedFilter> {
+		let geo_db_js = cx.this().downcast_or_throw::<BoxedGeoDB, _>(&mut cx)?;
+		let geo_db = geo_db_js.borrow();
+		let expression = cx.argument::<JsString>(0)?.value(&mut cx);
+		match geo_db.get_filter(&expression) {
+			Ok(filter) => Ok(cx.boxed(filter)),
+			Err(err) => cx.throw_error(err.to_string()),
+		}
+	}
+	pub fn js_find(mut cx: FunctionContext) -> JsResult<JsArray> {
+		let geo_db_js = cx.this().downcast_or_throw::<BoxedGeoDB, _>(&mut cx)?;
+		let geo_db = geo_db_js.borrow();
+
+		let bbox = get_bbox(&mut cx, 0)?;
+		let start_index = cx.argument::<JsNumber>(1)?.value(&mut cx) as usize;
+		let max_count = cx.argument::<JsNumber>(2)?.value(&mut cx) as usize;
+		let filter = get_filter(&mut cx, 3)?;
+		let query = get_query(&mut cx, &geo_db, &bbox, filter.as_deref().map(|filter| &**filter), 4)?;
+
+		let (entries, next_index) = query.page(start_index, max_count);
+		let array = cx.empty_array();
+
+		for (i, entry) in entries.iter().enumerate() {
+			let line = cx.string(from_utf8(entry).unwrap());
+			array.set(&mut cx, i as u32, line)?;
+		}
+
+		let next_index = cx.number(next_index as u32);
+		let n = array.len(&mut cx);
+		array.set(&mut cx, n, next_index)?;
+
+		Ok(array)
+	}
+	// Like js_find, but with the features parsed into JS objects, optionally only with some properties
+	pub fn js_find_objects(mut cx: FunctionContext) -> JsResult<JsArray> {
+		let geo_db_js = cx.this().downcast_or_throw::<BoxedGeoDB, _>(&mut cx)?;
+		let geo_db = geo_db_js.borrow();
+
+		let bbox = get_bbox(&mut cx, 0)?;
+		let start_index = cx.argument::<JsNumber>(1)?.value(&mut cx) as usize;
+		let max_count = cx.argument::<JsNumber>(2)?.value(&mut cx) as usize;
+		let filter = get_filter(&mut cx, 3)?;
+		let query = get_query(&mut cx, &geo_db, &bbox, filter.as_deref().map(|filter| &**filter), 4)?;
+		let properties: Option<Vec<String>> = match cx.argument_opt(6) {
+			Some(value) if value.is_a::<JsArray, _>(&mut cx) => Some(
+				value
+					.downcast_or_throw::<JsArray, _>(&mut cx)?
+					.to_vec(&mut cx)?
+					.into_iter()
+					.map(|v| Ok(v.downcast_or_throw::<JsString, _>(&mut cx)?.value(&mut cx)))
+					.collect::<NeonResult<_>>()?,
+			),
+			_ => None,
+		};
+
+		let (features, next_index) = match query.json_page(start_index, max_count, properties.as_deref()) {
+			Ok(page) => page,
+			Err(err) => return cx.throw_error(err.to_string()),
+		};
+		let array = cx.empty_array();
+		let mut keys: HashMap<String, Handle<JsString>> = HashMap::new();
+		for (i, feature) in features.iter().enumerate() {
+			let object = json_to_js(&mut cx, feature, &mut keys)?;
+			array.set(&mut cx, i as u32, object)?;
+		}
+
+		let next_index = cx.number(next_index as u32);
+		let n = array.len(&mut cx);
+		array.set(&mut cx, n, next_index)?;
+
+		Ok(array)
+	}
+	// A page of lines as one buffer, each line followed by a newline, with the offsets of the lines and
+	// the end of the buffer as native u32 values, followed by the next index
+	pub fn js_find_binary(mut cx: FunctionContext) -> JsResult<JsArray> {
+		let geo_db_js = cx.this().downcast_or_throw::<BoxedGeoDB, _>(&mut cx)?;
+		let geo_db = geo_db_js.borrow();
+		let bbox = get_bbox(&mut cx, 0)?;
+		let start_index = cx.argument::<JsNumber>(1)?.value(&mut cx) as usize;
+		let max_count = cx.argument::<JsNumber>(2)?.value(&mut cx) as usize;
+		let filter = get_filter(&mut cx, 3)?;
+		let query = get_query(&mut cx, &geo_db, &bbox, filter.as_deref().map(|filter| &**filter), 4)?;
+
+		let page = match query.binary_page(start_index, max_count) {
+			Ok(page) => page,
+			Err(err) => return cx.throw_error(err.to_string()),
+		};
+		let array = cx.empty_array();
+		// the data is handed over without copying, the offsets are copied into memory allocated by JS,
+		// which is aligned for a Uint32Array
+		let buffer = JsBuffer::external(&mut cx, page.data);
+		array.set(&mut cx, 0, buffer)?;
+		let mut offsets_buffer = JsArrayBuffer::new(&mut cx, page.offsets.len() * 4)?;
+		for (bytes, offset) in offsets_buffer
+			.as_mut_slice(&mut cx)
+			.chunks_exact_mut(4)
+			.zip(page.offsets)
+		{
+			bytes.copy_from_slice(&offset.to_ne_bytes());
+		}
+		array.set(&mut cx, 1, offsets_buffer)?;
+		let next_index = cx.number(page.next_index as u32);
+		array.set(&mut cx, 2, next_index)?;
+		Ok(array)
+	}
+	pub fn js_find_feature_collection(mut cx: FunctionContext) -> JsResult<JsBuffer> {
+		let geo_db_js = cx.this().downcast_or_throw::<BoxedGeoDB, _>(&mut cx)?;
+		let geo_db = geo_db_js.borrow();
+		let bbox = get_bbox(&mut cx, 0)?;
+		let filter = get_filter(&mut cx, 1)?;
+		let query = get_query(&mut cx, &geo_db, &bbox, filter.as_deref().map(|filter| &**filter), 2)?;
+
+		let mut output: Vec<u8> = Vec::new();
+		match query.write_feature_collection(&mut output) {
+			Ok(_) => Ok(JsBuffer::external(&mut cx, output)),
+			Err(err) => cx.throw_error(err.to_string()),
+		}
+	}
+	// A page of a FeatureCollection: the features separated by commas, followed by the next index
+	pub fn js_find_features(mut cx: FunctionContext) -> JsResult<JsArray> {
+		let geo_db_js = cx.this().downcast_or_throw::<BoxedGeoDB, _>(&mut cx)?;
+		let geo_db = geo_db_js.borrow();
+		let bbox = get_bbox(&mut cx, 0)?;
+		let start_index = cx.argument::<JsNumber>(1)?.value(&mut cx) as usize;
+		let max_count = cx.argument::<JsNumber>(2)?.value(&mut cx) as usize;
+		let filter = get_filter(&mut cx, 3)?;
+		let query = get_query(&mut cx, &geo_db, &bbox, filter.as_deref().map(|filter| &**filter), 4)?;
+
+		let (lines, next_index) = query.page(start_index, max_count);
+		let mut output: Vec<u8> = Vec::new();
+		for (i, line) in lines.iter().enumerate() {
+			if i > 0 {
+				output.push(b',');
+			}
+			if let Err(err) = geo_db.write_feature(line, &mut output) {
+				return cx.throw_error(err.to_string());
+			}
+		}
+
+		let array = cx.empty_array();
+		let buffer = JsBuffer::external(&mut cx, output);
+		array.set(&mut cx, 0, buffer)?;
+		let next_index = cx.number(next_index as u32);
+		array.set(&mut cx, 1, next_index)?;
+		Ok(array)
+	}
+	pub fn js_get(mut cx: FunctionContext) -> JsResult<JsValue> {
+		let geo_db_js = cx.this().downcast_or_throw::<BoxedGeoDB, _>(&mut cx)?;
+		let mut geo_db = geo_db_js.borrow_mut();
+		let id = get_id(&mut cx, 0)?;
+
+		match geo_db.get(&id) {
+			Ok(Some(line)) => Ok(cx.string(from_utf8(line).unwrap()).upcast()),
+			Ok(None) => Ok(cx.undefined().upcast()),
+			Err(err) => cx.throw_error(err.to_string()),
+		}
+	}
+	pub fn js_get_many(mut cx: FunctionContext) -> JsResult<JsArray> {
+		let geo_db_js = cx.this().downcast_or_throw::<BoxedGeoDB, _>(&mut cx)?;
+		let mut geo_db = geo_db_js.borrow_mut();
+
+		let ids = cx.argument::<JsArray>(0)?.to_vec(&mut cx)?;
+		let ids: Vec<String> = ids
+			.into_iter()
+			.map(|v| id_from_js(&mut cx, v))
+			.collect::<NeonResult<_>>()?;
+		let ids: Vec<&str> = ids.iter().map(String::as_str).collect();
+
+		let lines = match geo_db.get_many(&ids) {
+			Ok(lines) => lines,
+			Err(err) => return cx.throw_error(err.to_string()),
+		};
+		let array = cx.empty_array();
+		for (i, line) in lines.iter().enumerate() {
+			let value: Handle<JsValue> = match line {
+				Some(line) => cx.string(from_utf8(line).unwrap()).upcast(),
+				None => cx.undefined().upcast(),
+			};
+			array.set(&mut cx, i as u32, value)?;
+		}
+		Ok(array)
+	}
+	pub fn js_append(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+		let geo_db_js = cx.this().downcast_or_throw::<BoxedGeoDB, _>(&mut cx)?;
+		let mut geo_db = geo_db_js.borrow_mut();
+
+		let lines = cx.argument::<JsArray>(0)?.to_vec(&mut cx)?;
+		let lines: Vec<String> = lines
+			.iter()
+			.map(|v| Ok(v.downcast_or_throw::<JsString, _>(&mut cx)?.value(&mut cx)))
+			.collect::<NeonResult<_>>()?;
+		let lines: Vec<&str> = lines.iter().map(String::as_str).collect();
+
+		match geo_db.append(&lines) {
+			Ok(()) => Ok(cx.undefined()),
+			Err(err) => cx.throw_error(err.to_string()),
+		}
+	}
+	pub fn js_delete(mut cx: FunctionContext) -> JsResult<JsBoolean> {
+		let geo_db_js = cx.this().downcast_or_throw::<BoxedGeoDB, _>(&mut cx)?;
+		let mut geo_db = geo_db_js.borrow_mut();
+		let id = get_id(&mut cx, 0)?;
+
+		match geo_db.delete(&id) {
+			Ok(deleted) => Ok(cx.boolean(deleted)),
+			Err(err) => cx.throw_error(err.to_string()),
+		}
+	}
+	pub fn js_update(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+		let geo_db_js = cx.this().downcast_or_throw::<BoxedGeoDB, _>(&mut cx)?;
+		let mut geo_db = geo_db_js.borrow_mut();
+		let id = get_id(&mut cx, 0)?;
+		let line = cx.argument::<JsString>(1)?.value(&mut cx);
+
+		match geo_db.update(&id, &line) {
+			Ok(()) => Ok(cx.undefined()),
+			Err(err) => cx.throw_error(err.to_string()),
+		}
+	}
+	pub fn js_compact(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+		let geo_db_js = cx.this().downcast_or_throw::<BoxedGeoDB, _>(&mut cx)?;
+		let mut geo_db = geo_db_js.borrow_mut();
+
+		match geo_db.compact(None) {
+			Ok(()) => Ok(cx.undefined()),
+			Err(err) => cx.throw_error(err.to_string()),
+		}
+	}
+}
+
+fn get_bbox(cx: &mut FunctionContext, index: i32) -> NeonResult<GeoBBox> {
+	let bbox = cx.argument::<JsArray>(index)?.to_vec(cx)?;
+	let bbox: Vec<f64> = bbox
+		.iter()
+		.map(|v| Ok(v.downcast_or_throw::<JsNumber, _>(cx)?.value(cx)))
+		.collect::<NeonResult<_>>()?;
+	if bbox.len() != 4 {
+		return cx.throw_error("bbox must be an Array of 4 numbers");
+	}
+	Ok(GeoBBox::from_f64(bbox[0], bbox[2], bbox[1], bbox[3]))
+}
+
+// The filter created by geofileFilter at this index, parsed once for all pages of a query
+fn get_filter<'a>(cx: &mut FunctionContext<'a>, index: i32) -> NeonResult<Option<Handle<'a, BoxedFilter>>> {
+	match cx.argument_opt(index) {
+		Some(value) if !value.is_a::<JsUndefined, _>(cx) => Ok(Some(value.downcast_or_throw::<BoxedFilter, _>(cx)?)),
+		_ => Ok(None),
+	}
+}
+
+// The query with the filter and the from and to arguments starting at this index
+fn get_query<'a>(
+	cx: &mut FunctionContext, geo_db: &'a GeoDB, bbox: &GeoBBox, filter: Option<&'a LineFilter>, index: i32,
+) -> NeonResult<Query<'a>> {
+	let mut query = geo_db.query(bbox);
+	if let Some(filter) = filter {
+		query = query.line_filter(filter);
+	}
+	let from = get_time(cx, index)?;
+	let to = get_time(cx, index + 1)?;
+	if from.is_some() || to.is_some() {
+		query = query.time(TimeRange::new(from.unwrap_or(i64::MIN), to.unwrap_or(i64::MAX)));
+	}
+	Ok(query)
+}
+
+// Convert parsed JSON to JS values. Handles of key strings are reused, as features repeat the same keys.
+fn json_to_js<'a>(
+	cx: &mut FunctionContext<'a>, value: &JsonValue, keys: &mut HashMap<String, Handle<'a, JsString>>,
+) -> JsResult<'a, JsValue> {
+	Ok(match value {
+		JsonValue::Null => cx.null().upcast(),
+		JsonValue::Bool(value) => cx.boolean(*value).upcast(),
+		JsonValue::Number(value) => cx.number(value.as_f64().unwrap_or(f64::NAN)).upcast(),
+		JsonValue::String(value) => cx.string(value).upcast(),
+		JsonValue::Array(values) => {
+			let array = JsArray::new(cx, values.len() as u32);
+			for (i, value) in values.iter().enumerate() {
+				let value = json_to_js(cx, value, keys)?;
+				array.set(cx, i as u32, value)?;
+			}
+			array.upcast()
+		}
+		JsonValue::Object(values) => {
+			let object = cx.empty_object();
+			for (key, value) in values {
+				let key = match keys.get(key) {
+					Some(key) => *key,
+					None => {
+						let handle = cx.string(key);
+						keys.insert(key.clone(), handle);
+						handle
+					}
+				};
+				let value = json_to_js(cx, value, keys)?;
+				object.set(cx, key, value)?;
+			}
+			object.upcast()
+		}
+	})
+}
+
+// Times can be given as milliseconds since 1970 or as ISO 8601 strings
+fn get_time(cx: &mut FunctionContext, index: i32) -> NeonResult<Option<i64>> {
+	let value = match cx.argument_opt(index) {
+		Some(value) => value,
+		None => return Ok(None),
+	};
+	if let Ok(number) = value.downcast::<JsNumber, _>(cx) {
+		let number = number.value(cx);
+		if number.is_nan() {
+			// e.g. an invalid Date, which would otherwise find the features at 1970-01-01
+			return cx.throw_type_error("Invalid time: NaN");
+		}
+		return Ok(Some(number as i64));
+	}
+	if let Ok(text) = value.downcast::<JsString, _>(cx) {
+		let text = text.value(cx);
+		return match parse_time(&text) {
+			Some(time) => Ok(Some(time)),
+			None => cx.throw_error(format!("Invalid time: {}", text)),
+		};
+	}
+	Ok(None)
+}
+
+// Ids can be given as strings or numbers
+fn get_id(cx: &mut FunctionContext, index: i32) -> NeonResult<String> {
+	let value = cx.argument::<JsValue>(index)?;
+	id_from_js(cx, value)
+}
+
+fn id_from_js(cx: &mut FunctionContext, value: Handle<JsValue>) -> NeonResult<String> {
+	if let Ok(number) = value.downcast::<JsNumber, _>(cx) {
+		return Ok(number.value(cx).to_string());
+	}
+	Ok(value.downcast_or_throw::<JsString, _>(cx)?.value(cx))
+}
+
+fn progress_event_to_js<'a>(cx: &mut impl Context<'a>, event: &ProgressEvent) -> JsResult<'a, JsObject> {
+	let object = cx.empty_object();
+	let phase = cx.string(event.phase);
+	object.set(cx, "phase", phase)?;
+	for (key, value) in [
+		("rows", event.rows as f64),
+		("bytes", event.bytes as f64),
+		("percent", event.percent),
+		("rowsPerSecond", event.rows_per_second),
+		("bytesPerSecond", event.bytes_per_second),
+	] {
+		let value = cx.number(value);
+		object.set(cx, key, value)?;
+	}
+	Ok(object)
+}
+
+#[neon::main]
+fn main(mut cx: ModuleContext) -> NeonResult<()> {
+	cx.export_function("geofileOpen", GeoDB::js_open)?;
+	cx.export_function("geofileFilter", GeoDB::js_filter)?;
+	cx.export_function("geofileFind", GeoDB::js_find)?;
+	cx.export_function("geofileFindObjects", GeoDB::js_find_objects)?;
+	cx.export_function("geofileFindBinary", GeoDB::js_find_binary)?;
+	cx.export_function("geofileFindFeatureCollection", GeoDB::js_find_feature_collection)?;
+	cx.export_function("geofileFindFeatures", GeoDB::js_find_features)?;
+	cx.export_function("geofileGet", GeoDB::js_get)?;
+	cx.export_function("geofileGetMany", GeoDB::js_get_many)?;
+	cx.export_function("geofileAppend", GeoDB::js_append)?;
+	cx.export_function("geofileDelete", GeoDB::js_delete)?;
+	cx.export_function("geofileUpdate", GeoDB::js_update)?;
+	cx.export_function("geofileCompact", GeoDB::js_compact)?;
+	Ok(())
+}
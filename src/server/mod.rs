@@ -0,0 +1,871 @@
+mod mvt;
+mod ogc;
+
+use crate::{format_time, parse_time, FeatureCollectionWriter, GeoBBox, GeoDB, TimeRange};
+use ogc::{link, percent_encode, GEOJSON, JSON};
+use serde_json::{json, Map, Value};
+use std::{
+	error::Error,
+	result::Result,
+	thread,
+	time::{SystemTime, UNIX_EPOCH},
+};
+use tiny_http::{Header, Method};
+
+// Number of features of an items response without a limit parameter, and the largest allowed limit
+const DEFAULT_LIMIT: usize = 10;
+const MAX_LIMIT: usize = 10000;
+// Deepest zoom level of vector tiles
+const MAX_ZOOM: u32 = 24;
+// Default of the largest number of features in a vector tile, see GeoServer::set_max_tile_features
+const MAX_TILE_FEATURES: usize = 100_000;
+
+/// A response of [`GeoServer::handle`]
+#[derive(Debug, PartialEq)]
+pub struct Response {
+	pub status: u16,
+	pub content_type: &'static str,
+	pub body: Vec<u8>,
+}
+
+struct Collection {
+	name: String,
+	geo_db: GeoDB,
+	// lookups by id need the id indexes loaded on startup, CSV/TSV files without an id column have none
+	has_ids: bool,
+	// extent of the index when the collection was added, in the coordinates of this EPSG code
+	extent: Option<GeoBBox>,
+	epsg_code: u32,
+	time: Option<TimeRange>,
+}
+
+/// An HTTP server hosting several [`GeoDB`]s as collections. It implements the core of OGC API - Features,
+/// so that GIS clients like QGIS can load the collections:
+///
+/// - `/`, `/api`, `/conformance`: the landing page, the OpenAPI definition and the conformance classes
+/// - `/collections` and `/collections/{name}`: the collections with their extent
+/// - `/collections/{name}/items?bbox=&limit=&offset=&datetime=&where=&from=&to=`: a FeatureCollection of the
+///   features in a bbox, with a next link to the following page, which continues at a `cursor` in the index
+/// - `/collections/{name}/items/{id}` and `/items/{id}`: a feature by id, the latter from the first collection with it
+/// - `/tiles/{z}/{x}/{y}.mvt?collections=`: a Mapbox Vector Tile with a layer for each collection
+///
+/// Extents and tiles expect longitude/latitude coordinates. CSV/TSV lines are returned as GeoJSON point features.
+///
+/// ```no_run
+/// use geojson_db::{GeoDB, GeoServer};
+///
+/// let mut server = GeoServer::new();
+/// server.add_collection("places", GeoDB::builder("places.geojsonl.gz").open()?)?;
+/// server.serve("127.0.0.1:8080", 4)?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub struct GeoServer {
+	collections: Vec<Collection>,
+	max_tile_features: usize,
+	base_url: Option<String>,
+}
+
+impl Default for GeoServer {
+	fn default() -> Self {
+		GeoServer {
+			collections: Vec::new(),
+			max_tile_features: MAX_TILE_FEATURES,
+			base_url: None,
+		}
+	}
+}
+
+impl GeoServer {
+	/// A server without collections
+	pub fn new() -> Self {
+		GeoServer::default()
+	}
+
+	/// Start all links with this URL, e.g. "https://example.com/features" behind a reverse proxy. By default,
+	/// links start with the Host header of the request, and with the scheme of an X-Forwarded-Proto header.
+	pub fn set_base_url(&mut self, base_url: &str) {
+		self.base_url = Some(base_url.trim_end_matches('/').to_string());
+	}
+
+	/// Limit the number of features of a vector tile, 100000 by default. Further features in the tile's bbox
+	/// are left out, so that tiles of dense data at low zoom levels stay small.
+	pub fn set_max_tile_features(&mut self, max_features: usize) {
+		self.max_tile_features = max_features;
+	}
+
+	/// Add a collection. Names must be unique and can't contain slashes.
+	pub fn add_collection(&mut self, name: &str, mut geo_db: GeoDB) -> Result<(), Box<dyn Error>> {
+		if name.is_empty() || name.contains('/') || self.get_collection(name).is_some() {
+			return Err(Box::new(std::io::Error::new(
+				std::io::ErrorKind::InvalidInput,
+				format!("Invalid or duplicate collection name: {}", name),
+			)));
+		}
+		let has_ids = geo_db.has_ids();
+		if has_ids {
+			geo_db.load_ids()?;
+		}
+		let info = geo_db.info();
+		let epsg_code = geo_db.epsg_code();
+		self.collections.push(Collection {
+			name: name.to_string(),
+			geo_db,
+			has_ids,
+			extent: info.extent,
+			epsg_code,
+			time: info.time,
+		});
+		Ok(())
+	}
+
+	/// Listen on an address like "127.0.0.1:8080" and answer GET requests with this many threads.
+	/// Only returns if the address can't be used.
+	pub fn serve(&self, address: &str, threads: usize) -> Result<(), Box<dyn Error>> {
+		let server = tiny_http::Server::http(address).map_err(|error| -> Box<dyn Error> { error })?;
+		self.serve_requests(&server, threads.max(1));
+		Ok(())
+	}
+
+	// Answer the requests of a listening server with this many threads, each until it is unblocked
+	fn serve_requests(&self, server: &tiny_http::Server, threads: usize) {
+		thread::scope(|scope| {
+			for _ in 0..threads {
+				scope.spawn(|| {
+					for request in server.incoming_requests() {
+						let base_url = self.get_base_url(request.headers());
+						let response = match request.method() {
+							Method::Get | Method::Head => self.respond(&base_url, request.url()),
+							_ => error(405, "MethodNotAllowed", "Only GET requests are supported"),
+						};
+						let content_type = Header::from_bytes("Content-Type", response.content_type).unwrap();
+						let cors = Header::from_bytes("Access-Control-Allow-Origin", "*").unwrap();
+						let http_response = tiny_http::Response::from_data(response.body)
+							.with_status_code(response.status)
+							.with_header(content_type)
+							.with_header(cors);
+						// the client may be gone already
+						request.respond(http_response).ok();
+					}
+				});
+			}
+		});
+	}
+
+	// The configured base URL, or the host the client asked for, with the scheme a proxy received the request with
+	fn get_base_url(&self, headers: &[Header]) -> String {
+		if let Some(base_url) = &self.base_url {
+			return base_url.clone();
+		}
+		let header = |name: &'static str| {
+			headers
+				.iter()
+				.find(|header| header.field.equiv(name))
+				.map(|header| header.value.as_str())
+		};
+		let scheme = header("X-Forwarded-Proto")
+			.and_then(|protocols| protocols.split(',').next())
+			.map(str::trim)
+			.filter(|scheme| matches!(*scheme, "http" | "https"))
+			.unwrap_or("http");
+		header("Host")
+			.map(|host| format!("{}://{}", scheme, host))
+			.unwrap_or_default()
+	}
+
+	/// Answer a GET request of a URL path with its query string, e.g. "/collections/places/items?bbox=7,50,8,51".
+	/// Links in the responses are relative to the host, like "/collections/places".
+	pub fn handle(&self, url: &str) -> Response {
+		self.respond("", url)
+	}
+
+	// Answer a GET request with links starting with base_url, e.g. "http://localhost:8080"
+	fn respond(&self, base_url: &str, url: &str) -> Response {
+		let (path, query) = url.split_once('?').unwrap_or((url, ""));
+		let params: Vec<(String, String)> = query
+			.split('&')
+			.filter(|param| !param.is_empty())
+			.map(|param| {
+				let (key, value) = param.split_once('=').unwrap_or((param, ""));
+				(percent_decode(key), percent_decode(value))
+			})
+			.collect();
+		let segments: Vec<String> = path.split('/').filter(|s| !s.is_empty()).map(percent_decode).collect();
+		let segments: Vec<&str> = segments.iter().map(String::as_str).collect();
+
+		let result = match segments[..] {
+			[] => Some(json_response(ogc::landing_page(base_url))),
+			["api"] => Some(Response {
+				status: 200,
+				content_type: ogc::OPENAPI,
+				body: ogc::api_definition(base_url).to_string().into_bytes(),
+			}),
+			["conformance"] => Some(json_response(ogc::conformance())),
+			["collections"] => Some(self.collections(base_url)),
+			["collections", name] => self.get_collection(name).map(|c| {
+				json_response(ogc::collection(
+					base_url,
+					&c.name,
+					c.extent.as_ref(),
+					c.epsg_code,
+					c.time.as_ref(),
+				))
+			}),
+			["collections", name, "items"] => self.get_collection(name).map(|c| self.items(base_url, c, &params)),
+			["collections", name, "items", id] => self.get_collection(name).map(|c| self.item(base_url, &[c], id)),
+			["items", id] => Some(self.item(base_url, &self.collections.iter().collect::<Vec<_>>(), id)),
+			["tiles", z, x, y] => Some(self.tile(z, x, y, get_param(&params, "collections"))),
+			_ => None,
+		};
+		result.unwrap_or_else(|| error(404, "NotFound", &format!("Not found: {}", path)))
+	}
+
+	fn get_collection(&self, name: &str) -> Option<&Collection> {
+		self.collections.iter().find(|collection| collection.name == name)
+	}
+
+	fn collections(&self, base_url: &str) -> Response {
+		let collections: Vec<Value> = self
+			.collections
+			.iter()
+			.map(|c| ogc::collection(base_url, &c.name, c.extent.as_ref(), c.epsg_code, c.time.as_ref()))
+			.collect();
+		json_response(json!({
+			"links": [link(&format!("{}/collections", base_url), "self", JSON, "The feature collections")],
+			"collections": collections,
+		}))
+	}
+
+	fn items(&self, base_url: &str, collection: &Collection, params: &[(String, String)]) -> Response {
+		let param = |name: &str| get_param(params, name);
+		let bbox = match param("bbox").map(parse_bbox).transpose() {
+			Ok(bbox) => bbox.unwrap_or(GeoBBox::new(f32::MIN, f32::MAX, f32::MIN, f32::MAX)),
+			Err(message) => return error(400, "InvalidParameterValue", &message),
+		};
+		let limit = match param("limit").map(|limit| limit.parse::<usize>()).transpose() {
+			Ok(Some(0)) | Err(_) => return error(400, "InvalidParameterValue", "Invalid limit"),
+			Ok(limit) => limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT),
+		};
+		let offset = match param("offset").map(|offset| offset.parse::<usize>()).transpose() {
+			Ok(offset) => offset.unwrap_or(0),
+			Err(_) => return error(400, "InvalidParameterValue", "Invalid offset"),
+		};
+		let cursor = match param("cursor").map(|cursor| cursor.parse::<usize>()).transpose() {
+			Ok(cursor) => cursor.unwrap_or(0),
+			Err(_) => return error(400, "InvalidParameterValue", "Invalid cursor"),
+		};
+		let times = match param("datetime") {
+			Some(datetime) => parse_datetime(datetime),
+			None => parse_time_param(param("from")).and_then(|from| Ok((from, parse_time_param(param("to"))?))),
+		};
+		let (from, to) = match times {
+			Ok(times) => times,
+			Err(message) => return error(400, "InvalidParameterValue", &message),
+		};
+
+		let mut query = collection.geo_db.query(&bbox);
+		if let Some(expression) = param("where") {
+			query = match query.filter(expression) {
+				Ok(query) => query,
+				Err(message) => return error(400, "InvalidParameterValue", &message.to_string()),
+			};
+		}
+		if from.is_some() || to.is_some() {
+			query = query.time(TimeRange::new(from.unwrap_or(i64::MIN), to.unwrap_or(i64::MAX)));
+		}
+
+		// skip offset features after the cursor, a page at a time
+		let mut start_index = cursor;
+		let mut skip = offset;
+		let mut is_done = false;
+		while skip > 0 && !is_done {
+			let (lines, next_index) = query.page(start_index, skip.min(MAX_LIMIT));
+			skip -= lines.len();
+			start_index = next_index;
+			is_done = next_index == 0;
+		}
+		let (lines, next_index) = match is_done {
+			true => (Vec::new(), 0),
+			false => query.page(start_index, limit),
+		};
+		let mut writer = match FeatureCollectionWriter::new(&collection.geo_db, Vec::new()) {
+			Ok(writer) => writer,
+			Err(message) => return error(500, "InvalidFeature", &message.to_string()),
+		};
+		for line in &lines {
+			if let Err(message) = writer.write_line(line) {
+				return error(500, "InvalidFeature", &message.to_string());
+			}
+		}
+
+		// the next page continues at a cursor, a position in the index, with the other parameters unchanged
+		let items_url = format!("{}/collections/{}/items", base_url, percent_encode(&collection.name));
+		let page_url = |cursor: Option<usize>| {
+			let mut query: Vec<String> = params
+				.iter()
+				.filter(|(key, _)| cursor.is_none() || (key != "offset" && key != "cursor"))
+				.map(|(key, value)| format!("{}={}", percent_encode(key), percent_encode(value)))
+				.collect();
+			if let Some(cursor) = cursor {
+				query.push(format!("cursor={}", cursor));
+			}
+			match query.is_empty() {
+				true => items_url.clone(),
+				false => format!("{}?{}", items_url, query.join("&")),
+			}
+		};
+		let mut links = vec![
+			link(&page_url(None), "self", GEOJSON, "This page"),
+			link(
+				&format!("{}/collections/{}", base_url, percent_encode(&collection.name)),
+				"collection",
+				JSON,
+				"The collection",
+			),
+		];
+		if next_index > 0 {
+			links.push(link(&page_url(Some(next_index)), "next", GEOJSON, "The next page"));
+		}
+		let now = SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.map_or(0, |duration| duration.as_millis() as i64);
+		let mut members = Map::new();
+		members.insert("numberReturned".to_string(), Value::from(lines.len()));
+		members.insert("timeStamp".to_string(), Value::from(format_time(now)));
+		members.insert("links".to_string(), Value::from(links));
+		match writer.finish_with_members(&members) {
+			Ok(body) => geojson_response(body),
+			Err(message) => error(500, "InvalidFeature", &message.to_string()),
+		}
+	}
+
+	fn item(&self, base_url: &str, collections: &[&Collection], id: &str) -> Response {
+		for collection in collections.iter().filter(|collection| collection.has_ids) {
+			if let Some(line) = collection.geo_db.get_loaded(id) {
+				let mut body: Vec<u8> = Vec::new();
+				let feature = collection
+					.geo_db
+					.write_feature(line, &mut body)
+					.and_then(|()| Ok(serde_json::from_slice::<Value>(&body)?));
+				let mut feature = match feature {
+					Ok(Value::Object(feature)) => feature,
+					Ok(_) => return error(500, "InvalidFeature", "The feature is not an object"),
+					Err(message) => return error(500, "InvalidFeature", &message.to_string()),
+				};
+				let url = format!("{}/collections/{}", base_url, percent_encode(&collection.name));
+				feature.insert(
+					"links".to_string(),
+					json!([
+						link(
+							&format!("{}/items/{}", url, percent_encode(id)),
+							"self",
+							GEOJSON,
+							"This feature"
+						),
+						link(&url, "collection", JSON, "The collection"),
+					]),
+				);
+				return geojson_response(Value::Object(feature).to_string().into_bytes());
+			}
+		}
+		error(404, "NotFound", &format!("Feature not found: {}", id))
+	}
+
+	fn tile(&self, z: &str, x: &str, y: &str, collections: Option<&str>) -> Response {
+		let coordinates = y
+			.strip_suffix(".mvt")
+			.and_then(|y| Some((z.parse().ok()?, x.parse().ok()?, y.parse().ok()?)));
+		let (z, x, y): (u32, u32, u32) = match coordinates {
+			Some((z, x, y)) if z <= MAX_ZOOM && (x as u64) < (1u64 << z) && (y as u64) < (1u64 << z) => (z, x, y),
+			_ => return error(404, "NotFound", "Invalid tile"),
+		};
+		let names: Option<Vec<&str>> = collections.map(|names| names.split(',').collect());
+		if let Some(name) = names.iter().flatten().find(|name| self.get_collection(name).is_none()) {
+			return error(404, "NotFound", &format!("Collection not found: {}", name));
+		}
+
+		let bbox = mvt::tile_bbox(z, x, y);
+		let mut body: Vec<u8> = Vec::new();
+		let mut count = 0;
+		for collection in &self.collections {
+			if names
+				.as_ref()
+				.is_some_and(|names| !names.contains(&collection.name.as_str()))
+			{
+				continue;
+			}
+			let mut layer = mvt::Layer::new(&collection.name, z, x, y);
+			for line in collection.geo_db.query(&bbox).lines() {
+				if count >= self.max_tile_features {
+					break;
+				}
+				// lines that aren't valid features are left out of the tile
+				if let Ok(feature) = collection.geo_db.parse_feature(line) {
+					layer.add_feature(&feature);
+					count += 1;
+				}
+			}
+			if !layer.is_empty() {
+				layer.encode(&mut body);
+			}
+		}
+		Response {
+			status: 200,
+			content_type: "application/vnd.mapbox-vector-tile",
+			body,
+		}
+	}
+}
+
+fn get_param<'a>(params: &'a [(String, String)], name: &str) -> Option<&'a str> {
+	params
+		.iter()
+		.find(|(key, _)| key == name)
+		.map(|(_, value)| value.as_str())
+}
+
+fn json_response(value: Value) -> Response {
+	Response {
+		status: 200,
+		content_type: JSON,
+		body: value.to_string().into_bytes(),
+	}
+}
+
+fn geojson_response(body: Vec<u8>) -> Response {
+	Response {
+		status: 200,
+		content_type: GEOJSON,
+		body,
+	}
+}
+
+// An error response in the format of OGC API exceptions
+fn error(status: u16, code: &str, description: &str) -> Response {
+	Response {
+		status,
+		content_type: "application/json",
+		body: json!({ "code": code, "description": description })
+			.to_string()
+			.into_bytes(),
+	}
+}
+
+// A bbox parameter "x_min,y_min,x_max,y_max", or with heights "x_min,y_min,z_min,x_max,y_max,z_max"
+fn parse_bbox(text: &str) -> Result<GeoBBox, String> {
+	let values: Vec<f64> = text
+		.split(',')
+		.map(|value| value.trim().parse::<f64>())
+		.collect::<Result<_, _>>()
+		.map_err(|_| format!("Invalid bbox: {}", text))?;
+	match values[..] {
+		[x_min, y_min, x_max, y_max] | [x_min, y_min, _, x_max, y_max, _] => {
+			Ok(GeoBBox::from_f64(x_min, x_max, y_min, y_max))
+		}
+		_ => Err(format!("Invalid bbox: {}", text)),
+	}
+}
+
+// An ISO 8601 time or milliseconds since 1970
+fn parse_time_param(text: Option<&str>) -> Result<Option<i64>, String> {
+	match text {
+		Some(text) => match text.parse::<i64>().ok().or_else(|| parse_time(text)) {
+			Some(time) => Ok(Some(time)),
+			None => Err(format!("Invalid time: {}", text)),
+		},
+		None => Ok(None),
+	}
+}
+
+// A datetime parameter: a time, or an interval of two times separated by "/", with ".." or nothing for open ends
+fn parse_datetime(text: &str) -> Result<(Option<i64>, Option<i64>), String> {
+	let bound = |text: &str| match text {
+		"" | ".." => Ok(None),
+		text => parse_time_param(Some(text)),
+	};
+	match text.split_once('/') {
+		Some((from, to)) => Ok((bound(from)?, bound(to)?)),
+		None => {
+			let time = parse_time_param(Some(text))?;
+			Ok((time, time))
+		}
+	}
+}
+
+// Decode %XX escapes and "+" for spaces, keeping invalid escapes as they are
+fn percent_decode(text: &str) -> String {
+	let bytes = text.as_bytes();
+	let mut decoded: Vec<u8> = Vec::with_capacity(bytes.len());
+	let mut i = 0;
+	while i < bytes.len() {
+		let hex = bytes
+			.get(i + 1..i + 3)
+			.and_then(|hex| std::str::from_utf8(hex).ok())
+			.and_then(|hex| u8::from_str_radix(hex, 16).ok());
+		match (bytes[i], hex) {
+			(b'%', Some(byte)) => {
+				decoded.push(byte);
+				i += 3;
+			}
+			(b'+', _) => {
+				decoded.push(b' ');
+				i += 1;
+			}
+			(byte, _) => {
+				decoded.push(byte);
+				i += 1;
+			}
+		}
+	}
+	String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use assert_fs::TempDir;
+	use serde_json::Value;
+	use std::{
+		fs::write,
+		io::{Read, Write},
+		net::TcpStream,
+	};
+
+	fn create_server(dir: &TempDir) -> Result<GeoServer, Box<dyn Error>> {
+		let filename = dir.path().join("places.geojsonl");
+		let feature = |id: usize, name: &str, x: f64, y: f64| {
+			format!(
+				"{{\"type\":\"Feature\",\"id\":{},\"properties\":{{\"name\":\"{}\"}},\"geometry\":{{\"type\":\"Point\",\"coordinates\":[{},{}]}}}}",
+				id, name, x, y
+			)
+		};
+		let lines = [
+			feature(1, "Stuttgart", 9.18, 48.78),
+			feature(2, "Mainz", 8.27, 50.0),
+			feature(3, "Bad Ems", 7.71, 50.33),
+		];
+		write(&filename, lines.join("\n") + "\n")?;
+		let filename_csv = dir.path().join("stations.csv");
+		write(&filename_csv, "name,x,y\nMannheim,8.47,49.48\n")?;
+
+		let mut server = GeoServer::new();
+		server.add_collection("places", GeoDB::builder(&filename).open()?)?;
+		server.add_collection(
+			"stations",
+			GeoDB::builder(&filename_csv).skip_lines(1).col_x(1).col_y(2).open()?,
+		)?;
+		assert!(server
+			.add_collection("places", GeoDB::builder(&filename).open()?)
+			.is_err());
+		Ok(server)
+	}
+
+	fn get_json(server: &GeoServer, url: &str) -> Result<(u16, Value), Box<dyn Error>> {
+		let response = server.handle(url);
+		Ok((response.status, serde_json::from_slice(&response.body)?))
+	}
+
+	fn names(value: &Value) -> Vec<&str> {
+		let mut names: Vec<&str> = value["features"]
+			.as_array()
+			.unwrap()
+			.iter()
+			.map(|feature| feature["properties"]["name"].as_str().unwrap())
+			.collect();
+		names.sort();
+		names
+	}
+
+	#[test]
+	fn test_items() -> Result<(), Box<dyn Error>> {
+		let dir = TempDir::new()?;
+		let server = create_server(&dir)?;
+
+		let (status, value) = get_json(&server, "/collections/places/items")?;
+		assert_eq!(status, 200);
+		assert_eq!(value["type"], "FeatureCollection");
+		assert_eq!(value["numberReturned"], 3);
+		let (_, value) = get_json(&server, "/collections/places/items?bbox=7,49.9,9,51")?;
+		assert_eq!(names(&value), vec!["Bad Ems", "Mainz"]);
+		let (_, value) = get_json(&server, "/collections/places/items?bbox=7,49.9,9,51&limit=1")?;
+		assert_eq!(value["numberReturned"], 1);
+		let (_, value) = get_json(&server, "/collections/places/items?where=name+%3D+%27Bad%20Ems%27")?;
+		assert_eq!(names(&value), vec!["Bad Ems"]);
+
+		// CSV rows become point features
+		let (_, value) = get_json(&server, "/collections/stations/items")?;
+		assert_eq!(names(&value), vec!["Mannheim"]);
+		assert_eq!(value["features"][0]["geometry"]["coordinates"], json!([8.47, 49.48]));
+
+		for url in [
+			"/collections/places/items?bbox=1,2,3",
+			"/collections/places/items?limit=-1",
+			"/collections/places/items?where=name+%3D",
+			"/collections/places/items?from=soon",
+			"/collections/places/items?limit=0",
+			"/collections/places/items?offset=first",
+			"/collections/places/items?cursor=-1",
+			"/collections/places/items?datetime=2024-01-01/later",
+		] {
+			let (status, value) = get_json(&server, url)?;
+			assert_eq!(status, 400, "{}", url);
+			assert_eq!(value["code"], "InvalidParameterValue");
+		}
+		assert_eq!(server.handle("/collections/rivers/items").status, 404);
+		assert_eq!(server.handle("/unknown").status, 404);
+		Ok(())
+	}
+
+	#[test]
+	fn test_ogc_api() -> Result<(), Box<dyn Error>> {
+		let dir = TempDir::new()?;
+		let server = create_server(&dir)?;
+
+		let (status, value) = get_json(&server, "/")?;
+		assert_eq!(status, 200);
+		let rels: Vec<&str> = value["links"]
+			.as_array()
+			.unwrap()
+			.iter()
+			.map(|link| link["rel"].as_str().unwrap())
+			.collect();
+		assert_eq!(rels, vec!["self", "service-desc", "conformance", "data"]);
+		let (_, value) = get_json(&server, "/conformance")?;
+		assert!(value["conformsTo"]
+			.as_array()
+			.unwrap()
+			.contains(&json!("http://www.opengis.net/spec/ogcapi-features-1/1.0/conf/core")));
+		let (status, value) = get_json(&server, "/api")?;
+		assert_eq!(status, 200);
+		let parameters: Vec<&str> = value["paths"]["/collections/{collectionId}/items"]["get"]["parameters"]
+			.as_array()
+			.unwrap()
+			.iter()
+			.map(|parameter| parameter["name"].as_str().unwrap())
+			.collect();
+		assert!(parameters.contains(&"offset") && parameters.contains(&"cursor"));
+
+		let (_, value) = get_json(&server, "/collections")?;
+		let ids: Vec<&str> = value["collections"]
+			.as_array()
+			.unwrap()
+			.iter()
+			.map(|collection| collection["id"].as_str().unwrap())
+			.collect();
+		assert_eq!(ids, vec!["places", "stations"]);
+		let (_, value) = get_json(&server, "/collections/places")?;
+		// the index rounds bboxes outwards to f32
+		let bbox: Vec<f64> = serde_json::from_value(value["extent"]["spatial"]["bbox"][0].clone())?;
+		for (value, expected) in bbox.iter().zip([7.71, 48.78, 9.18, 50.33]) {
+			assert!((value - expected).abs() < 1e-4, "{:?}", bbox);
+		}
+		assert_eq!(
+			value["extent"]["spatial"]["crs"],
+			"http://www.opengis.net/def/crs/OGC/1.3/CRS84"
+		);
+		assert_eq!(value["links"][1]["href"], "/collections/places/items");
+		assert_eq!(server.handle("/collections/rivers").status, 404);
+
+		// extents of reprojected collections are in the target CRS
+		let filename = dir.path().join("mercator.geojsonl");
+		std::fs::copy(dir.path().join("places.geojsonl"), &filename)?;
+		let mut server = GeoServer::new();
+		server.add_collection("mercator", GeoDB::builder(&filename).target_crs("EPSG:3857").open()?)?;
+		let (_, value) = get_json(&server, "/collections/mercator")?;
+		assert_eq!(
+			value["extent"]["spatial"]["crs"],
+			"http://www.opengis.net/def/crs/EPSG/0/3857"
+		);
+		assert!(value["extent"]["spatial"]["bbox"][0][2].as_f64().unwrap() > 1e6);
+		Ok(())
+	}
+
+	#[test]
+	fn test_paging() -> Result<(), Box<dyn Error>> {
+		let dir = TempDir::new()?;
+		let server = create_server(&dir)?;
+
+		// follow the next links through all features, keeping the other parameters
+		let mut url = "/collections/places/items?limit=1&where=name+%3C%3E+%27Mainz%27".to_string();
+		let mut found: Vec<String> = Vec::new();
+		loop {
+			let (status, value) = get_json(&server, &url)?;
+			assert_eq!(status, 200);
+			assert!(value["timeStamp"].is_string());
+			assert!(value["numberReturned"].as_u64().unwrap() <= 1);
+			found.extend(names(&value).into_iter().map(String::from));
+			let links = value["links"].as_array().unwrap();
+			assert_eq!(links[0]["rel"], "self");
+			match links.iter().find(|link| link["rel"] == "next") {
+				Some(link) => {
+					let next = link["href"].as_str().unwrap();
+					assert!(next.contains("where=name%20%3C%3E%20%27Mainz%27"), "{}", next);
+					url = next.to_string();
+				}
+				None => break,
+			}
+		}
+		found.sort();
+		assert_eq!(found, vec!["Bad Ems", "Stuttgart"]);
+
+		// offset skips features, also across the pages of a cursor
+		let (_, value) = get_json(&server, "/collections/places/items?offset=1")?;
+		assert_eq!(value["numberReturned"], 2);
+		let (_, value) = get_json(&server, "/collections/places/items?offset=5")?;
+		assert_eq!(value["numberReturned"], 0);
+		let (_, value) = get_json(&server, "/collections/places/items?offset=1&limit=1")?;
+		assert_eq!(value["numberReturned"], 1);
+		assert_eq!(value["links"][0]["href"], "/collections/places/items?offset=1&limit=1");
+		let first = names(&value)[0].to_string();
+		let next = value["links"][2]["href"].as_str().unwrap();
+		assert!(
+			next.contains("?limit=1&cursor=") && !next.contains("offset"),
+			"{}",
+			next
+		);
+		let (_, value) = get_json(&server, next)?;
+		assert_eq!(value["numberReturned"], 1);
+		assert_ne!(names(&value)[0], first);
+		let (_, value) = get_json(&server, &format!("{}&offset=1", next))?;
+		assert_eq!(value["numberReturned"], 0);
+
+		let (_, value) = get_json(&server, "/collections/places/items?limit=3")?;
+		assert_eq!(value["numberReturned"], 3);
+		assert!(!value["links"]
+			.as_array()
+			.unwrap()
+			.iter()
+			.any(|link| link["rel"] == "next"));
+		Ok(())
+	}
+
+	#[test]
+	fn test_item() -> Result<(), Box<dyn Error>> {
+		let dir = TempDir::new()?;
+		let server = create_server(&dir)?;
+
+		let (status, value) = get_json(&server, "/collections/places/items/2")?;
+		assert_eq!(status, 200);
+		assert_eq!(value["properties"]["name"], "Mainz");
+		assert_eq!(value["links"][0]["href"], "/collections/places/items/2");
+		let (_, value) = get_json(&server, "/items/3")?;
+		assert_eq!(value["properties"]["name"], "Bad Ems");
+		assert_eq!(server.handle("/items/4").status, 404);
+		// the CSV file has no id column
+		assert_eq!(server.handle("/collections/stations/items/Mannheim").status, 404);
+		Ok(())
+	}
+
+	#[test]
+	fn test_tile() -> Result<(), Box<dyn Error>> {
+		let dir = TempDir::new()?;
+		let server = create_server(&dir)?;
+
+		let response = server.handle("/tiles/0/0/0.mvt");
+		assert_eq!(response.status, 200);
+		assert_eq!(response.content_type, "application/vnd.mapbox-vector-tile");
+		let contains = |bytes: &[u8], part: &[u8]| bytes.windows(part.len()).any(|window| window == part);
+		assert!(contains(&response.body, b"places"));
+		assert!(contains(&response.body, b"stations"));
+		assert!(contains(&response.body, b"Bad Ems"));
+
+		let body = server.handle("/tiles/0/0/0.mvt?collections=stations").body;
+		assert!(!contains(&body, b"places"));
+		assert!(contains(&body, b"Mannheim"));
+		// z8 tile of Stuttgart
+		let body = server.handle("/tiles/8/134/88.mvt").body;
+		assert!(contains(&body, b"Stuttgart"));
+		assert!(!contains(&body, b"Mainz"));
+		assert!(server.handle("/tiles/8/0/0.mvt").body.is_empty());
+
+		// only the first features of a tile are included
+		let mut server = server;
+		server.set_max_tile_features(1);
+		let body = server.handle("/tiles/0/0/0.mvt").body;
+		let places = ["Stuttgart", "Mainz", "Bad Ems"];
+		assert_eq!(places.iter().filter(|name| contains(&body, name.as_bytes())).count(), 1);
+		assert!(!contains(&body, b"stations"));
+
+		for url in [
+			"/tiles/1/2/0.mvt",
+			"/tiles/0/0/0.png",
+			"/tiles/0/0/0.mvt?collections=rivers",
+		] {
+			assert_eq!(server.handle(url).status, 404, "{}", url);
+		}
+		Ok(())
+	}
+
+	fn request(address: &str, request: &str) -> Result<String, Box<dyn Error>> {
+		let mut stream = TcpStream::connect(address)?;
+		stream.write_all(request.as_bytes())?;
+		let mut response = String::new();
+		stream.read_to_string(&mut response)?;
+		Ok(response)
+	}
+
+	#[test]
+	fn test_serve() -> Result<(), Box<dyn Error>> {
+		let dir = TempDir::new()?;
+		let server = create_server(&dir)?;
+		// a free port, bound before the requests are sent
+		let listener = tiny_http::Server::http("127.0.0.1:0").map_err(|error| -> Box<dyn Error> { error })?;
+		let address = listener.server_addr().to_ip().ok_or("Not an IP address")?.to_string();
+
+		let requests = [
+			"GET /collections/places/items/1 HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+			"GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+			"POST /items/1 HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\nContent-Length: 0\r\n\r\n",
+			"GET / HTTP/1.1\r\nHost: example.com\r\nX-Forwarded-Proto: https\r\nConnection: close\r\n\r\n",
+		];
+		let responses: Vec<Result<String, Box<dyn Error>>> = thread::scope(|scope| {
+			scope.spawn(|| server.serve_requests(&listener, 2));
+			let responses = requests.iter().map(|text| request(&address, text)).collect();
+			// stop both threads, so that the scope ends
+			listener.unblock();
+			listener.unblock();
+			responses
+		});
+		let responses = responses.into_iter().collect::<Result<Vec<String>, _>>()?;
+
+		assert!(responses[0].starts_with("HTTP/1.1 200"), "{}", responses[0]);
+		assert!(responses[0].contains("Content-Type: application/geo+json"));
+		assert!(responses[0].contains("Stuttgart"));
+		assert!(
+			responses[1].contains("\"href\":\"http://localhost/collections\""),
+			"{}",
+			responses[1]
+		);
+		assert!(responses[2].starts_with("HTTP/1.1 405"), "{}", responses[2]);
+		// links keep the scheme of a proxy
+		assert!(
+			responses[3].contains("\"href\":\"https://example.com/collections\""),
+			"{}",
+			responses[3]
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn test_base_url() -> Result<(), Box<dyn Error>> {
+		let dir = TempDir::new()?;
+		let mut server = create_server(&dir)?;
+		let header = |field: &str, value: &str| Header::from_bytes(field, value).unwrap();
+		let headers = [
+			header("Host", "localhost:8080"),
+			header("X-Forwarded-Proto", "https, http"),
+		];
+		assert_eq!(server.get_base_url(&headers), "https://localhost:8080");
+		assert_eq!(server.get_base_url(&headers[..1]), "http://localhost:8080");
+		assert_eq!(server.get_base_url(&[]), "");
+		server.set_base_url("https://example.com/features/");
+		assert_eq!(server.get_base_url(&headers), "https://example.com/features");
+		Ok(())
+	}
+
+	#[test]
+	fn test_percent_decode() {
+		assert_eq!(percent_decode("name+%3D+%27B%C3%BChl%27"), "name = 'Bühl'");
+		assert_eq!(percent_decode("100%"), "100%");
+		assert_eq!(percent_decode("%zz"), "%zz");
+	}
+}
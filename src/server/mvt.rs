@@ -0,0 +1,582 @@
+use crate::GeoBBox;
+use geojson::{feature::Id, Feature, Geometry, JsonValue, Value};
+use std::{collections::HashMap, f64::consts::PI};
+
+// Size of a tile in tile coordinates
+const EXTENT: u32 = 4096;
+// Features are included up to this far outside of a tile, in tile coordinates, so that lines and
+// polygons continue across tile edges. Geometries are clipped to the tile with this buffer.
+const BUFFER: f64 = 64.;
+const CLIP_MIN: f64 = -BUFFER;
+const CLIP_MAX: f64 = EXTENT as f64 + BUFFER;
+// Web Mercator ends at this latitude
+const MAX_LATITUDE: f64 = 85.051_128_779_806_59;
+
+const GEOM_POINT: u32 = 1;
+const GEOM_LINESTRING: u32 = 2;
+const GEOM_POLYGON: u32 = 3;
+
+const COMMAND_MOVE_TO: u32 = 1;
+const COMMAND_LINE_TO: u32 = 2;
+const COMMAND_CLOSE_PATH: u32 = 7;
+
+// The longitude/latitude bbox of a Web Mercator tile, including the buffer
+pub fn tile_bbox(z: u32, x: u32, y: u32) -> GeoBBox {
+	let size = (1u64 << z) as f64;
+	let buffer = BUFFER / EXTENT as f64;
+	let longitude = |x: f64| x / size * 360. - 180.;
+	let latitude = |y: f64| (PI * (1. - 2. * y / size)).sinh().atan().to_degrees();
+	GeoBBox::from_f64(
+		longitude(x as f64 - buffer),
+		longitude(x as f64 + 1. + buffer),
+		latitude(y as f64 + 1. + buffer),
+		latitude(y as f64 - buffer),
+	)
+}
+
+// A layer of a Mapbox Vector Tile: features with their geometries in tile coordinates, and tags referencing
+// the shared keys and values of their properties
+pub struct Layer {
+	name: String,
+	z: u32,
+	x: u32,
+	y: u32,
+	features: Vec<Vec<u8>>,
+	keys: Vec<String>,
+	key_index: HashMap<String, u32>,
+	values: Vec<Vec<u8>>,
+	value_index: HashMap<Vec<u8>, u32>,
+}
+
+impl Layer {
+	pub fn new(name: &str, z: u32, x: u32, y: u32) -> Self {
+		Layer {
+			name: name.to_string(),
+			z,
+			x,
+			y,
+			features: Vec::new(),
+			keys: Vec::new(),
+			key_index: HashMap::new(),
+			values: Vec::new(),
+			value_index: HashMap::new(),
+		}
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.features.is_empty()
+	}
+
+	// Add a feature. Geometry collections become one feature per geometry type, empty geometries are skipped.
+	pub fn add_feature(&mut self, feature: &Feature) {
+		let geometry = match &feature.geometry {
+			Some(geometry) => geometry,
+			None => return,
+		};
+		let mut parts: [Vec<Vec<(i64, i64)>>; 3] = [Vec::new(), Vec::new(), Vec::new()];
+		self.collect_parts(geometry, &mut parts);
+		let mut encoded: Vec<(u32, Vec<u32>)> = Vec::new();
+		if !parts[0].is_empty() {
+			encoded.push((GEOM_POINT, encode_points(&parts[0].concat())));
+		}
+		if !parts[1].is_empty() {
+			encoded.push((GEOM_LINESTRING, encode_lines(&parts[1])));
+		}
+		if !parts[2].is_empty() {
+			encoded.push((GEOM_POLYGON, encode_rings(&parts[2])));
+		}
+		encoded.retain(|(_, commands)| !commands.is_empty());
+		if encoded.is_empty() {
+			return;
+		}
+
+		let tags = self.get_tags(feature);
+		for (geom_type, commands) in encoded {
+			let mut message: Vec<u8> = Vec::new();
+			if let Some(Id::Number(id)) = &feature.id {
+				if let Some(id) = id.as_u64() {
+					write_key(&mut message, 1, 0);
+					write_varint(&mut message, id);
+				}
+			}
+			write_packed(&mut message, 2, &tags);
+			write_key(&mut message, 3, 0);
+			write_varint(&mut message, geom_type as u64);
+			write_packed(&mut message, 4, &commands);
+			self.features.push(message);
+		}
+	}
+
+	// Append the layer as a field of a tile message. Tiles are the concatenation of their layers.
+	pub fn encode(&self, output: &mut Vec<u8>) {
+		let mut message: Vec<u8> = Vec::new();
+		write_key(&mut message, 15, 0);
+		write_varint(&mut message, 2);
+		write_bytes(&mut message, 1, self.name.as_bytes());
+		for feature in &self.features {
+			write_bytes(&mut message, 2, feature);
+		}
+		for key in &self.keys {
+			write_bytes(&mut message, 3, key.as_bytes());
+		}
+		for value in &self.values {
+			write_bytes(&mut message, 4, value);
+		}
+		write_key(&mut message, 5, 0);
+		write_varint(&mut message, EXTENT as u64);
+		write_bytes(output, 3, &message);
+	}
+
+	// Points of the geometry in tile coordinates, clipped to the tile with its buffer:
+	// [points, lines, polygons as rings with the exterior ring first]
+	fn collect_parts(&self, geometry: &Geometry, parts: &mut [Vec<Vec<(i64, i64)>>; 3]) {
+		let line = |positions: &[Vec<f64>]| -> Vec<(f64, f64)> {
+			positions.iter().map(|position| self.project(position)).collect()
+		};
+		let points = |positions: &[Vec<f64>]| -> Vec<(i64, i64)> {
+			line(positions).into_iter().filter(is_inside).map(round_point).collect()
+		};
+		let lines = |positions: &[Vec<f64>]| clip_line(&line(positions));
+		let polygon = |rings: &[Vec<Vec<f64>>]| -> Vec<Vec<(i64, i64)>> {
+			rings
+				.iter()
+				.map(|ring| clip_ring(line(ring)).into_iter().map(round_point).collect())
+				.collect()
+		};
+		match &geometry.value {
+			Value::Point(position) => parts[0].push(points(std::slice::from_ref(position))),
+			Value::MultiPoint(positions) => parts[0].push(points(positions)),
+			Value::LineString(positions) => parts[1].extend(lines(positions)),
+			Value::MultiLineString(positions) => parts[1].extend(positions.iter().flat_map(|positions| lines(positions))),
+			Value::Polygon(rings) => add_polygon(&mut parts[2], polygon(rings)),
+			Value::MultiPolygon(polygons) => {
+				for rings in polygons {
+					add_polygon(&mut parts[2], polygon(rings));
+				}
+			}
+			Value::GeometryCollection(geometries) => geometries
+				.iter()
+				.for_each(|geometry| self.collect_parts(geometry, parts)),
+		}
+		parts[0].retain(|points| !points.is_empty());
+	}
+
+	// Project longitude and latitude into the coordinates of this tile
+	fn project(&self, position: &[f64]) -> (f64, f64) {
+		let size = (1u64 << self.z) as f64;
+		let longitude = position.first().copied().unwrap_or(0.);
+		let latitude = position
+			.get(1)
+			.copied()
+			.unwrap_or(0.)
+			.clamp(-MAX_LATITUDE, MAX_LATITUDE)
+			.to_radians();
+		let x = (longitude + 180.) / 360. * size;
+		let y = (1. - (latitude.tan() + 1. / latitude.cos()).ln() / PI) / 2. * size;
+		((x - self.x as f64) * EXTENT as f64, (y - self.y as f64) * EXTENT as f64)
+	}
+
+	// Tags of the properties: pairs of key and value indexes. Nested values are stored as JSON strings.
+	fn get_tags(&mut self, feature: &Feature) -> Vec<u32> {
+		let mut tags: Vec<u32> = Vec::new();
+		for (key, value) in feature.properties.iter().flatten() {
+			let mut message: Vec<u8> = Vec::new();
+			match value {
+				JsonValue::Null => continue,
+				JsonValue::String(text) => write_bytes(&mut message, 1, text.as_bytes()),
+				JsonValue::Bool(flag) => {
+					write_key(&mut message, 7, 0);
+					write_varint(&mut message, *flag as u64);
+				}
+				JsonValue::Number(number) => {
+					if let Some(number) = number.as_u64() {
+						write_key(&mut message, 5, 0);
+						write_varint(&mut message, number);
+					} else if let Some(number) = number.as_i64() {
+						write_key(&mut message, 6, 0);
+						write_varint(&mut message, ((number << 1) ^ (number >> 63)) as u64);
+					} else {
+						write_key(&mut message, 3, 1);
+						message.extend_from_slice(&number.as_f64().unwrap_or(0.).to_le_bytes());
+					}
+				}
+				JsonValue::Array(_) | JsonValue::Object(_) => write_bytes(&mut message, 1, value.to_string().as_bytes()),
+			}
+
+			let key_index = match self.key_index.get(key) {
+				Some(index) => *index,
+				None => {
+					let index = self.keys.len() as u32;
+					self.keys.push(key.clone());
+					self.key_index.insert(key.clone(), index);
+					index
+				}
+			};
+			let value_index = match self.value_index.get(&message) {
+				Some(index) => *index,
+				None => {
+					let index = self.values.len() as u32;
+					self.values.push(message.clone());
+					self.value_index.insert(message, index);
+					index
+				}
+			};
+			tags.push(key_index);
+			tags.push(value_index);
+		}
+		tags
+	}
+}
+
+fn is_inside(point: &(f64, f64)) -> bool {
+	(CLIP_MIN..=CLIP_MAX).contains(&point.0) && (CLIP_MIN..=CLIP_MAX).contains(&point.1)
+}
+
+// Round clipped coordinates, which are small enough for the zigzag encoding of commands
+fn round_point(point: (f64, f64)) -> (i64, i64) {
+	(point.0.round() as i64, point.1.round() as i64)
+}
+
+// Split a line into its parts inside the tile with its buffer
+fn clip_line(line: &[(f64, f64)]) -> Vec<Vec<(i64, i64)>> {
+	let mut lines: Vec<Vec<(i64, i64)>> = Vec::new();
+	let mut current: Vec<(i64, i64)> = Vec::new();
+	let mut flush = |current: &mut Vec<(i64, i64)>| {
+		if current.len() >= 2 {
+			lines.push(std::mem::take(current));
+		}
+		current.clear();
+	};
+	for segment in line.windows(2) {
+		let (start, end) = match clip_segment(segment[0], segment[1]) {
+			Some(clipped) => clipped,
+			None => {
+				flush(&mut current);
+				continue;
+			}
+		};
+		// a segment entering the tile starts a new part
+		if start != segment[0] || current.is_empty() {
+			flush(&mut current);
+			current.push(round_point(start));
+		}
+		current.push(round_point(end));
+		if end != segment[1] {
+			flush(&mut current);
+		}
+	}
+	flush(&mut current);
+	lines
+}
+
+// The part of a segment inside the tile with its buffer, by Liang-Barsky
+fn clip_segment(start: (f64, f64), end: (f64, f64)) -> Option<((f64, f64), (f64, f64))> {
+	let (dx, dy) = (end.0 - start.0, end.1 - start.1);
+	let (mut t0, mut t1) = (0f64, 1f64);
+	for (p, q) in [
+		(-dx, start.0 - CLIP_MIN),
+		(dx, CLIP_MAX - start.0),
+		(-dy, start.1 - CLIP_MIN),
+		(dy, CLIP_MAX - start.1),
+	] {
+		if p == 0. {
+			if q < 0. {
+				return None;
+			}
+		} else if p < 0. {
+			t0 = t0.max(q / p);
+		} else {
+			t1 = t1.min(q / p);
+		}
+		if t0 > t1 {
+			return None;
+		}
+	}
+	let point = |t: f64| (start.0 + t * dx, start.1 + t * dy);
+	Some((
+		if t0 > 0. { point(t0) } else { start },
+		if t1 < 1. { point(t1) } else { end },
+	))
+}
+
+// Clip a ring to the tile with its buffer, by Sutherland-Hodgman. Rings outside of it become empty.
+fn clip_ring(mut ring: Vec<(f64, f64)>) -> Vec<(f64, f64)> {
+	let coordinate = |point: &(f64, f64), axis: usize| if axis == 0 { point.0 } else { point.1 };
+	for (axis, bound, is_min) in [
+		(0, CLIP_MIN, true),
+		(0, CLIP_MAX, false),
+		(1, CLIP_MIN, true),
+		(1, CLIP_MAX, false),
+	] {
+		let inside = |point: &(f64, f64)| {
+			let value = coordinate(point, axis);
+			if is_min {
+				value >= bound
+			} else {
+				value <= bound
+			}
+		};
+		let intersect = |a: &(f64, f64), b: &(f64, f64)| {
+			let t = (bound - coordinate(a, axis)) / (coordinate(b, axis) - coordinate(a, axis));
+			(a.0 + t * (b.0 - a.0), a.1 + t * (b.1 - a.1))
+		};
+		let input = std::mem::take(&mut ring);
+		for (i, point) in input.iter().enumerate() {
+			let previous = &input[(i + input.len() - 1) % input.len()];
+			if inside(point) {
+				if !inside(previous) {
+					ring.push(intersect(previous, point));
+				}
+				ring.push(*point);
+			} else if inside(previous) {
+				ring.push(intersect(previous, point));
+			}
+		}
+	}
+	ring
+}
+
+// Add the rings of a polygon, dropping the polygon if its exterior ring collapses in tile coordinates
+fn add_polygon(rings: &mut Vec<Vec<(i64, i64)>>, polygon: Vec<Vec<(i64, i64)>>) {
+	let mut polygon = polygon.into_iter().map(clean_ring);
+	let mut exterior = match polygon.next() {
+		Some(ring) if ring.len() >= 3 && ring_area(&ring) != 0 => ring,
+		_ => return,
+	};
+	// exterior rings have a positive area in tile coordinates, where y points down; interior rings a negative one
+	if ring_area(&exterior) < 0 {
+		exterior.reverse();
+	}
+	rings.push(exterior);
+	for mut interior in polygon.filter(|ring| ring.len() >= 3) {
+		if ring_area(&interior) > 0 {
+			interior.reverse();
+		}
+		rings.push(interior);
+	}
+}
+
+// Remove repeated points and the closing point of a ring
+fn clean_ring(mut ring: Vec<(i64, i64)>) -> Vec<(i64, i64)> {
+	ring.dedup();
+	if ring.len() > 1 && ring.first() == ring.last() {
+		ring.pop();
+	}
+	ring
+}
+
+// Twice the signed area of a ring
+fn ring_area(ring: &[(i64, i64)]) -> i64 {
+	let mut area = 0;
+	for i in 0..ring.len() {
+		let (x1, y1) = ring[i];
+		let (x2, y2) = ring[(i + 1) % ring.len()];
+		area += x1 * y2 - x2 * y1;
+	}
+	area
+}
+
+// Geometry commands with coordinates relative to the cursor, which moves across all parts of a feature
+struct Commands {
+	commands: Vec<u32>,
+	cursor: (i64, i64),
+}
+
+impl Commands {
+	fn new() -> Self {
+		Commands {
+			commands: Vec::new(),
+			cursor: (0, 0),
+		}
+	}
+	fn command(&mut self, id: u32, count: usize) {
+		self.commands.push(id | ((count as u32) << 3));
+	}
+	fn point(&mut self, point: (i64, i64)) {
+		let zigzag = |value: i64| ((value << 1) ^ (value >> 63)) as u32;
+		self.commands.push(zigzag(point.0 - self.cursor.0));
+		self.commands.push(zigzag(point.1 - self.cursor.1));
+		self.cursor = point;
+	}
+}
+
+fn encode_points(points: &[(i64, i64)]) -> Vec<u32> {
+	let mut commands = Commands::new();
+	if !points.is_empty() {
+		commands.command(COMMAND_MOVE_TO, points.len());
+		points.iter().for_each(|point| commands.point(*point));
+	}
+	commands.commands
+}
+
+fn encode_lines(lines: &[Vec<(i64, i64)>]) -> Vec<u32> {
+	let mut commands = Commands::new();
+	for line in lines {
+		let mut line = line.clone();
+		line.dedup();
+		if line.len() < 2 {
+			continue;
+		}
+		commands.command(COMMAND_MOVE_TO, 1);
+		commands.point(line[0]);
+		commands.command(COMMAND_LINE_TO, line.len() - 1);
+		line[1..].iter().for_each(|point| commands.point(*point));
+	}
+	commands.commands
+}
+
+fn encode_rings(rings: &[Vec<(i64, i64)>]) -> Vec<u32> {
+	let mut commands = Commands::new();
+	for ring in rings {
+		commands.command(COMMAND_MOVE_TO, 1);
+		commands.point(ring[0]);
+		commands.command(COMMAND_LINE_TO, ring.len() - 1);
+		ring[1..].iter().for_each(|point| commands.point(*point));
+		commands.command(COMMAND_CLOSE_PATH, 1);
+	}
+	commands.commands
+}
+
+fn write_varint(output: &mut Vec<u8>, mut value: u64) {
+	while value >= 0x80 {
+		output.push((value as u8 & 0x7f) | 0x80);
+		value >>= 7;
+	}
+	output.push(value as u8);
+}
+
+fn write_key(output: &mut Vec<u8>, field: u32, wire_type: u32) {
+	write_varint(output, ((field << 3) | wire_type) as u64);
+}
+
+fn write_bytes(output: &mut Vec<u8>, field: u32, bytes: &[u8]) {
+	write_key(output, field, 2);
+	write_varint(output, bytes.len() as u64);
+	output.extend_from_slice(bytes);
+}
+
+fn write_packed(output: &mut Vec<u8>, field: u32, values: &[u32]) {
+	if values.is_empty() {
+		return;
+	}
+	let mut bytes: Vec<u8> = Vec::new();
+	values.iter().for_each(|value| write_varint(&mut bytes, *value as u64));
+	write_bytes(output, field, &bytes);
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::str::FromStr;
+
+	fn feature(json: &str) -> Feature {
+		Feature::from_str(json).unwrap()
+	}
+
+	#[test]
+	fn test_tile_bbox() {
+		let [x_min, y_min, x_max, y_max] = tile_bbox(1, 1, 0).to_array();
+		// the buffer is 1/64 of the tile
+		assert!((x_min + 2.8125).abs() < 1e-4);
+		assert!((x_max - 182.8125).abs() < 1e-4);
+		assert!(y_min < 0. && y_min > -3.);
+		assert!(y_max > 85.06);
+	}
+
+	#[test]
+	fn test_project() {
+		let layer = Layer::new("test", 0, 0, 0);
+		assert_eq!(round_point(layer.project(&[0., 0.])), (2048, 2048));
+		assert_eq!(round_point(layer.project(&[-180., 90.])), (0, 0));
+		assert_eq!(round_point(Layer::new("test", 1, 1, 1).project(&[90., 0.])), (2048, 0));
+	}
+
+	#[test]
+	fn test_encode_point() {
+		let mut layer = Layer::new("points", 0, 0, 0);
+		assert!(layer.is_empty());
+		layer.add_feature(&feature(
+			r#"{"type":"Feature","id":7,"properties":{"name":"a","n":-1,"x":null},"geometry":{"type":"Point","coordinates":[0,0]}}"#,
+		));
+		assert!(!layer.is_empty());
+		let mut tile: Vec<u8> = Vec::new();
+		layer.encode(&mut tile);
+
+		let mut expected_feature: Vec<u8> = vec![0x08, 7, 0x12, 4, 0, 0, 1, 1, 0x18, 1, 0x22, 5, 9];
+		// zigzag 2048 = 4096 = 0x80 0x20
+		expected_feature.extend_from_slice(&[0x80, 0x20, 0x80, 0x20]);
+		let mut expected_layer: Vec<u8> = vec![0x78, 2];
+		write_bytes(&mut expected_layer, 1, b"points");
+		write_bytes(&mut expected_layer, 2, &expected_feature);
+		// properties are sorted by key
+		write_bytes(&mut expected_layer, 3, b"n");
+		write_bytes(&mut expected_layer, 3, b"name");
+		write_bytes(&mut expected_layer, 4, &[0x30, 1]);
+		write_bytes(&mut expected_layer, 4, &[0x0a, 1, b'a']);
+		expected_layer.extend_from_slice(&[0x28, 0x80, 0x20]);
+		let mut expected: Vec<u8> = Vec::new();
+		write_bytes(&mut expected, 3, &expected_layer);
+		assert_eq!(tile, expected);
+	}
+
+	#[test]
+	fn test_encode_polygon() {
+		let mut layer = Layer::new("polygons", 0, 0, 0);
+		// counterclockwise on the map, so clockwise with y pointing down, which must be reversed
+		let polygon = vec![vec![(0, 0), (0, 10), (10, 10), (10, 0), (0, 0)]];
+		let mut rings: Vec<Vec<(i64, i64)>> = Vec::new();
+		add_polygon(&mut rings, polygon);
+		assert_eq!(rings, vec![vec![(10, 0), (10, 10), (0, 10), (0, 0)]]);
+		assert!(ring_area(&rings[0]) > 0);
+		assert_eq!(encode_rings(&rings), vec![9, 20, 0, 26, 0, 20, 19, 0, 0, 19, 15]);
+
+		// collapsed polygons are dropped, collections are split by geometry type
+		layer.add_feature(&feature(
+			r#"{"type":"Feature","properties":{},"geometry":{"type":"Polygon","coordinates":[[[0,0],[0.001,0],[0,0.001],[0,0]]]}}"#,
+		));
+		assert!(layer.is_empty());
+		layer.add_feature(&feature(
+			r#"{"type":"Feature","properties":{},"geometry":{"type":"GeometryCollection","geometries":[{"type":"Point","coordinates":[1,1]},{"type":"LineString","coordinates":[[0,0],[10,10]]}]}}"#,
+		));
+		assert_eq!(layer.features.len(), 2);
+	}
+
+	#[test]
+	fn test_clip() {
+		// lines are split where they leave the tile with its buffer
+		let line = [(-100., 0.), (100., 0.), (100., 5000.), (200., 5000.), (200., 100.)];
+		assert_eq!(
+			clip_line(&line),
+			vec![vec![(-64, 0), (100, 0), (100, 4160)], vec![(200, 4160), (200, 100)]]
+		);
+		assert!(clip_line(&[(-100., -100.), (5000., -100.)]).is_empty());
+
+		// rings are cut at the edges, rings outside vanish
+		let ring = vec![(-100., -100.), (100., -100.), (100., 100.), (-100., 100.)];
+		let clipped: Vec<(i64, i64)> = clip_ring(ring).into_iter().map(round_point).collect();
+		assert_eq!(clipped, vec![(-64, -64), (100, -64), (100, 100), (-64, 100)]);
+		assert!(clip_ring(vec![(5000., 0.), (6000., 0.), (6000., 1000.)]).is_empty());
+	}
+
+	#[test]
+	fn test_encode_deep_zoom() {
+		// at zoom 24, a large polygon spans billions of units around the tile, which must not overflow
+		let mut layer = Layer::new("polygons", 24, 8_600_000, 5_600_000);
+		layer.add_feature(&feature(
+			r#"{"type":"Feature","properties":{},"geometry":{"type":"Polygon","coordinates":[[[-170,-80],[170,-80],[170,80],[-170,80],[-170,-80]]]}}"#,
+		));
+		// lines passing far from the tile are left out
+		layer.add_feature(&feature(
+			r#"{"type":"Feature","properties":{},"geometry":{"type":"LineString","coordinates":[[-170,-80],[170,80]]}}"#,
+		));
+		assert_eq!(layer.features.len(), 1);
+		let mut parts: [Vec<Vec<(i64, i64)>>; 3] = [Vec::new(), Vec::new(), Vec::new()];
+		let geometry = feature(
+			r#"{"type":"Feature","properties":{},"geometry":{"type":"Polygon","coordinates":[[[-170,-80],[170,-80],[170,80],[-170,80],[-170,-80]]]}}"#,
+		)
+		.geometry
+		.unwrap();
+		layer.collect_parts(&geometry, &mut parts);
+		assert_eq!(parts[2], vec![vec![(4160, -64), (4160, 4160), (-64, 4160), (-64, -64)]]);
+	}
+}
@@ -0,0 +1,178 @@
+use crate::{format_time, GeoBBox, TimeRange};
+use serde_json::{json, Value};
+
+// Coordinate reference system of extents: longitude/latitude
+const CRS84: &str = "http://www.opengis.net/def/crs/OGC/1.3/CRS84";
+// Temporal reference system of extents
+const GREGORIAN: &str = "http://www.opengis.net/def/uom/ISO-8601/0/Gregorian";
+const CONFORMANCE_CLASSES: [&str; 3] = [
+	"http://www.opengis.net/spec/ogcapi-features-1/1.0/conf/core",
+	"http://www.opengis.net/spec/ogcapi-features-1/1.0/conf/geojson",
+	"http://www.opengis.net/spec/ogcapi-features-1/1.0/conf/oas30",
+];
+
+pub const JSON: &str = "application/json";
+pub const GEOJSON: &str = "application/geo+json";
+pub const OPENAPI: &str = "application/vnd.oai.openapi+json;version=3.0";
+
+pub fn link(href: &str, rel: &str, media_type: &str, title: &str) -> Value {
+	json!({ "href": href, "rel": rel, "type": media_type, "title": title })
+}
+
+// The landing page at the root of the API
+pub fn landing_page(base_url: &str) -> Value {
+	json!({
+		"title": "geojson_db",
+		"description": "Features of indexed GeoJSON and CSV/TSV files",
+		"links": [
+			link(&format!("{}/", base_url), "self", JSON, "This document"),
+			link(&format!("{}/api", base_url), "service-desc", OPENAPI, "The API definition"),
+			link(&format!("{}/conformance", base_url), "conformance", JSON, "Implemented conformance classes"),
+			link(&format!("{}/collections", base_url), "data", JSON, "The feature collections"),
+		],
+	})
+}
+
+pub fn conformance() -> Value {
+	json!({ "conformsTo": CONFORMANCE_CLASSES })
+}
+
+// The coordinate reference system of an EPSG code, CRS84 for longitude/latitude
+fn crs_uri(epsg_code: u32) -> String {
+	match epsg_code {
+		4326 => CRS84.to_string(),
+		epsg_code => format!("http://www.opengis.net/def/crs/EPSG/0/{}", epsg_code),
+	}
+}
+
+// The description of a collection, with the extent of its index in the coordinates of an EPSG code
+pub fn collection(
+	base_url: &str, name: &str, extent: Option<&GeoBBox>, epsg_code: u32, time: Option<&TimeRange>,
+) -> Value {
+	let url = format!("{}/collections/{}", base_url, percent_encode(name));
+	let mut extent_value = json!({});
+	if let Some(bbox) = extent {
+		let bbox: Vec<f64> = bbox.to_array().iter().map(|&value| shortest_f64(value)).collect();
+		extent_value["spatial"] = json!({ "bbox": [bbox], "crs": crs_uri(epsg_code) });
+	}
+	if let Some(time) = time {
+		extent_value["temporal"] = json!({
+			"interval": [[format_time(time.min), format_time(time.max)]],
+			"trs": GREGORIAN,
+		});
+	}
+	json!({
+		"id": name,
+		"title": name,
+		"itemType": "feature",
+		"extent": extent_value,
+		"links": [
+			link(&url, "self", JSON, "This collection"),
+			link(&format!("{}/items", url), "items", GEOJSON, "The features of this collection"),
+		],
+	})
+}
+
+// An OpenAPI 3.0 definition of the endpoints
+pub fn api_definition(base_url: &str) -> Value {
+	let parameter = |name: &str, location: &str, description: &str, schema: Value| {
+		json!({
+			"name": name,
+			"in": location,
+			"required": location == "path",
+			"description": description,
+			"schema": schema,
+		})
+	};
+	let string = json!({ "type": "string" });
+	let collection_id = parameter("collectionId", "path", "Name of a collection", string.clone());
+	let feature_id = parameter("featureId", "path", "Id of a feature", string.clone());
+	let operation = |summary: &str, parameters: Vec<Value>, response: &str| {
+		json!({
+			"get": {
+				"summary": summary,
+				"parameters": parameters,
+				"responses": { "200": { "description": response } },
+			}
+		})
+	};
+	let limit = json!({
+		"type": "integer",
+		"minimum": 1,
+		"maximum": super::MAX_LIMIT,
+		"default": super::DEFAULT_LIMIT,
+	});
+	json!({
+		"openapi": "3.0.3",
+		"info": { "title": "geojson_db", "version": env!("CARGO_PKG_VERSION") },
+		"servers": [{ "url": format!("{}/", base_url) }],
+		"paths": {
+			"/": operation("Landing page", vec![], "Links to the API definition, conformance and collections"),
+			"/api": operation("This API definition", vec![], "The OpenAPI definition"),
+			"/conformance": operation("Conformance classes", vec![], "The implemented conformance classes"),
+			"/collections": operation("Collections", vec![], "The feature collections"),
+			"/collections/{collectionId}": operation(
+				"A collection",
+				vec![collection_id.clone()],
+				"The description of a collection"
+			),
+			"/collections/{collectionId}/items": operation(
+				"Features of a collection",
+				vec![
+					collection_id.clone(),
+					parameter(
+						"bbox",
+						"query",
+						"Only features in this bbox: x_min,y_min,x_max,y_max",
+						json!({ "type": "array", "minItems": 4, "maxItems": 6, "items": { "type": "number" } })
+					),
+					parameter(
+						"limit",
+						"query",
+						"Maximum number of features",
+						limit
+					),
+					parameter(
+						"offset",
+						"query",
+						"Number of features to skip",
+						json!({ "type": "integer", "minimum": 0, "default": 0 })
+					),
+					parameter(
+						"cursor",
+						"query",
+						"Position in the index to continue from, as given by next links",
+						json!({ "type": "integer", "minimum": 0 })
+					),
+					parameter("datetime", "query", "Only features in this time or interval", string.clone()),
+					parameter("where", "query", "Only features matching a filter, e.g. name = 'Mainz'", string.clone()),
+					parameter("from", "query", "Only features at or after this time", string.clone()),
+					parameter("to", "query", "Only features at or before this time", string),
+				],
+				"A GeoJSON FeatureCollection"
+			),
+			"/collections/{collectionId}/items/{featureId}": operation(
+				"A feature",
+				vec![collection_id, feature_id],
+				"A GeoJSON Feature"
+			),
+		},
+	})
+}
+
+// The shortest decimal f64 of an f32, e.g. 7.71 instead of 7.710000038146973
+fn shortest_f64(value: f32) -> f64 {
+	value.to_string().parse().unwrap_or(value as f64)
+}
+
+// Encode all characters but unreserved ones as %XX
+pub fn percent_encode(text: &str) -> String {
+	let mut encoded = String::with_capacity(text.len());
+	for byte in text.bytes() {
+		match byte {
+			b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => encoded.push(byte as char),
+			_ => encoded.push_str(&format!("%{:02X}", byte)),
+		}
+	}
+	encoded
+}
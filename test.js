@@ -33,7 +33,7 @@ console.log(data.length);
 */
 
 
-//writeFileSync('test.geojson', JSON.stringify({ type: 'FeatureCollection', features: Array.from(file.find(bbox)) }));
+//writeFileSync('test.geojson', file.findAsFeatureCollection(bbox));
 
 
 
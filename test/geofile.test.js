@@ -0,0 +1,139 @@
+"use strict";
+
+import { test } from 'node:test';
+import assert from 'node:assert/strict';
+import { mkdtempSync, writeFileSync } from 'node:fs';
+import { tmpdir } from 'node:os';
+import { join } from 'node:path';
+import Geofile from '../index.js';
+
+const bbox = [0, 0, 10, 10];
+
+// Write a file into a new temporary directory and return its path
+function tempFile(name, content) {
+	const filename = join(mkdtempSync(join(tmpdir(), 'geojson_db-')), name);
+	writeFileSync(filename, content);
+	return filename;
+}
+
+function csvFile() {
+	const filename = tempFile('points.csv', 'name,x,y,land\na,1,1,BW\nb,2,2,BY\nc,3,3,BE\n');
+	return new Geofile(filename, { skipLines: 1, colX: 1, colY: 2, colId: 0 });
+}
+
+function geojsonFile() {
+	const features = [
+		{ type: 'Feature', id: 'mainz', properties: { name: 'Mainz', adresse: { ort: 'Mainz' }, land: 'RP' }, geometry: { type: 'Point', coordinates: [8.27, 50] } },
+		{ type: 'Feature', id: 'trier', properties: { name: 'Trier', land: 'RP' }, geometry: { type: 'Point', coordinates: [6.64, 49.75] } },
+	];
+	const filename = tempFile('places.geojsonl', features.map(feature => JSON.stringify(feature)).join('\n') + '\n');
+	return new Geofile(filename);
+}
+
+const byName = (a, b) => a.properties.name.localeCompare(b.properties.name);
+const names = file => [...file.find(bbox)].map(line => line.split(',')[0]).sort();
+
+test('find yields lines', () => {
+	const file = csvFile();
+	assert.deepEqual([...file.find(bbox)].sort(), ['a,1,1,BW', 'b,2,2,BY', 'c,3,3,BE']);
+	assert.deepEqual([...file.find([20, 20, 30, 30])], []);
+});
+
+test('find with parse yields objects', () => {
+	const features = [...csvFile().find(bbox, { parse: true })].sort(byName);
+	assert.deepEqual(features[0], {
+		type: 'Feature',
+		geometry: { type: 'Point', coordinates: [1, 1] },
+		properties: { name: 'a', land: 'BW' },
+	});
+	assert.equal(features.length, 3);
+
+	const places = [...geojsonFile().find([0, 40, 10, 60], { parse: true })].sort(byName);
+	assert.deepEqual(places[0], {
+		type: 'Feature',
+		id: 'mainz',
+		properties: { name: 'Mainz', adresse: { ort: 'Mainz' }, land: 'RP' },
+		geometry: { type: 'Point', coordinates: [8.27, 50] },
+	});
+});
+
+test('find with parse and properties keeps only these properties', () => {
+	const features = [...csvFile().find(bbox, { parse: true, properties: ['land', 'missing'] })];
+	assert.deepEqual(features.map(feature => feature.properties.land).sort(), ['BE', 'BW', 'BY']);
+	assert.ok(features.every(feature => Object.keys(feature.properties).length === 1));
+
+	const places = [...geojsonFile().find([0, 40, 10, 60], { parse: true, properties: ['name', 'adresse'] })].sort(byName);
+	assert.deepEqual(places.map(place => place.properties), [
+		{ name: 'Mainz', adresse: { ort: 'Mainz' } },
+		{ name: 'Trier' },
+	]);
+	assert.deepEqual(places[1].geometry, { type: 'Point', coordinates: [6.64, 49.75] });
+
+	assert.throws(() => [...csvFile().find(bbox, { properties: ['land'] })], /requires option "parse"/);
+	assert.throws(() => [...csvFile().find(bbox, { parse: true, properties: 'land' })], /must be an Array of strings/);
+});
+
+test('findBinary yields pages of lines', () => {
+	const pages = [...csvFile().findBinary(bbox, { pageSize: 2 })];
+	assert.deepEqual(pages.map(page => page.offsets.length - 1), [2, 1]);
+	const lines = [];
+	for (const { buffer, offsets } of pages) {
+		assert.equal(offsets[offsets.length - 1], buffer.length);
+		for (let i = 0; i + 1 < offsets.length; i++) {
+			lines.push(buffer.subarray(offsets[i], offsets[i + 1] - 1).toString());
+		}
+	}
+	assert.deepEqual(lines.sort(), ['a,1,1,BW', 'b,2,2,BY', 'c,3,3,BE']);
+	assert.deepEqual([...csvFile().findBinary([20, 20, 30, 30])], []);
+	assert.throws(() => [...csvFile().findBinary(bbox, { pageSize: 0 })], /positive integer/);
+});
+
+test('get and getMany look up lines by id', () => {
+	const file = csvFile();
+	assert.equal(file.get('b'), 'b,2,2,BY');
+	assert.equal(file.get('x'), undefined);
+	assert.deepEqual(file.getMany(['c', 'x', 'a']), ['c,3,3,BE', undefined, 'a,1,1,BW']);
+	assert.throws(() => file.getMany('a'), /must be an Array/);
+
+	const places = geojsonFile();
+	assert.equal(JSON.parse(places.get('trier')).properties.name, 'Trier');
+});
+
+test('append adds lines', () => {
+	const file = csvFile();
+	file.append(['d,4,4,HE', 'e,20,20,SH']);
+	assert.deepEqual(names(file), ['a', 'b', 'c', 'd']);
+	assert.equal(file.get('e'), 'e,20,20,SH');
+	assert.throws(() => file.append(['f,x,y,NW']));
+	assert.equal(file.get('f'), undefined);
+	assert.throws(() => file.append('d,4,4,HE'), /must be an Array of strings/);
+});
+
+test('delete and update replace features', () => {
+	const file = csvFile();
+	assert.equal(file.delete('b'), true);
+	assert.equal(file.delete('b'), false);
+	assert.equal(file.get('b'), undefined);
+	assert.deepEqual(names(file), ['a', 'c']);
+
+	file.update('c', 'c,4,4,HE');
+	assert.equal(file.get('c'), 'c,4,4,HE');
+	assert.deepEqual([...file.find([3.5, 3.5, 4.5, 4.5])], ['c,4,4,HE']);
+	assert.deepEqual([...file.find([2.5, 2.5, 3.5, 3.5])], []);
+	assert.throws(() => file.update('a', 'z,5,5,NW'));
+});
+
+test('compact keeps the current features', () => {
+	const file = csvFile();
+	file.append(['d,4,4,HE']);
+	file.delete('a');
+	file.update('b', 'b,5,5,BY');
+	file.compact();
+	assert.deepEqual(names(file), ['b', 'c', 'd']);
+	assert.deepEqual(file.getMany(['a', 'b', 'd']), [undefined, 'b,5,5,BY', 'd,4,4,HE']);
+	assert.deepEqual([...file.find([4.5, 4.5, 5.5, 5.5], { parse: true, properties: [] })], [{
+		type: 'Feature',
+		geometry: { type: 'Point', coordinates: [5, 5] },
+		properties: {},
+	}]);
+});